@@ -0,0 +1,153 @@
+//! `PolicyEngine::evaluate_permission` against a policy shaped like a busy
+//! project's: several roles, inheritance, denies and glob scopes.
+//!
+//! Besides the timings, prints the heap allocations made per call. Scopes and
+//! tool patterns are compiled when the policy is set, so what remains is the
+//! decision itself.
+//!
+//! ```sh
+//! cargo bench --bench policy
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nebula_lib::policy::{NebulaPolicy, PolicyEngine};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts allocations so the per-call figure can be reported
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn policy() -> NebulaPolicy {
+    let permission = |tool: &str, ops: &[&str], scopes: &[&str], deny: bool| {
+        serde_json::json!({
+            "tool_id": tool,
+            "operations": ops,
+            "resource_scope": scopes,
+            "deny": deny
+        })
+    };
+    serde_json::from_value(serde_json::json!({
+        "version": "0",
+        "project_id": "bench",
+        "name": "Bench policy",
+        "description": "",
+        "created_at": "2026-01-01T00:00:00Z",
+        "updated_at": "2026-01-01T00:00:00Z",
+        "agents": {
+            "merge_to_main": {
+                "allowed": false,
+                "allowed_agent_roles": [],
+                "require_approval": false,
+                "approvers": []
+            },
+            "deploy_permissions": {},
+            "max_concurrent_runs": 4
+        },
+        "repositories": {
+            "default_access": "read",
+            "write_scopes": [],
+            "auto_merge_branches": [],
+            "branch_pattern": ""
+        },
+        "deployment": {
+            "environments": {},
+            "progressive_delivery": {
+                "canary_steps": [],
+                "step_interval": 60,
+                "evaluation_metrics": []
+            },
+            "rollback": { "auto_rollback": false, "triggers": [], "rollback_timeout": 300 }
+        },
+        "gates": { "merge_gates": [], "deploy_gates": [] },
+        "data_classification": {
+            "default_classification": "internal",
+            "provider_rules": [],
+            "redaction_patterns": []
+        },
+        "tool_permissions": {
+            "default_permissions": [
+                permission("vault.*", &["read"], &["**"], false),
+                permission("shell", &["*"], &["/etc/**", "~/.ssh/**"], true)
+            ],
+            "role_permissions": {
+                "reader": [
+                    permission("git.*", &["read"], &["**"], false)
+                ],
+                "coder": [
+                    permission("git.*", &["write"], &["src/**/*.rs", "tests/**", "Cargo.toml"], false),
+                    permission("shell", &["execute"], &["cargo *", "npm run *"], false),
+                    permission("git.*", &["write"], &["src/secrets/**"], true)
+                ],
+                "reviewer": [
+                    permission("github.*", &["comment", "approve"], &["pulls/*"], false)
+                ]
+            },
+            "role_inheritance": {
+                "coder": ["reader"],
+                "reviewer": ["reader"]
+            }
+        }
+    }))
+    .expect("bench policy is valid")
+}
+
+fn evaluate_permission(c: &mut Criterion) {
+    let engine = PolicyEngine::new();
+    engine
+        .set_policy("bench", policy(), None, None)
+        .expect("policy installs");
+
+    let cases = [
+        ("allowed", "git.commit", "write", "src/policy/engine.rs"),
+        ("inherited", "git.log", "read", "docs/README.md"),
+        ("denied", "git.commit", "write", "src/secrets/key.rs"),
+        ("unmatched", "deploy", "execute", "production"),
+    ];
+
+    for (name, action, operation, resource) in cases {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        const CALLS: usize = 1000;
+        for _ in 0..CALLS {
+            black_box(engine.evaluate_permission(
+                "bench",
+                &["coder", "reviewer"],
+                action,
+                operation,
+                resource,
+            ));
+        }
+        let per_call = (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / CALLS as f64;
+        println!("evaluate_permission/{}: {:.1} allocations per call", name, per_call);
+
+        c.bench_function(&format!("evaluate_permission/{}", name), |b| {
+            b.iter(|| {
+                engine.evaluate_permission(
+                    black_box("bench"),
+                    black_box(&["coder", "reviewer"]),
+                    black_box(action),
+                    black_box(operation),
+                    black_box(resource),
+                )
+            })
+        });
+    }
+}
+
+criterion_group!(benches, evaluate_permission);
+criterion_main!(benches);
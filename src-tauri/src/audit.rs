@@ -1,15 +1,41 @@
+use crate::chain;
+use crate::emitter::{AuditEmitter, EmissionConfig};
+use crate::event_schema::{EventSchema, UNVALIDATED_KEY};
+use crate::journal::{AuditBackendKind, AuditJournal, JournalRead};
+use crate::payload_query::{MatchMode, PayloadPath};
+use crate::policy::ToolMatcher;
+use crate::signing;
+use crate::subscription::{AuditSubscription, AuditTail, SubscriberSet};
+use crate::sync::{self, MutexExt};
+use ed25519_dalek::SigningKey;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::AppHandle;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, FixedOffset, Local, Utc};
 
-/// Immutable audit log store.
+/// Append-only audit log store.
 /// Records every action from user requests to agent decisions, tool calls,
-/// code changes, tests, deployments, and documentation writes.
+/// code changes, tests, deployments, and documentation writes. Events are
+/// hash-chained in recording order so tampering with the stored log shows
+/// up in `verify_chain`.
 pub struct AuditStore {
     events: Mutex<Vec<AuditEvent>>,
     runs: Mutex<HashMap<String, RunRecord>>,
+    /// Secondary index of run ids per workstream, in creation order
+    workstream_runs: Mutex<HashMap<String, Vec<String>>>,
+    emitter: Arc<AuditEmitter>,
+    journal: Mutex<AuditJournal>,
+    subscribers: Arc<SubscriberSet>,
+    schema: EventSchema,
+    /// Days each project's events are kept; projects not listed keep all
+    retention: Mutex<HashMap<String, u32>>,
+    /// Project and workstream ids that imported events were recorded under,
+    /// mapped to the ids they go by here
+    aliases: Mutex<HashMap<String, String>>,
 }
 
 impl AuditStore {
@@ -17,35 +43,238 @@ impl AuditStore {
         Self {
             events: Mutex::new(Vec::new()),
             runs: Mutex::new(HashMap::new()),
+            workstream_runs: Mutex::new(HashMap::new()),
+            emitter: Arc::new(AuditEmitter::new()),
+            journal: Mutex::new(AuditJournal::new()),
+            subscribers: Arc::new(SubscriberSet::new()),
+            schema: EventSchema::new(),
+            retention: Mutex::new(HashMap::new()),
+            aliases: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Append an event to the immutable log
-    pub fn record_event(&self, event: AuditEvent) {
-        let mut events = self.events.lock().unwrap();
+    /// Stream newly recorded events to the frontend
+    pub fn attach_app_handle(&self, app: AppHandle) {
+        self.emitter.attach(app);
+    }
+
+    pub fn emission_config(&self, project_id: &str) -> EmissionConfig {
+        self.emitter.config(project_id)
+    }
+
+    pub fn configure_emission(&self, project_id: &str, config: EmissionConfig) {
+        self.emitter.configure(project_id, config);
+    }
+
+    /// Open the audit data directory and reload the log if the last selected
+    /// backend was durable
+    pub fn attach_storage(&self, dir: PathBuf) {
+        let (kind, path) = {
+            let mut journal = self.journal.lock_recover();
+            (journal.attach(dir), journal.jsonl_path())
+        };
+
+        if let (AuditBackendKind::Jsonl, Some(path)) = (kind, path) {
+            match AuditJournal::read_events(&path) {
+                Ok(read) => {
+                    if let Some(index) = read.first_broken() {
+                        tracing::error!(
+                            "Audit log {} fails hash chain verification at event {}",
+                            path.display(),
+                            index
+                        );
+                    }
+                    self.load_events(read.events);
+                }
+                Err(e) => tracing::error!("Failed to read audit log {}: {}", path.display(), e),
+            }
+        }
+
+        self.journal.lock_recover().activate(kind);
+    }
+
+    /// Restore a stored log as-is, keeping its hashes, and rebuild its runs
+    fn load_events(&self, loaded: Vec<AuditEvent>) {
+        let mut log = self.events.lock_recover();
+        let mut runs = self.runs.lock_recover();
+        let mut report = ImportReport::default();
+        self.apply_events(&mut log, &mut runs, loaded, &mut report);
+    }
+
+    /// Check the hash chain of the stored log, re-reading the JSONL file when
+    /// that's the active backend. Returns the index of the first broken link.
+    pub fn verify_chain(&self) -> Result<ChainVerification, AuditError> {
+        let path = {
+            let journal = self.journal.lock_recover();
+            match journal.kind() {
+                AuditBackendKind::Jsonl => journal.jsonl_path(),
+                AuditBackendKind::Memory => None,
+            }
+        };
+
+        let read = match path {
+            Some(path) => AuditJournal::read_events(&path)?,
+            None => JournalRead {
+                events: self.events.lock_recover().clone(),
+                unreadable_at: None,
+            },
+        };
+
+        Ok(ChainVerification {
+            events_checked: read.events.len() as u32,
+            first_broken: read.first_broken(),
+        })
+    }
+
+    pub fn backend_kind(&self) -> AuditBackendKind {
+        self.journal.lock_recover().kind()
+    }
+
+    /// Copy the whole log into another backend and make it the active one.
+    /// The target is rewritten from the in-memory log in one atomic step, so
+    /// rerunning after a crash never duplicates events. The written log is
+    /// read back and compared id-by-id before the switch.
+    pub fn migrate_to(&self, target: AuditBackendKind) -> Result<MigrationReport, MigrateError> {
+        let events = self.events.lock_recover();
+        let runs_migrated = self.runs.lock_recover().len() as u32;
+        let mut journal = self.journal.lock_recover();
+        let from = journal.kind();
+
+        if target == AuditBackendKind::Jsonl {
+            let path = journal
+                .jsonl_path()
+                .ok_or(MigrateError::StorageUnavailable)?;
+            AuditJournal::write_events(&path, &events)?;
+
+            let written = AuditJournal::read_events(&path)?;
+            if let Some(i) = written.unreadable_at {
+                return Err(MigrateError::Verification(format!(
+                    "unreadable event at position {}",
+                    i
+                )));
+            }
+            let written = written.events;
+            if written.len() != events.len() {
+                return Err(MigrateError::Verification(format!(
+                    "expected {} events, found {}",
+                    events.len(),
+                    written.len()
+                )));
+            }
+            if let Some((i, _)) = written
+                .iter()
+                .zip(events.iter())
+                .enumerate()
+                .find(|(_, (w, e))| w.id != e.id)
+            {
+                return Err(MigrateError::Verification(format!(
+                    "event {} out of order at position {}",
+                    events[i].id, i
+                )));
+            }
+            if let Some(i) = chain::first_broken_link(&written) {
+                return Err(MigrateError::Verification(format!(
+                    "hash chain broken at position {}",
+                    i
+                )));
+            }
+        }
+
+        journal.save_kind(target)?;
+        journal.activate(target);
+
+        Ok(MigrationReport {
+            from,
+            to: target,
+            events_migrated: events.len() as u32,
+            runs_migrated,
+            verified: true,
+        })
+    }
+
+    /// Send any batched events that haven't gone out yet
+    pub fn flush_emitter(&self) {
+        self.emitter.flush_all();
+    }
+
+    /// Follow a project's events as they're recorded
+    pub fn subscribe(&self, project_id: &str) -> AuditSubscription {
+        self.subscribers.subscribe(project_id)
+    }
+
+    /// Replay a project's existing events, then follow live ones until none
+    /// arrives within `timeout`. Dropping the tail unsubscribes.
+    pub fn tail(&self, project_id: &str, timeout: Duration) -> AuditTail {
+        // Subscribe under the events lock so nothing lands between the
+        // backlog snapshot and the subscription
+        let aliases = self.aliases();
+        let events = self.events.lock_recover();
+        let subscription = self.subscribers.subscribe(project_id);
+        let backlog = events
+            .iter()
+            .filter(|e| current_id(&aliases, &e.project_id) == project_id)
+            .cloned()
+            .collect();
+        drop(events);
+
+        AuditTail::new(backlog, subscription, timeout)
+    }
+
+    /// Hand a newly recorded event to the frontend emitter and subscribers
+    fn publish(&self, event: &AuditEvent) {
+        self.emitter.enqueue(event);
+        self.subscribers.publish(event);
+    }
+
+    /// Append an event to the log. The event is only kept once the active
+    /// backend has durably written it; a failed write is returned, not dropped.
+    /// An event that doesn't match its registered shape is still recorded,
+    /// with the problem noted in its payload.
+    pub fn record_event(&self, mut event: AuditEvent) -> Result<(), AuditError> {
+        if let Err(problem) = self.schema.check(&event) {
+            tracing::warn!(
+                "Recording unvalidated {} event: {}",
+                event.event_type,
+                problem
+            );
+            if let Some(payload) = event.payload.as_object_mut() {
+                payload.insert(UNVALIDATED_KEY.to_string(), serde_json::json!(problem));
+            }
+        }
+
+        let mut events = self.events.lock_recover();
+        chain::link(events.last(), &mut event);
+        self.journal
+            .lock_recover()
+            .append(std::slice::from_ref(&event))?;
 
         // Also add to run record if run exists
-        if let Ok(mut runs) = self.runs.lock() {
+        {
+            let mut runs = self.runs.lock_recover();
             if let Some(run) = runs.get_mut(&event.run_id) {
                 run.events.push(event.clone());
             }
         }
 
+        self.publish(&event);
         events.push(event);
+        Ok(())
     }
 
-    /// Create a new run record
+    /// Create a new run record for the workstream's current phase
     pub fn create_run(
         &self,
         project_id: &str,
         workstream_id: &str,
+        phase: &str,
         user_request: &str,
-    ) -> String {
+    ) -> Result<String, AuditError> {
         let run_id = Uuid::new_v4().to_string();
         let run = RunRecord {
             id: run_id.clone(),
             project_id: project_id.to_string(),
             workstream_id: workstream_id.to_string(),
+            phase: phase.to_string(),
             started_at: Utc::now().to_rfc3339(),
             completed_at: None,
             status: "running".to_string(),
@@ -54,11 +283,8 @@ impl AuditStore {
             summary: None,
         };
 
-        let mut runs = self.runs.lock().unwrap();
-        runs.insert(run_id.clone(), run);
-
         // Record the run start event
-        let event = AuditEvent {
+        let mut event = AuditEvent {
             id: Uuid::new_v4().to_string(),
             timestamp: Utc::now().to_rfc3339(),
             run_id: run_id.clone(),
@@ -74,48 +300,1077 @@ impl AuditStore {
             payload: serde_json::json!({
                 "kind": "run",
                 "status": "started",
+                "phase": phase,
                 "input": user_request
             }),
             parent_event_id: None,
             span_id: None,
             trace_id: None,
+            prev_hash: None,
+            hash: None,
         };
 
-        let mut events = self.events.lock().unwrap();
+        // The run only exists once its start event is durably recorded
+        let mut events = self.events.lock_recover();
+        chain::link(events.last(), &mut event);
+        self.journal
+            .lock_recover()
+            .append(std::slice::from_ref(&event))?;
+
+        {
+            let mut runs = self.runs.lock_recover();
+            runs.insert(run_id.clone(), run);
+        }
+        self.index_run(workstream_id, &run_id);
+
+        self.publish(&event);
         events.push(event);
 
-        run_id
+        Ok(run_id)
+    }
+
+    /// Finish a run with one of `RUN_END_STATUSES`. The matching
+    /// `run.<status>` event is recorded so the completion survives a reload.
+    pub fn complete_run(&self, run_id: &str, status: &str) -> Result<RunRecord, AuditError> {
+        self.end_run(run_id, status, AuditActor::user(), None)
+    }
+
+    /// Stop a run that is still going. The `run.cancelled` event carries the
+    /// reason and who cancelled it; the summary covers the events so far.
+    pub fn cancel_run(
+        &self,
+        run_id: &str,
+        reason: &str,
+        actor: AuditActor,
+    ) -> Result<RunRecord, AuditError> {
+        self.end_run(run_id, "cancelled", actor, Some(reason))
+    }
+
+    /// Open a span on a running run, recording `span.started`. The returned
+    /// span id is passed to `end_span` once the work it brackets is done.
+    pub fn start_span(
+        &self,
+        run_id: &str,
+        name: &str,
+        actor: AuditActor,
+    ) -> Result<String, AuditError> {
+        let (project_id, workstream_id) = {
+            let runs = self.runs.lock_recover();
+            let run = runs
+                .get(run_id)
+                .ok_or_else(|| AuditError::RunNotFound(run_id.to_string()))?;
+            if run.completed_at.is_some() {
+                return Err(AuditError::RunAlreadyCompleted(run_id.to_string()));
+            }
+            (run.project_id.clone(), run.workstream_id.clone())
+        };
+
+        let span_id = Uuid::new_v4().to_string();
+        let mut event = AuditEvent::new(
+            &project_id,
+            &workstream_id,
+            run_id,
+            "span.started",
+            actor,
+            serde_json::json!({ "kind": "span", "name": name }),
+        );
+        event.span_id = Some(span_id.clone());
+        self.record_event(event)?;
+        Ok(span_id)
+    }
+
+    /// Close a span with one of `SPAN_END_STATUSES`, recording `span.ended`
+    /// as a child of its start event with the span's duration.
+    pub fn end_span(
+        &self,
+        span_id: &str,
+        status: &str,
+        actor: AuditActor,
+    ) -> Result<RunSpan, AuditError> {
+        if !SPAN_END_STATUSES.contains(&status) {
+            return Err(AuditError::InvalidStatus(format!(
+                "'{}'; expected one of {}",
+                status,
+                SPAN_END_STATUSES.join(", ")
+            )));
+        }
+
+        let start = {
+            let events = self.events.lock_recover();
+            let span_events = events
+                .iter()
+                .rev()
+                .filter(|e| e.span_id.as_deref() == Some(span_id));
+            let mut start = None;
+            for event in span_events {
+                match event.event_type.as_str() {
+                    "span.ended" => return Err(AuditError::SpanAlreadyEnded(span_id.to_string())),
+                    "span.started" => {
+                        start = Some(event.clone());
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            start.ok_or_else(|| AuditError::SpanNotFound(span_id.to_string()))?
+        };
+
+        let name = start.payload["name"].as_str().unwrap_or_default();
+        let ended_at = Utc::now();
+        let duration_ms = DateTime::parse_from_rfc3339(&start.timestamp)
+            .map(|started| {
+                (ended_at - started.with_timezone(&Utc))
+                    .num_milliseconds()
+                    .max(0) as u64
+            })
+            .unwrap_or(0);
+        let mut event = AuditEvent::new(
+            &start.project_id,
+            &start.workstream_id,
+            &start.run_id,
+            "span.ended",
+            actor,
+            serde_json::json!({
+                "kind": "span",
+                "name": name,
+                "status": status,
+                "started_at": start.timestamp,
+                "duration_ms": duration_ms
+            }),
+        );
+        event.timestamp = ended_at.to_rfc3339();
+        event.span_id = Some(span_id.to_string());
+        event.parent_event_id = Some(start.id.clone());
+        self.record_event(event)?;
+
+        Ok(RunSpan {
+            span_id: span_id.to_string(),
+            name: name.to_string(),
+            started_at: start.timestamp,
+            ended_at: Some(ended_at.to_rfc3339()),
+            status: Some(status.to_string()),
+            duration_ms: Some(duration_ms),
+        })
+    }
+
+    /// The run's spans in start order, open ones without an end
+    pub fn run_spans(&self, run_id: &str) -> Result<Vec<RunSpan>, AuditError> {
+        let run = self
+            .get_run(run_id)
+            .ok_or_else(|| AuditError::RunNotFound(run_id.to_string()))?;
+        Ok(RunSpan::pair(&run.events))
     }
 
-    /// Complete a run
-    pub fn complete_run(&self, run_id: &str, status: &str) {
-        let mut runs = self.runs.lock().unwrap();
-        if let Some(run) = runs.get_mut(run_id) {
-            run.completed_at = Some(Utc::now().to_rfc3339());
-            run.status = status.to_string();
-            run.summary = Some(Self::compute_summary(&run.events));
+    fn end_run(
+        &self,
+        run_id: &str,
+        status: &str,
+        actor: AuditActor,
+        reason: Option<&str>,
+    ) -> Result<RunRecord, AuditError> {
+        if !RUN_END_STATUSES.contains(&status) {
+            return Err(AuditError::InvalidStatus(format!(
+                "'{}'; expected one of {}",
+                status,
+                RUN_END_STATUSES.join(", ")
+            )));
+        }
+
+        let mut events = self.events.lock_recover();
+        let mut runs = self.runs.lock_recover();
+        let run = runs
+            .get_mut(run_id)
+            .ok_or_else(|| AuditError::RunNotFound(run_id.to_string()))?;
+        if run.completed_at.is_some() {
+            return Err(AuditError::RunAlreadyCompleted(run_id.to_string()));
         }
+
+        let mut payload = serde_json::json!({
+            "kind": "run",
+            "status": status,
+            "phase": run.phase
+        });
+        if let Some(reason) = reason {
+            payload["reason"] = serde_json::json!(reason);
+        }
+        let mut event = AuditEvent::new(
+            &run.project_id,
+            &run.workstream_id,
+            run_id,
+            &format!("run.{}", status),
+            actor,
+            payload,
+        );
+        chain::link(events.last(), &mut event);
+        self.journal
+            .lock_recover()
+            .append(std::slice::from_ref(&event))?;
+
+        run.completed_at = Some(event.timestamp.clone());
+        run.status = status.to_string();
+        run.summary = Some(Self::compute_summary(run));
+        let completed = run.with_events(Vec::new());
+        drop(runs);
+
+        self.publish(&event);
+        events.push(event);
+        Ok(completed)
     }
 
     /// Get a run record
     pub fn get_run(&self, run_id: &str) -> Option<RunRecord> {
-        let runs = self.runs.lock().unwrap();
+        let runs = self.runs.lock_recover();
         runs.get(run_id).cloned()
     }
 
+    /// Totals across every run in a project. Finished runs use their stored
+    /// summary, computed once if missing; runs still going are summarized
+    /// from their events so far and left out of the duration figures.
+    pub fn project_stats(&self, project_id: &str) -> ProjectStats {
+        let mut stats = ProjectStats {
+            project_id: project_id.to_string(),
+            ..ProjectStats::default()
+        };
+        let mut durations = Vec::new();
+
+        let aliases = self.aliases();
+        let mut runs = self.runs.lock_recover();
+        for run in runs
+            .values_mut()
+            .filter(|r| current_id(&aliases, &r.project_id) == project_id)
+        {
+            stats.total_runs += 1;
+            *stats.runs_by_status.entry(run.status.clone()).or_default() += 1;
+            if run.completed_at.is_some() {
+                let summary = match &run.summary {
+                    Some(summary) => summary.clone(),
+                    None => {
+                        let summary = Self::compute_summary(run);
+                        run.summary = Some(summary.clone());
+                        summary
+                    }
+                };
+                stats.totals.add(&summary);
+                durations.push(summary.duration);
+            } else {
+                let partial = Self::compute_summary(run);
+                stats.totals.add(&RunSummary {
+                    duration: 0,
+                    ..partial
+                });
+            }
+        }
+        drop(runs);
+
+        durations.sort_unstable();
+        if !durations.is_empty() {
+            let n = durations.len();
+            stats.mean_duration = Some(durations.iter().sum::<u64>() as f64 / n as f64);
+            stats.median_duration = Some(if n % 2 == 0 {
+                (durations[n / 2 - 1] + durations[n / 2]) as f64 / 2.0
+            } else {
+                durations[n / 2] as f64
+            });
+        }
+        stats
+    }
+
+    /// Runs started for a workstream, oldest first, without their event lists
+    pub fn runs_for_workstream(&self, workstream_id: &str) -> Vec<RunRecord> {
+        let run_ids = {
+            let index = self.workstream_runs.lock_recover();
+            index.get(workstream_id).cloned().unwrap_or_default()
+        };
+
+        let runs = self.runs.lock_recover();
+        run_ids
+            .iter()
+            .filter_map(|id| runs.get(id))
+            .map(|run| run.with_events(Vec::new()))
+            .collect()
+    }
+
+    fn index_run(&self, workstream_id: &str, run_id: &str) {
+        let mut index = self.workstream_runs.lock_recover();
+        index
+            .entry(workstream_id.to_string())
+            .or_default()
+            .push(run_id.to_string());
+    }
+
+    /// Get a run record with its event list trimmed.
+    /// Only events whose type starts with `event_type_prefix` are kept, and
+    /// none at all when `include_events` is false.
+    pub fn get_run_filtered(
+        &self,
+        run_id: &str,
+        include_events: bool,
+        event_type_prefix: Option<&str>,
+    ) -> Option<RunRecord> {
+        let runs = self.runs.lock_recover();
+        let run = runs.get(run_id)?;
+
+        let events = if include_events {
+            run.events
+                .iter()
+                .filter(|e| event_type_prefix.is_none_or(|p| e.event_type.starts_with(p)))
+                .cloned()
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Some(run.with_events(events))
+    }
+
+    /// The `run.started` event of a run. Start events live only in the log,
+    /// not in the run's own event list.
+    pub fn run_start_event(&self, run_id: &str) -> Option<AuditEvent> {
+        let events = self.events.lock_recover();
+        events
+            .iter()
+            .find(|e| e.run_id == run_id && e.event_type == "run.started")
+            .cloned()
+    }
+
+    /// Find runs matching every supplied criterion, newest first
+    pub fn search_runs(&self, query: &RunQuery) -> Vec<RunRecord> {
+        let aliases = self.aliases();
+        let runs = self.runs.lock_recover();
+        let mut matches: Vec<RunRecord> = runs
+            .values()
+            .filter(|r| query.matches(r, &aliases))
+            .cloned()
+            .collect();
+
+        matches.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        matches
+    }
+
+    /// Timestamp of the newest event per project, in one pass over the log
+    pub fn last_activity(&self) -> HashMap<String, String> {
+        let aliases = self.aliases();
+        let mut latest: HashMap<String, String> = HashMap::new();
+        for event in self.events.lock_recover().iter() {
+            let project_id = current_id(&aliases, &event.project_id);
+            match latest.get_mut(project_id) {
+                Some(timestamp) if *timestamp >= event.timestamp => {}
+                Some(timestamp) => timestamp.clone_from(&event.timestamp),
+                None => {
+                    latest.insert(project_id.to_string(), event.timestamp.clone());
+                }
+            }
+        }
+        latest
+    }
+
+    /// Latest recorded result per gate id in a project, optionally narrowed
+    /// to one workstream. Read from `gate.passed`/`gate.failed` events naming
+    /// a `gate_id` and from the `gate_results` of `gate.evaluated` events.
+    pub fn gate_results(
+        &self,
+        project_id: &str,
+        workstream_id: Option<&str>,
+    ) -> HashMap<String, GateResult> {
+        let mut results = HashMap::new();
+        let aliases = self.aliases();
+        let events = self.events.lock_recover();
+        let relevant = events.iter().filter(|e| {
+            current_id(&aliases, &e.project_id) == project_id
+                && workstream_id.is_none_or(|ws| current_id(&aliases, &e.workstream_id) == ws)
+        });
+        for event in relevant {
+            let mut record = |gate_id: &str, passed: bool| {
+                results.insert(
+                    gate_id.to_string(),
+                    GateResult {
+                        passed,
+                        evaluated_at: event.timestamp.clone(),
+                        event_id: event.id.clone(),
+                    },
+                );
+            };
+            match event.event_type.as_str() {
+                "gate.passed" | "gate.failed" => {
+                    if let Some(gate_id) = event.payload["gate_id"].as_str() {
+                        record(gate_id, event.event_type == "gate.passed");
+                    }
+                }
+                "gate.evaluated" => {
+                    if let Some(gate_results) = event.payload["gate_results"].as_object() {
+                        for (gate_id, passed) in gate_results {
+                            record(gate_id, passed.as_bool().unwrap_or(false));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        results
+    }
+
+    /// Number of events in the log across all projects
+    pub fn event_count(&self) -> usize {
+        self.events.lock_recover().len()
+    }
+
+    /// Directory the log persists to, if storage is attached
+    pub fn storage_dir(&self) -> Option<PathBuf> {
+        self.journal.lock_recover().dir().map(Path::to_path_buf)
+    }
+
+    /// Locks left poisoned by a panicking holder
+    pub fn poisoned_locks(&self) -> Vec<&'static str> {
+        sync::poisoned([
+            ("events", self.events.is_poisoned()),
+            ("runs", self.runs.is_poisoned()),
+            ("workstream_runs", self.workstream_runs.is_poisoned()),
+            ("journal", self.journal.is_poisoned()),
+            ("retention", self.retention.is_poisoned()),
+            ("aliases", self.aliases.is_poisoned()),
+        ])
+    }
+
+    /// Read events and runs recorded under an old project or workstream id
+    /// as belonging to the new one, e.g. after a project was imported with
+    /// fresh ids. Maps source ids to current ids.
+    pub fn add_aliases(&self, ids: &HashMap<String, String>) {
+        let mut index = self.workstream_runs.lock_recover();
+        for (source, current) in ids {
+            if let Some(run_ids) = index.get(source).cloned() {
+                let runs = index.entry(current.clone()).or_default();
+                for run_id in run_ids {
+                    if !runs.contains(&run_id) {
+                        runs.push(run_id);
+                    }
+                }
+            }
+        }
+        drop(index);
+        self.aliases.lock_recover().extend(ids.clone());
+    }
+
+    fn aliases(&self) -> HashMap<String, String> {
+        self.aliases.lock_recover().clone()
+    }
+
     /// Get all events for a project
     pub fn get_events(&self, project_id: &str, limit: usize) -> Vec<AuditEvent> {
-        let events = self.events.lock().unwrap();
+        let aliases = self.aliases();
+        let events = self.events.lock_recover();
         events
             .iter()
-            .filter(|e| e.project_id == project_id)
+            .filter(|e| current_id(&aliases, &e.project_id) == project_id)
             .rev()
             .take(limit)
             .cloned()
             .collect()
     }
 
-    fn compute_summary(events: &[AuditEvent]) -> RunSummary {
+    /// A project's events matching every constraint in `filter`, newest first.
+    /// `total` counts all matches even when `limit` truncates the list.
+    pub fn query_events(&self, project_id: &str, filter: &EventFilter) -> EventQueryResult {
+        let type_matchers: Vec<ToolMatcher> = filter
+            .event_types
+            .iter()
+            .map(|t| ToolMatcher::new(t))
+            .collect();
+
+        let aliases = self.aliases();
+        let events = self.events.lock_recover();
+        let mut matching: Vec<&AuditEvent> = events
+            .iter()
+            .filter(|e| current_id(&aliases, &e.project_id) == project_id)
+            .filter(|e| {
+                type_matchers.is_empty() || type_matchers.iter().any(|m| m.matches(&e.event_type))
+            })
+            .filter(|e| {
+                filter
+                    .actor_type
+                    .as_deref()
+                    .is_none_or(|a| a == e.actor.actor_type)
+            })
+            .filter(|e| filter.run_id.as_deref().is_none_or(|r| r == e.run_id))
+            .filter(|e| {
+                if filter.from.is_none() && filter.to.is_none() {
+                    return true;
+                }
+                let Ok(ts) = DateTime::parse_from_rfc3339(&e.timestamp) else {
+                    return false;
+                };
+                let ts = ts.with_timezone(&Utc);
+                filter.from.is_none_or(|from| ts >= from) && filter.to.is_none_or(|to| ts <= to)
+            })
+            .collect();
+        matching.reverse();
+
+        let total = matching.len();
+        EventQueryResult {
+            events: matching
+                .into_iter()
+                .take(filter.limit.unwrap_or(usize::MAX))
+                .cloned()
+                .collect(),
+            total,
+        }
+    }
+
+    /// A project's events whose payload has a value at `selector` matching
+    /// `value`, newest first. Events without that path are skipped.
+    /// `event_type` (exact or `prefix.*`) narrows the scan first.
+    pub fn search_payloads(
+        &self,
+        project_id: &str,
+        selector: &str,
+        value: &str,
+        mode: MatchMode,
+        event_type: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<AuditEvent>, AuditError> {
+        let path = PayloadPath::parse(selector).map_err(AuditError::InvalidSelector)?;
+        let type_matcher = event_type.map(ToolMatcher::new);
+
+        let aliases = self.aliases();
+        let events = self.events.lock_recover();
+        Ok(events
+            .iter()
+            .rev()
+            .filter(|e| current_id(&aliases, &e.project_id) == project_id)
+            .filter(|e| {
+                type_matcher
+                    .as_ref()
+                    .is_none_or(|m| m.matches(&e.event_type))
+            })
+            .filter(|e| {
+                path.select(&e.payload)
+                    .into_iter()
+                    .any(|leaf| mode.matches(leaf, value))
+            })
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    /// A page of a project's events, newest first by timestamp with the event
+    /// id breaking ties. `before` is the `next_cursor` of the previous page;
+    /// `next_cursor` is `None` once the oldest event has been returned.
+    pub fn get_events_page(
+        &self,
+        project_id: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<EventPage, AuditError> {
+        let before = before.map(EventCursor::parse).transpose()?;
+
+        let aliases = self.aliases();
+        let events = self.events.lock_recover();
+        let mut matching: Vec<(EventCursor, &AuditEvent)> = events
+            .iter()
+            .filter(|e| current_id(&aliases, &e.project_id) == project_id)
+            .map(|e| (EventCursor::of(e), e))
+            .filter(|(cursor, _)| before.as_ref().is_none_or(|b| cursor < b))
+            .collect();
+        matching.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let has_more = matching.len() > limit;
+        matching.truncate(limit);
+        let next_cursor = if has_more {
+            matching.last().map(|(cursor, _)| cursor.to_string())
+        } else {
+            None
+        };
+
+        Ok(EventPage {
+            events: matching.into_iter().map(|(_, e)| e.clone()).collect(),
+            next_cursor,
+        })
+    }
+
+    /// Import events from an external log or backup.
+    /// Each event's shape is validated; with `verify` the first invalid event
+    /// rejects the whole import, otherwise invalid events are skipped. Event ids
+    /// already present reject the import unless `merge` is set, in which case
+    /// they are skipped. Run records are rebuilt from `run.*` lifecycle events.
+    pub fn import_events(
+        &self,
+        events: Vec<AuditEvent>,
+        verify: bool,
+        merge: bool,
+    ) -> Result<ImportReport, AuditError> {
+        let mut report = ImportReport::default();
+        let mut log = self.events.lock_recover();
+        let mut runs = self.runs.lock_recover();
+
+        let mut known_ids: HashSet<String> = log.iter().map(|e| e.id.clone()).collect();
+        let mut accepted: Vec<AuditEvent> = Vec::new();
+
+        for mut event in events {
+            if let Err(reason) = validate_event_shape(&event) {
+                if verify {
+                    return Err(AuditError::InvalidEvent(format!(
+                        "{}: {}",
+                        event.id, reason
+                    )));
+                }
+                report.skipped_invalid += 1;
+                continue;
+            }
+
+            if !known_ids.insert(event.id.clone()) {
+                if !merge {
+                    return Err(AuditError::DuplicateEvent(event.id));
+                }
+                report.skipped_duplicates += 1;
+                continue;
+            }
+
+            // Imported events join this log's chain; their old links don't apply
+            chain::link(accepted.last().or(log.last()), &mut event);
+            accepted.push(event);
+        }
+
+        self.journal.lock_recover().append(&accepted)?;
+        self.apply_events(&mut log, &mut runs, accepted, &mut report);
+        Ok(report)
+    }
+
+    /// Push events onto the log, rebuilding run records from their
+    /// `run.started` and terminal events
+    fn apply_events(
+        &self,
+        log: &mut Vec<AuditEvent>,
+        runs: &mut HashMap<String, RunRecord>,
+        events: Vec<AuditEvent>,
+        report: &mut ImportReport,
+    ) {
+        for event in events {
+            match event.event_type.as_str() {
+                "run.started" => {
+                    if !runs.contains_key(&event.run_id) {
+                        runs.insert(event.run_id.clone(), RunRecord::from_start_event(&event));
+                        self.index_run(&event.workstream_id, &event.run_id);
+                        report.runs_created += 1;
+                    }
+                }
+                "run.completed" | "run.failed" | "run.cancelled" => {
+                    if let Some(run) = runs.get_mut(&event.run_id) {
+                        run.completed_at = Some(event.timestamp.clone());
+                        run.status = event.event_type["run.".len()..].to_string();
+                    }
+                }
+                _ => {
+                    if let Some(run) = runs.get_mut(&event.run_id) {
+                        run.events.push(event.clone());
+                    }
+                }
+            }
+
+            log.push(event);
+            report.imported += 1;
+        }
+
+        for run in runs.values_mut() {
+            if run.completed_at.is_some() && run.summary.is_none() {
+                run.summary = Some(Self::compute_summary(run));
+            }
+        }
+    }
+
+    /// Collapse events in a project that are exact copies of an earlier one
+    /// (same type, actor, payload, timestamp, run, and parent), keeping the
+    /// first. A tombstone event records which ids were removed. Removing
+    /// events means the chain is re-linked from the start, so the stored log
+    /// is rewritten as a whole and only swapped in once that succeeds.
+    pub fn dedup(&self, project_id: &str, actor: AuditActor) -> Result<DedupReport, AuditError> {
+        let mut events = self.events.lock_recover();
+        let mut seen = HashSet::new();
+        let mut removed_ids = Vec::new();
+        let examined = events.iter().filter(|e| e.project_id == project_id).count();
+
+        let mut kept: Vec<AuditEvent> = Vec::with_capacity(events.len() + 1);
+        for e in events.iter() {
+            if e.project_id != project_id {
+                kept.push(e.clone());
+                continue;
+            }
+            let key = serde_json::to_string(&(
+                &e.event_type,
+                &e.actor,
+                &e.payload,
+                &e.timestamp,
+                &e.run_id,
+                &e.workstream_id,
+                &e.parent_event_id,
+            ))
+            .unwrap_or_default();
+            if seen.insert(key) {
+                kept.push(e.clone());
+            } else {
+                removed_ids.push(e.id.clone());
+            }
+        }
+
+        let mut tombstone = AuditEvent::new(
+            project_id,
+            "",
+            "",
+            "audit.deduplicated",
+            actor,
+            serde_json::json!({
+                "kind": "audit.maintenance",
+                "action": "dedup",
+                "examined": examined,
+                "removed": removed_ids.len(),
+                "removed_event_ids": removed_ids
+            }),
+        );
+
+        {
+            let journal = self.journal.lock_recover();
+            if removed_ids.is_empty() {
+                chain::link(kept.last(), &mut tombstone);
+                journal.append(std::slice::from_ref(&tombstone))?;
+                kept.push(tombstone.clone());
+            } else {
+                kept.push(tombstone.clone());
+                chain::relink(&mut kept);
+                tombstone = kept.last().cloned().unwrap_or(tombstone);
+                journal.rewrite(&kept)?;
+            }
+        }
+        *events = kept;
+
+        if !removed_ids.is_empty() {
+            self.sync_run_events(&events, &removed_ids.iter().cloned().collect());
+        }
+
+        self.publish(&tombstone);
+
+        Ok(DedupReport {
+            examined: examined as u32,
+            removed: removed_ids.len() as u32,
+            removed_event_ids: removed_ids,
+            tombstone_event_id: tombstone.id,
+        })
+    }
+
+    /// Drop removed events from the run records and refresh the chain links
+    /// of the rest, which carry the links of the old chain
+    fn sync_run_events(&self, events: &[AuditEvent], removed: &HashSet<String>) {
+        let links: HashMap<&str, &AuditEvent> = events.iter().map(|e| (e.id.as_str(), e)).collect();
+        let mut runs = self.runs.lock_recover();
+        for run in runs.values_mut() {
+            run.events.retain(|e| !removed.contains(&e.id));
+            for event in &mut run.events {
+                if let Some(linked) = links.get(event.id.as_str()) {
+                    event.prev_hash = linked.prev_hash.clone();
+                    event.hash = linked.hash.clone();
+                }
+            }
+        }
+    }
+
+    /// Keep a project's events for `days`, or forever with `None`
+    pub fn set_retention(&self, project_id: &str, days: Option<u32>) {
+        let mut retention = self.retention.lock_recover();
+        match days {
+            Some(days) => retention.insert(project_id.to_string(), days),
+            None => retention.remove(project_id),
+        };
+    }
+
+    /// Remove events older than their project's retention window, except
+    /// those of runs still in progress. Each project that lost events gets an
+    /// `audit.pruned` event, and the chain is re-linked and the stored log
+    /// rewritten so `verify_chain` still holds. Returns how many events were
+    /// removed.
+    pub fn prune(&self, now: DateTime<Utc>) -> Result<u32, AuditError> {
+        let cutoffs: HashMap<String, DateTime<Utc>> = self
+            .retention
+            .lock_recover()
+            .iter()
+            .map(|(project, days)| {
+                let window = chrono::Duration::days((*days).into());
+                (project.clone(), now - window)
+            })
+            .collect();
+        if cutoffs.is_empty() {
+            return Ok(0);
+        }
+
+        let mut events = self.events.lock_recover();
+        let running: HashSet<String> = self
+            .runs
+            .lock_recover()
+            .values()
+            .filter(|r| r.status == "running")
+            .map(|r| r.id.clone())
+            .collect();
+
+        let mut removed: HashSet<String> = HashSet::new();
+        let mut per_project: BTreeMap<String, u32> = BTreeMap::new();
+        for e in events.iter() {
+            let expired = cutoffs.get(&e.project_id).is_some_and(|cutoff| {
+                !running.contains(&e.run_id)
+                    && DateTime::parse_from_rfc3339(&e.timestamp).is_ok_and(|t| t < *cutoff)
+            });
+            if expired {
+                removed.insert(e.id.clone());
+                *per_project.entry(e.project_id.clone()).or_default() += 1;
+            }
+        }
+        if removed.is_empty() {
+            return Ok(0);
+        }
+
+        let tombstones: Vec<AuditEvent> = per_project
+            .iter()
+            .map(|(project_id, count)| {
+                AuditEvent::new(
+                    project_id,
+                    "",
+                    "",
+                    "audit.pruned",
+                    AuditActor::system(),
+                    serde_json::json!({
+                        "kind": "audit.maintenance",
+                        "action": "prune",
+                        "removed": count,
+                        "before": cutoffs[project_id].to_rfc3339()
+                    }),
+                )
+            })
+            .collect();
+        let mut kept: Vec<AuditEvent> = events
+            .iter()
+            .filter(|e| !removed.contains(&e.id))
+            .cloned()
+            .chain(tombstones.iter().cloned())
+            .collect();
+        chain::relink(&mut kept);
+        self.journal.lock_recover().rewrite(&kept)?;
+
+        *events = kept;
+        self.sync_run_events(&events, &removed);
+        let tombstones: Vec<AuditEvent> = events[events.len() - tombstones.len()..].to_vec();
+        drop(events);
+
+        for tombstone in &tombstones {
+            self.publish(tombstone);
+        }
+        Ok(removed.len() as u32)
+    }
+
+    /// Count a project's events by `event_type`, optionally within
+    /// `[start, end)` and under an event-type prefix such as `tool.*`
+    pub fn event_type_histogram(
+        &self,
+        project_id: &str,
+        time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        event_type_prefix: Option<&str>,
+    ) -> HashMap<String, u32> {
+        let prefix = event_type_prefix.map(|p| p.trim_end_matches('*'));
+        let aliases = self.aliases();
+        let events = self.events.lock_recover();
+
+        let mut histogram = HashMap::new();
+        for event in events
+            .iter()
+            .filter(|e| current_id(&aliases, &e.project_id) == project_id)
+        {
+            if prefix.is_some_and(|p| !event.event_type.starts_with(p)) {
+                continue;
+            }
+            if let Some((start, end)) = time_range {
+                let Ok(ts) = DateTime::parse_from_rfc3339(&event.timestamp) else {
+                    continue;
+                };
+                let ts = ts.with_timezone(&Utc);
+                if ts < start || ts >= end {
+                    continue;
+                }
+            }
+            *histogram.entry(event.event_type.clone()).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Render a run in an export format: every event recorded for it, oldest
+    /// first, as JSON lines, or the `export_run` report as Markdown
+    pub fn render_export(&self, run_id: &str, format: ExportFormat) -> Result<String, AuditError> {
+        match format {
+            ExportFormat::Jsonl => {
+                let mut events: Vec<AuditEvent> = {
+                    let events = self.events.lock_recover();
+                    events
+                        .iter()
+                        .filter(|e| e.run_id == run_id)
+                        .cloned()
+                        .collect()
+                };
+                if events.is_empty() {
+                    return Err(AuditError::RunNotFound(run_id.to_string()));
+                }
+                events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+                let mut out = String::new();
+                for event in &events {
+                    out.push_str(&serde_json::to_string(event)?);
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+            ExportFormat::Markdown => {
+                Ok(self.export_run(run_id)?.render(ReportFormat::Markdown)?)
+            }
+        }
+    }
+
+    /// Render a run export and sign its bytes so it can be verified outside the app
+    pub fn export_signed(
+        &self,
+        run_id: &str,
+        format: ExportFormat,
+        signing_key: &SigningKey,
+    ) -> Result<SignedExport, AuditError> {
+        let content = self.render_export(run_id, format)?;
+        Ok(SignedExport {
+            run_id: run_id.to_string(),
+            format,
+            signature: signing::sign(signing_key, content.as_bytes()),
+            public_key: hex::encode(signing_key.verifying_key().to_bytes()),
+            signed_at: Utc::now().to_rfc3339(),
+            content,
+        })
+    }
+
+    /// The run's events as a forest built from `parent_event_id`, children in
+    /// timestamp order. Events whose parent isn't in the run become roots, and
+    /// a cycle is broken at its earliest event so every event appears once.
+    pub fn event_tree(&self, run_id: &str) -> Result<Vec<EventNode>, AuditError> {
+        let mut events = self
+            .get_run(run_id)
+            .ok_or_else(|| AuditError::RunNotFound(run_id.to_string()))?
+            .events;
+        events.sort_by_key(EventCursor::of);
+
+        let index: HashMap<&str, usize> = events
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.id.as_str(), i))
+            .collect();
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); events.len()];
+        let mut roots = Vec::new();
+        for (i, event) in events.iter().enumerate() {
+            match event.parent_event_id.as_deref().and_then(|p| index.get(p)) {
+                Some(&parent) if parent != i => children[parent].push(i),
+                _ => roots.push(i),
+            }
+        }
+
+        fn build(
+            i: usize,
+            events: &[AuditEvent],
+            children: &[Vec<usize>],
+            visited: &mut [bool],
+        ) -> EventNode {
+            visited[i] = true;
+            let mut node = EventNode {
+                event: events[i].clone(),
+                children: Vec::new(),
+            };
+            for &child in &children[i] {
+                if !visited[child] {
+                    node.children.push(build(child, events, children, visited));
+                }
+            }
+            node
+        }
+
+        let mut visited = vec![false; events.len()];
+        let mut forest: Vec<EventNode> = roots
+            .into_iter()
+            .map(|i| build(i, &events, &children, &mut visited))
+            .collect();
+        // Whatever is left only hangs off a cycle
+        while let Some(i) = visited.iter().position(|v| !v) {
+            forest.push(build(i, &events, &children, &mut visited));
+        }
+        forest.sort_by(|a, b| EventCursor::of(&a.event).cmp(&EventCursor::of(&b.event)));
+
+        Ok(forest)
+    }
+
+    /// Everything recorded for a run in one structure: the run record, its
+    /// events in timestamp order, its summary (computed now if the run is
+    /// still going), and whether the log's hash chain is intact
+    pub fn export_run(&self, run_id: &str) -> Result<RunExport, AuditError> {
+        let run = self
+            .get_run(run_id)
+            .ok_or_else(|| AuditError::RunNotFound(run_id.to_string()))?;
+
+        let mut events = run.events.clone();
+        events.sort_by_key(EventCursor::of);
+        let summary = run
+            .summary
+            .clone()
+            .unwrap_or_else(|| Self::compute_summary(&run));
+
+        Ok(RunExport {
+            run: run.with_events(Vec::new()),
+            spans: RunSpan::pair(&events),
+            events,
+            summary,
+            chain: self.verify_chain()?,
+            exported_at: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Reformat a stored RFC3339 timestamp for presentation.
+    /// `tz` may be `"UTC"` (the default), `"local"`, a fixed offset such as
+    /// `"+05:30"`, or an IANA zone name like `"Europe/Berlin"`. `fmt` is a
+    /// strftime pattern; RFC3339 is used when it is omitted.
+    pub fn format_timestamp(
+        ts: &str,
+        tz: Option<&str>,
+        fmt: Option<&str>,
+    ) -> Result<String, String> {
+        let parsed = DateTime::parse_from_rfc3339(ts)
+            .map_err(|e| format!("Invalid timestamp {}: {}", ts, e))?;
+
+        match tz.unwrap_or("UTC") {
+            "UTC" | "utc" | "Z" => render_timestamp(parsed.with_timezone(&Utc), fmt),
+            "local" | "Local" => render_timestamp(parsed.with_timezone(&Local), fmt),
+            zone if zone.starts_with('+') || zone.starts_with('-') => {
+                let offset = zone
+                    .parse::<FixedOffset>()
+                    .map_err(|_| format!("Invalid UTC offset: {}", zone))?;
+                render_timestamp(parsed.with_timezone(&offset), fmt)
+            }
+            zone => {
+                let named = zone
+                    .parse::<chrono_tz::Tz>()
+                    .map_err(|_| format!("Unknown timezone: {}", zone))?;
+                render_timestamp(parsed.with_timezone(&named), fmt)
+            }
+        }
+    }
+
+    /// Whole seconds between a run's start and completion, 0 if either is
+    /// missing or unparseable
+    fn run_duration(run: &RunRecord) -> u64 {
+        let parse = |ts: &str| DateTime::parse_from_rfc3339(ts).ok();
+        let (Some(started), Some(completed)) = (
+            parse(&run.started_at),
+            run.completed_at.as_deref().and_then(parse),
+        ) else {
+            return 0;
+        };
+        (completed - started).num_seconds().max(0) as u64
+    }
+
+    fn compute_summary(run: &RunRecord) -> RunSummary {
+        let events = &run.events;
         let mut summary = RunSummary {
             total_events: events.len() as u32,
             agent_decisions: 0,
@@ -129,7 +1384,11 @@ impl AuditStore {
             deployments_completed: 0,
             documentation_updates: 0,
             linear_updates: 0,
-            duration: 0,
+            open_spans: RunSpan::pair(events)
+                .iter()
+                .filter(|span| span.ended_at.is_none())
+                .count() as u32,
+            duration: Self::run_duration(run),
         };
 
         for event in events {
@@ -153,6 +1412,42 @@ impl AuditStore {
     }
 }
 
+fn validate_event_shape(event: &AuditEvent) -> Result<(), String> {
+    if event.id.is_empty() {
+        return Err("missing id".to_string());
+    }
+    if event.project_id.is_empty() {
+        return Err("missing project_id".to_string());
+    }
+    if event.event_type.is_empty() {
+        return Err("missing event_type".to_string());
+    }
+    if event.event_type.starts_with("run.") && event.run_id.is_empty() {
+        return Err(format!("{} event without run_id", event.event_type));
+    }
+    DateTime::parse_from_rfc3339(&event.timestamp)
+        .map_err(|e| format!("invalid timestamp {}: {}", event.timestamp, e))?;
+    Ok(())
+}
+
+fn render_timestamp<Tz>(dt: DateTime<Tz>, fmt: Option<&str>) -> Result<String, String>
+where
+    Tz: chrono::TimeZone,
+    Tz::Offset: std::fmt::Display,
+{
+    use std::fmt::Write;
+
+    match fmt {
+        None => Ok(dt.to_rfc3339()),
+        Some(fmt) => {
+            let mut out = String::new();
+            write!(out, "{}", dt.format(fmt))
+                .map_err(|_| format!("Invalid timestamp format: {}", fmt))?;
+            Ok(out)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditEvent {
     pub id: String,
@@ -166,6 +1461,40 @@ pub struct AuditEvent {
     pub parent_event_id: Option<String>,
     pub span_id: Option<String>,
     pub trace_id: Option<String>,
+    /// Hash of the event recorded before this one; `None` for the first event
+    #[serde(default)]
+    pub prev_hash: Option<String>,
+    /// SHA-256 over this event's fields and `prev_hash`, set when recorded
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+impl AuditEvent {
+    /// Build a top-level event stamped with a fresh id and the current time
+    pub fn new(
+        project_id: &str,
+        workstream_id: &str,
+        run_id: &str,
+        event_type: &str,
+        actor: AuditActor,
+        payload: serde_json::Value,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            run_id: run_id.to_string(),
+            workstream_id: workstream_id.to_string(),
+            project_id: project_id.to_string(),
+            event_type: event_type.to_string(),
+            actor,
+            payload,
+            parent_event_id: None,
+            span_id: None,
+            trace_id: None,
+            prev_hash: None,
+            hash: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -176,11 +1505,64 @@ pub struct AuditActor {
     pub name: String,
 }
 
+impl AuditActor {
+    pub fn user() -> Self {
+        Self {
+            actor_type: "user".to_string(),
+            id: "user".to_string(),
+            role: None,
+            name: "User".to_string(),
+        }
+    }
+
+    /// A specific agent instance, when its role isn't known
+    pub fn agent(id: &str, name: &str) -> Self {
+        Self {
+            actor_type: "agent".to_string(),
+            id: id.to_string(),
+            role: None,
+            name: name.to_string(),
+        }
+    }
+
+    /// Nebula itself, for scheduled maintenance nobody requested
+    pub fn system() -> Self {
+        Self {
+            actor_type: "system".to_string(),
+            id: "nebula".to_string(),
+            role: None,
+            name: "Nebula".to_string(),
+        }
+    }
+
+    /// An agent identified only by its role
+    pub fn agent_role(role: &str) -> Self {
+        Self {
+            actor_type: "agent".to_string(),
+            id: role.to_string(),
+            role: Some(role.to_string()),
+            name: role.to_string(),
+        }
+    }
+}
+
+/// How often events past their retention window are pruned
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Statuses a run can finish with; each has a matching `run.<status>` event
+pub const RUN_END_STATUSES: [&str; 3] = ["completed", "failed", "cancelled"];
+
+/// Statuses a span can end with
+pub const SPAN_END_STATUSES: [&str; 3] = ["ok", "error", "cancelled"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunRecord {
     pub id: String,
     pub project_id: String,
     pub workstream_id: String,
+    /// Workstream phase the run was started in
+    #[serde(default = "unknown_phase")]
+    pub phase: String,
     pub started_at: String,
     pub completed_at: Option<String>,
     pub status: String,
@@ -189,7 +1571,441 @@ pub struct RunRecord {
     pub summary: Option<RunSummary>,
 }
 
+fn unknown_phase() -> String {
+    "unknown".to_string()
+}
+
+impl RunRecord {
+    /// Copy of the run metadata carrying only the given events
+    fn with_events(&self, events: Vec<AuditEvent>) -> Self {
+        Self {
+            id: self.id.clone(),
+            project_id: self.project_id.clone(),
+            workstream_id: self.workstream_id.clone(),
+            phase: self.phase.clone(),
+            started_at: self.started_at.clone(),
+            completed_at: self.completed_at.clone(),
+            status: self.status.clone(),
+            user_request: self.user_request.clone(),
+            events,
+            summary: self.summary.clone(),
+        }
+    }
+
+    /// Rebuild a run shell from its `run.started` event
+    fn from_start_event(event: &AuditEvent) -> Self {
+        Self {
+            id: event.run_id.clone(),
+            project_id: event.project_id.clone(),
+            workstream_id: event.workstream_id.clone(),
+            phase: event.payload["phase"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(unknown_phase),
+            started_at: event.timestamp.clone(),
+            completed_at: None,
+            status: "running".to_string(),
+            user_request: event.payload["input"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            events: Vec::new(),
+            summary: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunQuery {
+    pub project_id: Option<String>,
+    pub workstream_id: Option<String>,
+    pub phase: Option<String>,
+    pub status: Option<String>,
+}
+
+impl RunQuery {
+    fn matches(&self, run: &RunRecord, aliases: &HashMap<String, String>) -> bool {
+        fn field_matches(wanted: &Option<String>, actual: &str) -> bool {
+            wanted.as_deref().is_none_or(|w| w == actual)
+        }
+
+        field_matches(&self.project_id, current_id(aliases, &run.project_id))
+            && field_matches(&self.workstream_id, current_id(aliases, &run.workstream_id))
+            && field_matches(&self.phase, &run.phase)
+            && field_matches(&self.status, &run.status)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Jsonl,
+    Markdown,
+}
+
+/// A stretch of work within a run, bracketed by `span.started` and
+/// `span.ended` events sharing a `span_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSpan {
+    pub span_id: String,
+    pub name: String,
+    pub started_at: String,
+    /// `None` while the span is still open
+    pub ended_at: Option<String>,
+    pub status: Option<String>,
+    pub duration_ms: Option<u64>,
+}
+
+impl RunSpan {
+    /// Pair up the span events among `events`, in start order
+    fn pair(events: &[AuditEvent]) -> Vec<RunSpan> {
+        let mut spans: Vec<RunSpan> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for event in events {
+            let Some(span_id) = event.span_id.as_deref() else {
+                continue;
+            };
+            match event.event_type.as_str() {
+                "span.started" => {
+                    index.insert(span_id, spans.len());
+                    spans.push(RunSpan {
+                        span_id: span_id.to_string(),
+                        name: event.payload["name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        started_at: event.timestamp.clone(),
+                        ended_at: None,
+                        status: None,
+                        duration_ms: None,
+                    });
+                }
+                "span.ended" => {
+                    if let Some(span) = index.get(span_id).map(|&i| &mut spans[i]) {
+                        span.ended_at = Some(event.timestamp.clone());
+                        span.status = event.payload["status"].as_str().map(str::to_string);
+                        span.duration_ms = event.payload["duration_ms"].as_u64();
+                    }
+                }
+                _ => {}
+            }
+        }
+        spans
+    }
+}
+
+/// A gate's most recent outcome in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateResult {
+    pub passed: bool,
+    pub evaluated_at: String,
+    pub event_id: String,
+}
+
+/// An event and the events that name it as their parent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventNode {
+    pub event: AuditEvent,
+    pub children: Vec<EventNode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Json,
+    Markdown,
+}
+
+/// A run's complete audit trail, self-contained for handing to an auditor.
+/// `run.events` is left empty; the ordered events are in `events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunExport {
+    pub run: RunRecord,
+    pub events: Vec<AuditEvent>,
+    pub spans: Vec<RunSpan>,
+    pub summary: RunSummary,
+    pub chain: ChainVerification,
+    pub exported_at: String,
+}
+
+impl RunExport {
+    pub fn render(&self, format: ReportFormat) -> Result<String, serde_json::Error> {
+        match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self),
+            ReportFormat::Markdown => Ok(self.to_markdown()),
+        }
+    }
+
+    /// Header, summary table, chain status, and a timeline of events grouped
+    /// by the phase the run was in. Phase changes are read from the `phase`
+    /// or `to_phase` payload fields.
+    fn to_markdown(&self) -> String {
+        use std::fmt::Write;
+
+        let run = &self.run;
+        let mut out = String::new();
+        let _ = writeln!(out, "# Run {}\n", run.id);
+        let _ = writeln!(out, "- **Project:** {}", run.project_id);
+        let _ = writeln!(out, "- **Workstream:** {}", run.workstream_id);
+        let _ = writeln!(out, "- **Status:** {}", run.status);
+        let _ = writeln!(out, "- **Started:** {}", run.started_at);
+        let _ = writeln!(
+            out,
+            "- **Completed:** {}",
+            run.completed_at.as_deref().unwrap_or("—")
+        );
+        let _ = writeln!(out, "- **Request:** {}", run.user_request);
+        let _ = writeln!(out, "- **Exported:** {}\n", self.exported_at);
+
+        let s = &self.summary;
+        out.push_str("## Summary\n\n| Metric | Value |\n|--------|-------|\n");
+        for (label, value) in [
+            ("Events", s.total_events as u64),
+            ("Agent decisions", s.agent_decisions as u64),
+            ("Tool calls", s.tool_calls as u64),
+            ("Code changes", s.code_changes as u64),
+            ("Tests run", s.tests_run as u64),
+            ("Tests passed", s.tests_passed as u64),
+            ("Tests failed", s.tests_failed as u64),
+            ("Gates passed", s.gates_passed as u64),
+            ("Gates failed", s.gates_failed as u64),
+            ("Deployments", s.deployments_completed as u64),
+            ("Documentation updates", s.documentation_updates as u64),
+            ("Linear updates", s.linear_updates as u64),
+            ("Duration (s)", s.duration),
+        ] {
+            let _ = writeln!(out, "| {} | {} |", label, value);
+        }
+
+        out.push_str("\n## Hash chain\n\n");
+        match self.chain.first_broken {
+            None => {
+                let _ = writeln!(out, "Intact ({} events checked)", self.chain.events_checked);
+            }
+            Some(index) => {
+                let _ = writeln!(
+                    out,
+                    "**Broken** at event {} of {}",
+                    index + 1,
+                    self.chain.events_checked
+                );
+            }
+        }
+
+        if !self.spans.is_empty() {
+            out.push_str("\n## Spans\n\n| Span | Started | Duration (ms) | Status |\n");
+            out.push_str("|------|---------|---------------|--------|\n");
+            for span in &self.spans {
+                let duration = span
+                    .duration_ms
+                    .map_or_else(|| "—".to_string(), |ms| ms.to_string());
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} |",
+                    span.name,
+                    span.started_at,
+                    duration,
+                    span.status.as_deref().unwrap_or("open")
+                );
+            }
+        }
+
+        out.push_str("\n## Timeline\n");
+        let mut phase: Option<&str> = None;
+        for event in &self.events {
+            let event_phase = event.payload["to_phase"]
+                .as_str()
+                .or_else(|| event.payload["phase"].as_str())
+                .or(phase)
+                .unwrap_or(&run.phase);
+            if phase != Some(event_phase) {
+                let _ = writeln!(out, "\n### {}\n", event_phase);
+                phase = Some(event_phase);
+            }
+            let _ = writeln!(
+                out,
+                "- `{}` **{}** — {} ({})",
+                event.timestamp, event.event_type, event.actor.name, event.actor.actor_type
+            );
+        }
+
+        out
+    }
+}
+
+/// An export plus a detached Ed25519 signature over its UTF-8 bytes
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedExport {
+    pub run_id: String,
+    pub format: ExportFormat,
+    pub content: String,
+    pub signature: String,
+    pub public_key: String,
+    pub signed_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupReport {
+    pub examined: u32,
+    pub removed: u32,
+    pub removed_event_ids: Vec<String>,
+    pub tombstone_event_id: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("Invalid event {0}")]
+    InvalidEvent(String),
+    #[error("Duplicate event id: {0}")]
+    DuplicateEvent(String),
+    #[error("Failed to persist audit log: {0}")]
+    Persist(#[from] std::io::Error),
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+    #[error("Invalid payload selector: {0}")]
+    InvalidSelector(String),
+    #[error("Run not found: {0}")]
+    RunNotFound(String),
+    #[error("Run already completed: {0}")]
+    RunAlreadyCompleted(String),
+    #[error("Invalid run status {0}")]
+    InvalidStatus(String),
+    #[error("Span not found: {0}")]
+    SpanNotFound(String),
+    #[error("Span already ended: {0}")]
+    SpanAlreadyEnded(String),
+    #[error("Failed to serialize audit event: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+impl Serialize for AuditError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainVerification {
+    pub events_checked: u32,
+    /// Index of the first event whose hash link doesn't verify, or where an
+    /// unreadable line was skipped
+    pub first_broken: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from: AuditBackendKind,
+    pub to: AuditBackendKind,
+    pub events_migrated: u32,
+    pub runs_migrated: u32,
+    pub verified: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    #[error("No audit data directory is available for a durable backend")]
+    StorageUnavailable,
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Migration verification failed: {0}")]
+    Verification(String),
+}
+
+impl Serialize for MigrateError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// The id a project or workstream goes by now, for one a record was stored under
+fn current_id<'a>(aliases: &'a HashMap<String, String>, id: &'a str) -> &'a str {
+    aliases.get(id).map_or(id, String::as_str)
+}
+
+/// Constraints for `query_events`; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventFilter {
+    /// Exact types or `prefix.*`, as in tool permissions; any may match
+    pub event_types: Vec<String>,
+    pub actor_type: Option<String>,
+    pub run_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventQueryResult {
+    pub events: Vec<AuditEvent>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPage {
+    pub events: Vec<AuditEvent>,
+    pub next_cursor: Option<String>,
+}
+
+/// Position in timestamp order, rendered as `<rfc3339>|<event id>`. Events
+/// with unparseable timestamps sort before everything else.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct EventCursor {
+    timestamp: Option<DateTime<Utc>>,
+    id: String,
+}
+
+impl EventCursor {
+    fn of(event: &AuditEvent) -> Self {
+        Self {
+            timestamp: DateTime::parse_from_rfc3339(&event.timestamp)
+                .ok()
+                .map(|t| t.with_timezone(&Utc)),
+            id: event.id.clone(),
+        }
+    }
+
+    fn parse(cursor: &str) -> Result<Self, AuditError> {
+        let invalid = || AuditError::InvalidCursor(cursor.to_string());
+        let (timestamp, id) = cursor.split_once('|').ok_or_else(invalid)?;
+        let timestamp = if timestamp.is_empty() {
+            None
+        } else {
+            Some(
+                DateTime::parse_from_rfc3339(timestamp)
+                    .map_err(|_| invalid())?
+                    .with_timezone(&Utc),
+            )
+        };
+        Ok(Self {
+            timestamp,
+            id: id.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for EventCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let timestamp = self.timestamp.map(|t| t.to_rfc3339()).unwrap_or_default();
+        write!(f, "{}|{}", timestamp, self.id)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: u32,
+    pub skipped_duplicates: u32,
+    pub skipped_invalid: u32,
+    pub runs_created: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RunSummary {
     pub total_events: u32,
     pub agent_decisions: u32,
@@ -203,5 +2019,420 @@ pub struct RunSummary {
     pub deployments_completed: u32,
     pub documentation_updates: u32,
     pub linear_updates: u32,
+    /// Spans started but never ended
+    #[serde(default)]
+    pub open_spans: u32,
+    /// Seconds from run start to completion
     pub duration: u64,
 }
+
+impl RunSummary {
+    fn add(&mut self, other: &RunSummary) {
+        self.total_events += other.total_events;
+        self.agent_decisions += other.agent_decisions;
+        self.tool_calls += other.tool_calls;
+        self.code_changes += other.code_changes;
+        self.tests_run += other.tests_run;
+        self.tests_passed += other.tests_passed;
+        self.tests_failed += other.tests_failed;
+        self.gates_passed += other.gates_passed;
+        self.gates_failed += other.gates_failed;
+        self.deployments_completed += other.deployments_completed;
+        self.documentation_updates += other.documentation_updates;
+        self.linear_updates += other.linear_updates;
+        self.open_spans += other.open_spans;
+        self.duration += other.duration;
+    }
+}
+
+/// Run totals for a project, as returned by `AuditStore::project_stats`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectStats {
+    pub project_id: String,
+    pub total_runs: u32,
+    pub runs_by_status: BTreeMap<String, u32>,
+    /// Summed over all runs; `duration` covers finished runs only
+    pub totals: RunSummary,
+    /// Seconds, over finished runs; `None` until a run has finished
+    pub mean_duration: Option<f64>,
+    pub median_duration: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(store: &AuditStore, run_id: &str, event_type: &str) {
+        let run = store.get_run(run_id).unwrap();
+        store
+            .record_event(AuditEvent::new(
+                &run.project_id,
+                &run.workstream_id,
+                run_id,
+                event_type,
+                AuditActor::agent_role("coder"),
+                serde_json::json!({ "kind": event_type }),
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn completed_run_reports_its_duration() {
+        let store = AuditStore::new();
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        let started = Utc::now() - chrono::Duration::seconds(5);
+        store
+            .runs
+            .lock_recover()
+            .get_mut(&run_id)
+            .unwrap()
+            .started_at = started.to_rfc3339();
+        event(&store, &run_id, "agent.decision");
+        event(&store, &run_id, "tool.call");
+
+        let run = store.complete_run(&run_id, "completed").unwrap();
+        let summary = run.summary.unwrap();
+        assert!((5..=6).contains(&summary.duration), "{}", summary.duration);
+        assert_eq!(summary.agent_decisions, 1);
+        assert_eq!(summary.tool_calls, 1);
+    }
+
+    #[test]
+    fn cancelling_a_completed_run_fails() {
+        let store = AuditStore::new();
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        store.complete_run(&run_id, "completed").unwrap();
+
+        let err = store
+            .cancel_run(&run_id, "too late", AuditActor::user())
+            .unwrap_err();
+        assert!(matches!(err, AuditError::RunAlreadyCompleted(id) if id == run_id));
+        assert_eq!(store.get_run(&run_id).unwrap().status, "completed");
+    }
+
+    #[test]
+    fn cancelling_a_running_run_records_status_and_summary() {
+        let store = AuditStore::new();
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        event(&store, &run_id, "tool.call");
+
+        let run = store
+            .cancel_run(&run_id, "wrong branch", AuditActor::agent_role("lead"))
+            .unwrap();
+        assert_eq!(run.status, "cancelled");
+        assert!(run.completed_at.is_some());
+        assert_eq!(run.summary.unwrap().tool_calls, 1);
+
+        let stored = store.get_run(&run_id).unwrap();
+        assert_eq!(stored.status, "cancelled");
+        assert!(stored.summary.is_some());
+
+        let cancelled = &store.get_events("p", 1)[0];
+        assert_eq!(cancelled.event_type, "run.cancelled");
+        assert_eq!(cancelled.payload["reason"], "wrong branch");
+        assert_eq!(cancelled.actor.role.as_deref(), Some("lead"));
+    }
+
+    #[test]
+    fn spans_pair_their_start_and_end_events() {
+        let store = AuditStore::new();
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        let outer = store
+            .start_span(&run_id, "build", AuditActor::agent_role("coder"))
+            .unwrap();
+        let inner = store
+            .start_span(&run_id, "test", AuditActor::agent_role("coder"))
+            .unwrap();
+        store
+            .end_span(&inner, "ok", AuditActor::agent_role("coder"))
+            .unwrap();
+        store
+            .end_span(&outer, "error", AuditActor::agent_role("coder"))
+            .unwrap();
+
+        let spans = store.run_spans(&run_id).unwrap();
+        let pairs: Vec<(&str, &str, Option<&str>)> = spans
+            .iter()
+            .map(|s| (s.span_id.as_str(), s.name.as_str(), s.status.as_deref()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (outer.as_str(), "build", Some("error")),
+                (inner.as_str(), "test", Some("ok")),
+            ]
+        );
+        assert!(spans.iter().all(|s| s.ended_at.is_some()));
+
+        let err = store
+            .end_span(&inner, "ok", AuditActor::agent_role("coder"))
+            .unwrap_err();
+        assert!(matches!(err, AuditError::SpanAlreadyEnded(_)));
+    }
+
+    #[test]
+    fn unclosed_span_is_reported_open_in_the_summary() {
+        let store = AuditStore::new();
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        let closed = store
+            .start_span(&run_id, "build", AuditActor::agent_role("coder"))
+            .unwrap();
+        store
+            .end_span(&closed, "ok", AuditActor::agent_role("coder"))
+            .unwrap();
+        store
+            .start_span(&run_id, "deploy", AuditActor::agent_role("coder"))
+            .unwrap();
+
+        let run = store.complete_run(&run_id, "completed").unwrap();
+        assert_eq!(run.summary.unwrap().open_spans, 1);
+        let open: Vec<String> = store
+            .run_spans(&run_id)
+            .unwrap()
+            .into_iter()
+            .filter(|s| s.ended_at.is_none())
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(open, vec!["deploy".to_string()]);
+    }
+
+    #[test]
+    fn verify_chain_reports_an_unreadable_line_as_broken() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AuditStore::new();
+        store.attach_storage(dir.path().to_path_buf());
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        event(&store, &run_id, "agent.decision");
+        event(&store, &run_id, "tool.call");
+        assert_eq!(store.verify_chain().unwrap().first_broken, None);
+
+        let path = dir.path().join("events.jsonl");
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        lines[1] = "{ corrupted";
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        assert_eq!(store.verify_chain().unwrap().first_broken, Some(1));
+    }
+
+    /// A store holding two runs of project `p`: tool and file events from an
+    /// agent, an hour apart, and a user message. Plus one event elsewhere.
+    fn query_fixture() -> (AuditStore, String, String) {
+        let store = AuditStore::new();
+        let first = store.create_run("p", "w", "build", "ship it").unwrap();
+        let second = store.create_run("p", "w", "test", "check it").unwrap();
+        let other = store.create_run("q", "x", "build", "elsewhere").unwrap();
+        let hour_ago = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+
+        for (run_id, event_type, actor, timestamp) in [
+            (
+                &first,
+                "tool.called",
+                AuditActor::agent_role("coder"),
+                Some(&hour_ago),
+            ),
+            (
+                &first,
+                "tool.completed",
+                AuditActor::agent_role("coder"),
+                Some(&hour_ago),
+            ),
+            (
+                &second,
+                "tool.called",
+                AuditActor::agent_role("coder"),
+                None,
+            ),
+            (
+                &second,
+                "file.written",
+                AuditActor::agent_role("coder"),
+                None,
+            ),
+            (&second, "user.request", AuditActor::user(), None),
+            (&other, "tool.called", AuditActor::agent_role("coder"), None),
+        ] {
+            let run = store.get_run(run_id).unwrap();
+            let mut event = AuditEvent::new(
+                &run.project_id,
+                &run.workstream_id,
+                run_id,
+                event_type,
+                actor,
+                serde_json::json!({ "kind": event_type }),
+            );
+            if let Some(timestamp) = timestamp {
+                event.timestamp = timestamp.clone();
+            }
+            store.record_event(event).unwrap();
+        }
+        (store, first, second)
+    }
+
+    fn types(result: &EventQueryResult) -> Vec<&str> {
+        result
+            .events
+            .iter()
+            .map(|e| e.event_type.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn query_matches_type_prefixes() {
+        let (store, _, _) = query_fixture();
+        let filter = EventFilter {
+            event_types: vec!["tool.*".to_string()],
+            ..Default::default()
+        };
+
+        let result = store.query_events("p", &filter);
+        assert_eq!(
+            types(&result),
+            ["tool.called", "tool.completed", "tool.called"]
+        );
+    }
+
+    #[test]
+    fn query_matches_any_of_several_exact_types() {
+        let (store, _, _) = query_fixture();
+        let filter = EventFilter {
+            event_types: vec!["file.written".to_string(), "run.started".to_string()],
+            ..Default::default()
+        };
+
+        let result = store.query_events("p", &filter);
+        assert_eq!(
+            types(&result),
+            ["file.written", "run.started", "run.started"]
+        );
+    }
+
+    #[test]
+    fn query_filters_on_actor_and_run() {
+        let (store, first, second) = query_fixture();
+
+        let by_agent = store.query_events(
+            "p",
+            &EventFilter {
+                actor_type: Some("agent".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(by_agent.total, 4);
+        assert!(by_agent
+            .events
+            .iter()
+            .all(|e| e.actor.actor_type == "agent"));
+
+        let by_run = store.query_events(
+            "p",
+            &EventFilter {
+                run_id: Some(first.clone()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            types(&by_run),
+            ["tool.completed", "tool.called", "run.started"]
+        );
+
+        let user_in_second = store.query_events(
+            "p",
+            &EventFilter {
+                actor_type: Some("user".to_string()),
+                run_id: Some(second),
+                ..Default::default()
+            },
+        );
+        assert_eq!(types(&user_in_second), ["user.request", "run.started"]);
+    }
+
+    #[test]
+    fn query_keeps_events_inside_the_window() {
+        let (store, _, _) = query_fixture();
+        let now = Utc::now();
+
+        let earlier = store.query_events(
+            "p",
+            &EventFilter {
+                from: Some(now - chrono::Duration::hours(2)),
+                to: Some(now - chrono::Duration::minutes(30)),
+                ..Default::default()
+            },
+        );
+        assert_eq!(types(&earlier), ["tool.completed", "tool.called"]);
+
+        let recent = store.query_events(
+            "p",
+            &EventFilter {
+                from: Some(now - chrono::Duration::minutes(30)),
+                ..Default::default()
+            },
+        );
+        assert_eq!(recent.total, 5);
+    }
+
+    #[test]
+    fn query_total_counts_past_the_limit() {
+        let (store, _, _) = query_fixture();
+        let filter = EventFilter {
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let result = store.query_events("p", &filter);
+        assert_eq!(result.total, 7);
+        assert_eq!(types(&result), ["user.request", "file.written"]);
+    }
+
+    #[test]
+    fn signed_export_verifies_until_a_byte_changes() {
+        let store = AuditStore::new();
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        event(&store, &run_id, "tool.called");
+        let key = SigningKey::from_bytes(&[7; 32]);
+
+        for format in [ExportFormat::Jsonl, ExportFormat::Markdown] {
+            let export = store.export_signed(&run_id, format, &key).unwrap();
+            assert!(signing::verify_export(
+                export.content.as_bytes(),
+                &export.signature,
+                &export.public_key
+            ));
+
+            let mut tampered = export.content.into_bytes();
+            tampered[0] ^= 1;
+            assert!(!signing::verify_export(
+                &tampered,
+                &export.signature,
+                &export.public_key
+            ));
+        }
+    }
+
+    #[test]
+    fn markdown_export_is_the_run_report() {
+        let store = AuditStore::new();
+        let run_id = store.create_run("p", "w", "build", "ship it").unwrap();
+        event(&store, &run_id, "tool.called");
+
+        let markdown = store
+            .render_export(&run_id, ExportFormat::Markdown)
+            .unwrap();
+        let report = store
+            .export_run(&run_id)
+            .unwrap()
+            .render(ReportFormat::Markdown)
+            .unwrap();
+        // Only the export time differs between two renders
+        let without_time = |s: &str| {
+            s.lines()
+                .filter(|l| !l.contains("xported"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        assert_eq!(without_time(&markdown), without_time(&report));
+        assert!(markdown.contains("tool.called"));
+    }
+}
@@ -0,0 +1,73 @@
+//! Tamper-evident hash chaining for the audit log.
+//! Every event's `hash` covers its own fields plus the previous event's hash,
+//! so editing, dropping, or reordering any stored event breaks every link
+//! after it.
+
+use crate::audit::{AuditActor, AuditEvent};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Fields covered by the hash, in a fixed order
+#[derive(Serialize)]
+struct HashedFields<'a> {
+    id: &'a str,
+    timestamp: &'a str,
+    run_id: &'a str,
+    workstream_id: &'a str,
+    project_id: &'a str,
+    event_type: &'a str,
+    actor: &'a AuditActor,
+    payload: &'a serde_json::Value,
+    parent_event_id: Option<&'a str>,
+    span_id: Option<&'a str>,
+    trace_id: Option<&'a str>,
+    prev_hash: Option<&'a str>,
+}
+
+/// Hex SHA-256 over the event's canonical serialization, `hash` excluded
+pub fn event_hash(event: &AuditEvent) -> String {
+    let fields = HashedFields {
+        id: &event.id,
+        timestamp: &event.timestamp,
+        run_id: &event.run_id,
+        workstream_id: &event.workstream_id,
+        project_id: &event.project_id,
+        event_type: &event.event_type,
+        actor: &event.actor,
+        payload: &event.payload,
+        parent_event_id: event.parent_event_id.as_deref(),
+        span_id: event.span_id.as_deref(),
+        trace_id: event.trace_id.as_deref(),
+        prev_hash: event.prev_hash.as_deref(),
+    };
+    let bytes = serde_json::to_vec(&fields).unwrap_or_default();
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Link an event onto the end of the chain
+pub fn link(prev: Option<&AuditEvent>, event: &mut AuditEvent) {
+    event.prev_hash = prev.and_then(|p| p.hash.clone());
+    event.hash = Some(event_hash(event));
+}
+
+/// Re-link a whole log from the start, e.g. after maintenance removed events
+pub fn relink(events: &mut [AuditEvent]) {
+    for i in 0..events.len() {
+        let (before, rest) = events.split_at_mut(i);
+        link(before.last(), &mut rest[0]);
+    }
+}
+
+/// Index of the first event whose link doesn't verify, if any
+pub fn first_broken_link(events: &[AuditEvent]) -> Option<usize> {
+    let mut prev_hash: Option<&str> = None;
+    for (i, event) in events.iter().enumerate() {
+        if event.prev_hash.as_deref() != prev_hash
+            || event.hash.as_deref() != Some(event_hash(event).as_str())
+        {
+            return Some(i);
+        }
+        prev_hash = event.hash.as_deref();
+    }
+    None
+}
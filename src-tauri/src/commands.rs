@@ -1,13 +1,43 @@
-use crate::audit::{AuditEvent, AuditStore, RunRecord};
-use crate::policy::{NebulaPolicy, PolicyEngine};
-use crate::vault::{VaultManager, VaultNote, VaultNoteEntry};
+use crate::audit::{
+    AuditActor, AuditEvent, AuditStore, ChainVerification, DedupReport, EventFilter, EventNode,
+    EventPage, EventQueryResult, ExportFormat, ImportReport, MigrationReport, ProjectStats,
+    ReportFormat, RunQuery, RunRecord, RunSpan, SignedExport,
+};
+use crate::config::{ConfigStore, ProjectConfig};
+use crate::emitter::EmissionConfig;
+use crate::error::{CommandError, ErrorCode};
+use crate::journal::AuditBackendKind;
+use crate::links::BrokenLink;
+use crate::payload_query::MatchMode;
+use crate::persist;
+use crate::phase;
+use crate::policy::{
+    self, CanaryPlan, GateState, GateStatus, MergeEvaluation, NebulaPolicy, PermissionExplanation,
+    PolicyDecision, PolicyDiff, PolicyEngine, PolicyFileError, PolicyFormat, RedactionResult,
+    RollbackDecision,
+};
+use crate::render::RenderedNote;
+use crate::search_index::RankedSearchResult;
+use crate::signing::{self, SigningKeyStore};
+use crate::sync::{self, MutexExt};
+use crate::tags::TagCount;
+use crate::transcript;
+use crate::vault::{
+    AttachmentInfo, BundledNote, LevelRule, ListOptions, MoveReport, NoteLinks, NoteWrite,
+    Reference, ReferenceMatch, ResolvedLink, SearchOptions, SnapshotInfo, TemplateInfo,
+    VaultCacheStats, VaultDiff, VaultError, VaultHealthReport, VaultManager, VaultNote,
+    VaultNoteEntry, VaultSearchResult,
+};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::State;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_opener::OpenerExt;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
-/// Project data stored in memory (will be persisted to vault in later phases)
+/// Project data, cached in memory and persisted to the app data dir
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectData {
     pub id: String,
@@ -17,6 +47,10 @@ pub struct ProjectData {
     pub status: String,
     pub vault_path: String,
     pub workstreams: Vec<String>,
+    /// For an imported project, the project and workstream ids its history
+    /// was recorded under, mapped to the ids they have here
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub imported_ids: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +64,9 @@ pub struct WorkstreamData {
     pub user_request: String,
     pub current_phase: String,
     pub messages: Vec<MessageData>,
+    /// Run started for this workstream; absent on workstreams created before runs were tracked
+    #[serde(default)]
+    pub run_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,23 +77,321 @@ pub struct MessageData {
     pub timestamp: String,
     pub agent_id: Option<String>,
     pub agent_name: Option<String>,
+    /// Set when policy redaction replaced part of the content
+    #[serde(default)]
+    pub redacted: bool,
 }
 
-// In-memory store for projects and workstreams (will be replaced with vault persistence)
+// In-memory cache of projects and workstreams, mirrored to the app data dir
 static PROJECTS: std::sync::LazyLock<std::sync::Mutex<Vec<ProjectData>>> =
     std::sync::LazyLock::new(|| std::sync::Mutex::new(Vec::new()));
 
 static WORKSTREAMS: std::sync::LazyLock<std::sync::Mutex<Vec<WorkstreamData>>> =
     std::sync::LazyLock::new(|| std::sync::Mutex::new(Vec::new()));
 
+/// Where `projects.json` and `workstreams.json` live; unset means memory only
+static DATA_DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+const PROJECTS_FILE: &str = "projects.json";
+const WORKSTREAMS_FILE: &str = "workstreams.json";
+
+/// Load persisted projects and workstreams at startup, re-register each
+/// project's vault, load the policy saved in it, and point the audit store
+/// at the history of imported projects
+pub fn load_state(
+    data_dir: PathBuf,
+    vault_manager: &VaultManager,
+    policy_engine: &PolicyEngine,
+    audit_store: &AuditStore,
+) {
+    match persist::read_json::<Vec<ProjectData>>(&data_dir.join(PROJECTS_FILE)) {
+        Ok(Some(loaded)) => {
+            for project in &loaded {
+                audit_store.add_aliases(&project.imported_ids);
+            }
+            for project in loaded.iter().filter(|p| !p.vault_path.is_empty()) {
+                if let Err(e) =
+                    vault_manager.register_vault(&project.id, PathBuf::from(&project.vault_path))
+                {
+                    tracing::warn!("Vault for project {} unavailable: {}", project.id, e);
+                    continue;
+                }
+                if let Some(root) = vault_manager.vault_root(&project.id) {
+                    policy_engine.load_policy(&project.id, &root);
+                }
+            }
+            *PROJECTS.lock_recover() = loaded;
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to load projects: {}", e),
+    }
+
+    match persist::read_json::<Vec<WorkstreamData>>(&data_dir.join(WORKSTREAMS_FILE)) {
+        Ok(Some(loaded)) => *WORKSTREAMS.lock_recover() = loaded,
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to load workstreams: {}", e),
+    }
+    rehydrate_workstreams(vault_manager);
+
+    let _ = DATA_DIR.set(data_dir);
+}
+
+/// Add workstreams that only exist as notes in a project's vault, e.g. a vault
+/// carried over from another machine. Known workstreams keep their saved state.
+fn rehydrate_workstreams(vault_manager: &VaultManager) {
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let mut projects = PROJECTS.lock_recover();
+    let mut found = false;
+
+    for project in projects.iter_mut() {
+        let Some(root) = vault_manager.vault_root(&project.id) else {
+            continue;
+        };
+        let Ok(entries) = std::fs::read_dir(root.join(transcript::WORKSTREAMS_DIR)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let Some(mut ws) = transcript::parse(&content) else {
+                continue;
+            };
+            if workstreams.iter().any(|w| w.id == ws.id) {
+                continue;
+            }
+            ws.project_id = project.id.clone();
+            if !project.workstreams.contains(&ws.id) {
+                project.workstreams.push(ws.id.clone());
+            }
+            workstreams.push(ws);
+            found = true;
+        }
+    }
+
+    if found {
+        save_workstreams(&workstreams);
+        save_projects(&projects);
+    }
+}
+
+// Called with the collection's lock held so disk writes land in mutation order
+fn save_projects(projects: &[ProjectData]) {
+    if let Some(dir) = DATA_DIR.get() {
+        if let Err(e) = persist::write_json(&dir.join(PROJECTS_FILE), projects) {
+            tracing::warn!("Failed to persist projects: {}", e);
+        }
+    }
+}
+
+fn save_workstreams(workstreams: &[WorkstreamData]) {
+    if let Some(dir) = DATA_DIR.get() {
+        if let Err(e) = persist::write_json(&dir.join(WORKSTREAMS_FILE), workstreams) {
+            tracing::warn!("Failed to persist workstreams: {}", e);
+        }
+    }
+}
+
+/// Status of a project or workstream hidden from listings by default
+const ARCHIVED: &str = "archived";
+
+#[tauri::command]
+pub fn get_projects(include_archived: Option<bool>) -> Vec<ProjectData> {
+    let include_archived = include_archived.unwrap_or(false);
+    let projects = PROJECTS.lock_recover();
+    projects
+        .iter()
+        .filter(|p| include_archived || p.status != ARCHIVED)
+        .cloned()
+        .collect()
+}
+
+/// Poisoned locks in one of the app's state stores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreHealth {
+    pub name: String,
+    pub healthy: bool,
+    pub poisoned_locks: Vec<String>,
+}
+
+/// Whether a directory the app persists to accepts writes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageHealth {
+    pub name: String,
+    pub path: Option<String>,
+    pub writable: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemHealth {
+    /// No poisoned locks and every storage directory writable
+    pub healthy: bool,
+    pub checked_at: String,
+    pub projects: usize,
+    pub workstreams: usize,
+    pub vaults: usize,
+    pub audit_events: usize,
+    pub stores: Vec<StoreHealth>,
+    pub storage: Vec<StorageHealth>,
+}
+
+/// Readiness of the app's state for diagnostics and the status bar. Lock
+/// poisoning is reported rather than propagated; the counts that follow
+/// recover the lock as every command does.
+#[tauri::command]
+pub fn system_health(
+    audit_store: State<'_, AuditStore>,
+    policy_engine: State<'_, PolicyEngine>,
+    vault_manager: State<'_, VaultManager>,
+    config_store: State<'_, ConfigStore>,
+) -> SystemHealth {
+    let stores: Vec<StoreHealth> = [
+        (
+            "app_state",
+            sync::poisoned([
+                ("projects", PROJECTS.is_poisoned()),
+                ("workstreams", WORKSTREAMS.is_poisoned()),
+            ]),
+        ),
+        ("audit", audit_store.poisoned_locks()),
+        ("policy", policy_engine.poisoned_locks()),
+        ("vault", vault_manager.poisoned_locks()),
+        ("config", config_store.poisoned_locks()),
+    ]
+    .into_iter()
+    .map(|(name, poisoned)| StoreHealth {
+        name: name.to_string(),
+        healthy: poisoned.is_empty(),
+        poisoned_locks: poisoned.into_iter().map(str::to_string).collect(),
+    })
+    .collect();
+
+    let storage: Vec<StorageHealth> = [
+        ("app_data", DATA_DIR.get().cloned()),
+        ("audit", audit_store.storage_dir()),
+    ]
+    .into_iter()
+    .map(|(name, dir)| {
+        let result = match &dir {
+            Some(dir) => persist::probe_writable(dir).map_err(|e| e.to_string()),
+            None => Err("No storage directory; state is kept in memory only".to_string()),
+        };
+        StorageHealth {
+            name: name.to_string(),
+            path: dir.map(|d| d.to_string_lossy().to_string()),
+            writable: result.is_ok(),
+            error: result.err(),
+        }
+    })
+    .collect();
+
+    SystemHealth {
+        healthy: stores.iter().all(|s| s.healthy) && storage.iter().all(|s| s.writable),
+        checked_at: Utc::now().to_rfc3339(),
+        projects: PROJECTS.lock_recover().len(),
+        workstreams: WORKSTREAMS.lock_recover().len(),
+        vaults: vault_manager.vault_count(),
+        audit_events: audit_store.event_count(),
+        stores,
+        storage,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectSummary {
+    #[serde(flatten)]
+    pub project: ProjectData,
+    /// Workstreams that aren't archived
+    pub workstream_count: usize,
+    /// Workstreams that aren't archived or finished
+    pub active_workstream_count: usize,
+    /// Timestamp of the project's newest audit event
+    pub last_activity: Option<String>,
+}
+
+/// Projects with their workstream counts and last activity, for the project
+/// list
+#[tauri::command]
+pub fn get_projects_with_stats(
+    include_archived: Option<bool>,
+    audit_store: State<'_, AuditStore>,
+) -> Vec<ProjectSummary> {
+    let mut last_activity = audit_store.last_activity();
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    let workstreams = WORKSTREAMS.lock_recover();
+    for ws in workstreams.iter().filter(|w| w.status != ARCHIVED) {
+        let count = counts.entry(ws.project_id.as_str()).or_default();
+        count.0 += 1;
+        if !phase::is_terminal_status(&ws.status) {
+            count.1 += 1;
+        }
+    }
+
+    get_projects(include_archived)
+        .into_iter()
+        .map(|project| {
+            let (workstream_count, active_workstream_count) =
+                counts.get(project.id.as_str()).copied().unwrap_or_default();
+            ProjectSummary {
+                last_activity: last_activity.remove(&project.id),
+                workstream_count,
+                active_workstream_count,
+                project,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectPage {
+    pub projects: Vec<ProjectData>,
+    pub total: usize,
+}
+
+/// Newest-first page of projects whose name or description contains `query`
 #[tauri::command]
-pub fn get_projects() -> Vec<ProjectData> {
-    let projects = PROJECTS.lock().unwrap();
-    projects.clone()
+pub fn get_projects_paged(
+    offset: Option<usize>,
+    limit: Option<usize>,
+    query: Option<String>,
+) -> ProjectPage {
+    let projects = PROJECTS.lock_recover();
+    let query = query.map(|q| q.to_lowercase()).filter(|q| !q.is_empty());
+
+    let mut matching: Vec<&ProjectData> = projects
+        .iter()
+        .filter(|p| {
+            query.as_ref().is_none_or(|q| {
+                p.name.to_lowercase().contains(q) || p.description.to_lowercase().contains(q)
+            })
+        })
+        .collect();
+    matching.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    ProjectPage {
+        total: matching.len(),
+        projects: matching
+            .into_iter()
+            .skip(offset.unwrap_or(0))
+            .take(limit.unwrap_or(50))
+            .cloned()
+            .collect(),
+    }
 }
 
 #[tauri::command]
-pub fn create_project(name: String, description: String, vault_path: String) -> ProjectData {
+pub fn create_project(
+    name: String,
+    description: String,
+    vault_path: String,
+    allow_duplicate: Option<bool>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<ProjectData, CommandError> {
+    if !allow_duplicate.unwrap_or(false) {
+        check_unique_name(&PROJECTS.lock_recover(), &name, None)?;
+    }
+
     let project = ProjectData {
         id: Uuid::new_v4().to_string(),
         name,
@@ -65,173 +400,3792 @@ pub fn create_project(name: String, description: String, vault_path: String) ->
         status: "active".to_string(),
         vault_path,
         workstreams: Vec::new(),
+        imported_ids: HashMap::new(),
     };
 
-    let mut projects = PROJECTS.lock().unwrap();
+    if !project.vault_path.is_empty() {
+        vault_manager.register_vault(&project.id, PathBuf::from(&project.vault_path))?;
+    }
+
+    let mut projects = PROJECTS.lock_recover();
     projects.push(project.clone());
+    save_projects(&projects);
 
-    project
+    Ok(project)
 }
 
-#[tauri::command]
-pub fn get_project(project_id: String) -> Option<ProjectData> {
-    let projects = PROJECTS.lock().unwrap();
-    projects.iter().find(|p| p.id == project_id).cloned()
+/// Refuse a name already used by another non-archived project, ignoring case
+fn check_unique_name(
+    projects: &[ProjectData],
+    name: &str,
+    except_id: Option<&str>,
+) -> Result<(), CommandError> {
+    let wanted = name.trim().to_lowercase();
+    let existing = projects.iter().find(|p| {
+        p.status != ARCHIVED
+            && Some(p.id.as_str()) != except_id
+            && p.name.trim().to_lowercase() == wanted
+    });
+    match existing {
+        Some(existing) => Err(CommandError::new(
+            ErrorCode::ProjectExists,
+            format!(
+                "A project named \"{}\" already exists; archive or rename it first",
+                existing.name
+            ),
+        )
+        .with_details(serde_json::json!({ "existing_project_id": existing.id }))),
+        None => Ok(()),
+    }
+}
+
+/// Format version written into project bundles; import rejects any other
+const BUNDLE_VERSION: u32 = 1;
+
+/// A project with everything needed to recreate it on another machine.
+/// Audit events travel as a read-only record of the project's history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectBundle {
+    pub version: u32,
+    pub exported_at: String,
+    pub project: ProjectData,
+    pub workstreams: Vec<WorkstreamData>,
+    pub policy: Option<NebulaPolicy>,
+    pub notes: Vec<BundledNote>,
+    pub audit_events: Vec<AuditEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectImport {
+    pub project: ProjectData,
+    pub workstreams: usize,
+    pub notes: usize,
+    pub audit: ImportReport,
 }
 
+/// Collect a project, its workstreams, policy, vault notes, and audit events
+/// into one bundle. Notes `reader_role` can't read are left out.
 #[tauri::command]
-pub fn get_workstreams(project_id: String) -> Vec<WorkstreamData> {
-    let workstreams = WORKSTREAMS.lock().unwrap();
-    workstreams
+pub fn export_project(
+    project_id: String,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<ProjectBundle, CommandError> {
+    let project = PROJECTS
+        .lock_recover()
+        .iter()
+        .find(|p| p.id == project_id)
+        .cloned()
+        .ok_or_else(|| {
+            CommandError::new(
+                ErrorCode::ProjectNotFound,
+                format!("Project not found: {}", project_id),
+            )
+        })?;
+    let workstreams = WORKSTREAMS
+        .lock_recover()
         .iter()
         .filter(|w| w.project_id == project_id)
         .cloned()
-        .collect()
+        .collect();
+    let notes = if vault_manager.vault_root(&project_id).is_some() {
+        let mut notes = vault_manager.export_notes(&project_id)?;
+        notes.retain(|note| {
+            note_visible(
+                &project_id,
+                &note.path,
+                reader_role.as_deref(),
+                &vault_manager,
+                &policy_engine,
+            )
+        });
+        notes
+    } else {
+        Vec::new()
+    };
+    let mut audit_events = audit_store.get_events(&project_id, usize::MAX);
+    audit_events.reverse();
+
+    Ok(ProjectBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        policy: policy_engine.get_policy(&project_id),
+        project,
+        workstreams,
+        notes,
+        audit_events,
+    })
 }
 
+/// Recreate a bundled project with fresh project and workstream ids, its
+/// notes written into `vault_path`. Audit events are imported unchanged, so
+/// they still name the project, workstreams, and actors they were recorded
+/// for; the project keeps the old ids mapped to the new ones in
+/// `imported_ids`, and the audit store reads that history as the new
+/// project's. Workstreams keep their run ids, as the runs come along.
+/// The whole bundle, policy included, is validated before anything is
+/// applied, and a failure while writing undoes the vault registration and
+/// the notes already written.
 #[tauri::command]
-pub fn create_workstream(
-    project_id: String,
-    title: String,
-    user_request: String,
+pub fn import_project(
+    bundle: ProjectBundle,
+    vault_path: String,
+    allow_duplicate: Option<bool>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
     audit_store: State<'_, AuditStore>,
-) -> WorkstreamData {
-    let workstream_id = Uuid::new_v4().to_string();
+) -> Result<ProjectImport, CommandError> {
+    import_bundle(
+        bundle,
+        vault_path,
+        allow_duplicate.unwrap_or(false),
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )
+}
 
-    // Create a run for this workstream
-    let _run_id = audit_store.create_run(&project_id, &workstream_id, &user_request);
+fn import_bundle(
+    bundle: ProjectBundle,
+    vault_path: String,
+    allow_duplicate: bool,
+    vault_manager: &VaultManager,
+    policy_engine: &PolicyEngine,
+    audit_store: &AuditStore,
+) -> Result<ProjectImport, CommandError> {
+    if bundle.version != BUNDLE_VERSION {
+        return Err(CommandError::invalid_input(format!(
+            "Unsupported project bundle version {}",
+            bundle.version
+        ))
+        .with_details(serde_json::json!({
+            "version": bundle.version,
+            "supported": BUNDLE_VERSION
+        })));
+    }
+    if vault_path.is_empty() && !bundle.notes.is_empty() {
+        return Err(CommandError::invalid_input(
+            "A vault path is required to import the bundle's notes",
+        ));
+    }
+    if !allow_duplicate {
+        check_unique_name(&PROJECTS.lock_recover(), &bundle.project.name, None)?;
+    }
+    if let Some(policy) = &bundle.policy {
+        if let Err(errors) = policy.validate() {
+            return Err(CommandError::new(
+                ErrorCode::InvalidPolicy,
+                "Bundled policy failed validation",
+            )
+            .with_details(serde_json::json!(errors)));
+        }
+    }
 
-    let workstream = WorkstreamData {
-        id: workstream_id,
-        project_id: project_id.clone(),
-        title,
-        description: String::new(),
-        status: "draft".to_string(),
-        created_at: Utc::now().to_rfc3339(),
-        user_request,
-        current_phase: "design".to_string(),
-        messages: Vec::new(),
+    let source_project_id = bundle.project.id.clone();
+    let bundled_ids = bundle.project.imported_ids.clone();
+    let mut project = ProjectData {
+        id: Uuid::new_v4().to_string(),
+        vault_path,
+        workstreams: Vec::new(),
+        imported_ids: HashMap::new(),
+        ..bundle.project
     };
+    project
+        .imported_ids
+        .insert(source_project_id.clone(), project.id.clone());
+    let workstreams: Vec<WorkstreamData> = bundle
+        .workstreams
+        .into_iter()
+        .map(|ws| {
+            let id = Uuid::new_v4().to_string();
+            project.imported_ids.insert(ws.id.clone(), id.clone());
+            WorkstreamData {
+                id,
+                project_id: project.id.clone(),
+                ..ws
+            }
+        })
+        .collect();
+    project.workstreams = workstreams.iter().map(|w| w.id.clone()).collect();
+    // A project imported before keeps pointing its oldest ids at the newest
+    for (older, source) in bundled_ids {
+        if let Some(current) = project.imported_ids.get(&source).cloned() {
+            project.imported_ids.insert(older, current);
+        }
+    }
 
-    let mut workstreams = WORKSTREAMS.lock().unwrap();
-    workstreams.push(workstream.clone());
+    // Transcripts are rewritten under the new workstream ids
+    let transcripts = format!("{}/", transcript::WORKSTREAMS_DIR);
+    let notes: Vec<BundledNote> = bundle
+        .notes
+        .into_iter()
+        .filter(|n| !n.path.starts_with(&transcripts))
+        .collect();
 
-    // Update project workstream list
-    let mut projects = PROJECTS.lock().unwrap();
+    // Events keep their ids and are merged, so a retry after a failure below
+    // skips the ones already imported rather than duplicating them
+    let audit = audit_store.import_events(bundle.audit_events, false, true)?;
+
+    if !project.vault_path.is_empty() {
+        vault_manager.register_vault(&project.id, PathBuf::from(&project.vault_path))?;
+        let imported = match vault_manager.import_notes(&project.id, &notes) {
+            Ok(imported) => imported,
+            Err(e) => {
+                vault_manager.unregister_vault(&project.id);
+                return Err(e.into());
+            }
+        };
+        if let Some(policy) = bundle.policy {
+            let root = vault_manager.vault_root(&project.id);
+            if let Err(e) = policy_engine.set_policy(&project.id, policy, None, root.as_deref()) {
+                vault_manager.undo_import(&project.id, imported);
+                vault_manager.unregister_vault(&project.id);
+                return Err(e.into());
+            }
+        }
+        for ws in &workstreams {
+            write_workstream_note(vault_manager, ws);
+        }
+    } else if let Some(policy) = bundle.policy {
+        policy_engine.set_policy(&project.id, policy, None, None)?;
+    }
+
+    {
+        let mut all = WORKSTREAMS.lock_recover();
+        all.extend(workstreams.iter().cloned());
+        save_workstreams(&all);
+    }
+    {
+        let mut projects = PROJECTS.lock_recover();
+        projects.push(project.clone());
+        save_projects(&projects);
+    }
+    audit_store.add_aliases(&project.imported_ids);
+
+    // The project exists by now, so a failed write is logged, not returned
+    let recorded = audit_store.record_event(AuditEvent::new(
+        &project.id,
+        "",
+        "",
+        "project.imported",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "project",
+            "action": "import",
+            "source_project_id": source_project_id,
+            "imported_ids": project.imported_ids,
+            "workstreams": workstreams.len(),
+            "notes": notes.len(),
+            "audit_events": audit.imported
+        }),
+    ));
+    if let Err(e) = recorded {
+        tracing::error!("Failed to audit import of project {}: {}", project.id, e);
+    }
+
+    Ok(ProjectImport {
+        project,
+        workstreams: workstreams.len(),
+        notes: notes.len(),
+        audit,
+    })
+}
+
+#[tauri::command]
+pub fn register_vault(
+    project_id: String,
+    vault_path: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<(), CommandError> {
+    vault_manager.register_vault(&project_id, PathBuf::from(&vault_path))?;
+
+    let mut projects = PROJECTS.lock_recover();
     if let Some(project) = projects.iter_mut().find(|p| p.id == project_id) {
-        project.workstreams.push(workstream.id.clone());
+        project.vault_path = vault_path;
+        save_projects(&projects);
     }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_project(project_id: String) -> Option<ProjectData> {
+    let projects = PROJECTS.lock_recover();
+    projects.iter().find(|p| p.id == project_id).cloned()
+}
 
-    workstream
+/// Fields to change in `update_project`; those left out stay as they are
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// A path registers that directory as the vault; `null` detaches the vault
+    #[serde(default, deserialize_with = "sent")]
+    pub vault_path: Option<Option<String>>,
 }
 
+/// Deserialize a field that was sent, `null` included, as `Some`, so a
+/// missing field (`None` by default) can be told apart from a `null` one
+fn sent<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Edit a project; only the fields supplied change. A new vault path must be
+/// an existing directory and is registered in place of the old one, and the
+/// policy and config saved in it are loaded; a `null` one unregisters the
+/// vault and leaves its files alone. A new name must be unique like on
+/// create, unless `allow_duplicate`.
 #[tauri::command]
-pub fn send_message(
-    workstream_id: String,
-    content: String,
+#[allow(clippy::too_many_arguments)]
+pub fn update_project(
+    project_id: String,
+    changes: ProjectUpdate,
+    allow_duplicate: Option<bool>,
+    vault_manager: State<'_, VaultManager>,
     audit_store: State<'_, AuditStore>,
-) -> MessageData {
-    let message = MessageData {
-        id: Uuid::new_v4().to_string(),
-        role: "user".to_string(),
-        content: content.clone(),
-        timestamp: Utc::now().to_rfc3339(),
-        agent_id: None,
-        agent_name: None,
-    };
-
-    let mut workstreams = WORKSTREAMS.lock().unwrap();
-    if let Some(ws) = workstreams.iter_mut().find(|w| w.id == workstream_id) {
-        ws.messages.push(message.clone());
-
-        // Record user message in audit log
-        let event = AuditEvent {
-            id: Uuid::new_v4().to_string(),
-            timestamp: Utc::now().to_rfc3339(),
-            run_id: String::new(), // Will be populated by agent runtime
-            workstream_id: workstream_id.clone(),
-            project_id: ws.project_id.clone(),
-            event_type: "user.request".to_string(),
-            actor: crate::audit::AuditActor {
-                actor_type: "user".to_string(),
-                id: "user".to_string(),
-                role: None,
-                name: "User".to_string(),
-            },
-            payload: serde_json::json!({
-                "kind": "user.request",
-                "action": "request",
-                "content": content
-            }),
-            parent_event_id: None,
-            span_id: None,
-            trace_id: None,
-        };
-        audit_store.record_event(event);
+    policy_engine: State<'_, PolicyEngine>,
+    config_store: State<'_, ConfigStore>,
+) -> Result<ProjectData, CommandError> {
+    let updated = edit_project(
+        &project_id,
+        changes,
+        allow_duplicate.unwrap_or(false),
+        &vault_manager,
+        &audit_store,
+    )?;
+    if updated.vault_changed {
+        if let Some(root) = vault_manager.vault_root(&project_id) {
+            policy_engine.load_policy(&project_id, &root);
+        }
+        config_store.forget(&project_id);
+        project_config(&project_id, &config_store, &vault_manager, &audit_store)?;
+    }
+    Ok(updated.project)
+}
+
+struct EditedProject {
+    project: ProjectData,
+    vault_changed: bool,
+}
+
+/// Apply a `ProjectUpdate`. The changes are worked out and audited before
+/// any is applied; a newly registered vault is swapped back out if the
+/// audit fails.
+fn edit_project(
+    project_id: &str,
+    changes: ProjectUpdate,
+    allow_duplicate: bool,
+    vault_manager: &VaultManager,
+    audit_store: &AuditStore,
+) -> Result<EditedProject, CommandError> {
+    let ProjectUpdate {
+        name,
+        description,
+        vault_path,
+    } = changes;
+    let mut projects = PROJECTS.lock_recover();
+    if let Some(name) = &name {
+        if name.trim().is_empty() {
+            return Err(CommandError::invalid_input("Project name cannot be empty"));
+        }
+        if !allow_duplicate {
+            check_unique_name(&projects, name, Some(project_id))?;
+        }
+    }
+    let project = projects
+        .iter_mut()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| project_not_found(project_id))?;
+    let vault_path = match vault_path {
+        Some(Some(path)) if path != project.vault_path => Some(path),
+        Some(None) if !project.vault_path.is_empty() => Some(String::new()),
+        _ => None,
+    };
+
+    let mut changes = serde_json::Map::new();
+    for (field, current, new) in [
+        ("name", &project.name, &name),
+        ("description", &project.description, &description),
+        ("vault_path", &project.vault_path, &vault_path),
+    ] {
+        if let Some(new) = new.as_ref().filter(|&new| new != current) {
+            changes.insert(
+                field.to_string(),
+                serde_json::json!({ "from": current, "to": new }),
+            );
+        }
+    }
+    if changes.is_empty() {
+        return Ok(EditedProject {
+            project: project.clone(),
+            vault_changed: false,
+        });
+    }
+
+    let attaching = vault_path.as_deref().filter(|path| !path.is_empty());
+    if let Some(path) = attaching {
+        vault_manager.register_vault(&project.id, PathBuf::from(path))?;
     }
+    let recorded = audit_store.record_event(AuditEvent::new(
+        &project.id,
+        "",
+        "",
+        "project.updated",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "project",
+            "action": "update",
+            "changed": changes.keys().collect::<Vec<_>>(),
+            "changes": changes
+        }),
+    ));
+    if let Err(e) = recorded {
+        if attaching.is_some() {
+            restore_vault(vault_manager, project);
+        }
+        return Err(e.into());
+    }
+    if vault_path.as_deref() == Some("") {
+        vault_manager.unregister_vault(&project.id);
+    }
+
+    for (current, new) in [
+        (&mut project.name, name),
+        (&mut project.description, description),
+        (&mut project.vault_path, vault_path.clone()),
+    ] {
+        if let Some(new) = new {
+            *current = new;
+        }
+    }
+    let updated = project.clone();
+    save_projects(&projects);
+    Ok(EditedProject {
+        project: updated,
+        vault_changed: vault_path.is_some(),
+    })
+}
 
-    message
+/// Put a project's saved vault back after a failed switch
+fn restore_vault(vault_manager: &VaultManager, project: &ProjectData) {
+    if project.vault_path.is_empty() {
+        vault_manager.unregister_vault(&project.id);
+    } else if let Err(e) =
+        vault_manager.register_vault(&project.id, PathBuf::from(&project.vault_path))
+    {
+        tracing::warn!("Vault for project {} unavailable: {}", project.id, e);
+    }
 }
 
 #[tauri::command]
-pub fn get_audit_log(
+pub fn get_workstreams(project_id: String, include_archived: Option<bool>) -> Vec<WorkstreamData> {
+    let include_archived = include_archived.unwrap_or(false);
+    let workstreams = WORKSTREAMS.lock_recover();
+    workstreams
+        .iter()
+        .filter(|w| w.project_id == project_id && (include_archived || w.status != ARCHIVED))
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkstreamPage {
+    pub workstreams: Vec<WorkstreamData>,
+    pub total: usize,
+}
+
+/// Newest-first page of a project's workstreams, optionally only those with
+/// the given `status` and `phase`. Archived workstreams are left out unless
+/// `include_archived` is set or `status` asks for them.
+#[tauri::command]
+pub fn get_workstreams_paged(
     project_id: String,
+    status: Option<String>,
+    phase: Option<String>,
+    include_archived: Option<bool>,
+    offset: Option<usize>,
     limit: Option<usize>,
-    audit_store: State<'_, AuditStore>,
-) -> Vec<AuditEvent> {
-    audit_store.get_events(&project_id, limit.unwrap_or(100))
+) -> WorkstreamPage {
+    let include_archived = include_archived.unwrap_or(false) || status.as_deref() == Some(ARCHIVED);
+    let workstreams = WORKSTREAMS.lock_recover();
+
+    let mut matching: Vec<&WorkstreamData> = workstreams
+        .iter()
+        .filter(|w| w.project_id == project_id && (include_archived || w.status != ARCHIVED))
+        .filter(|w| status.as_deref().is_none_or(|s| s == w.status))
+        .filter(|w| phase.as_deref().is_none_or(|p| p == w.current_phase))
+        .collect();
+    matching.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    WorkstreamPage {
+        total: matching.len(),
+        workstreams: matching
+            .into_iter()
+            .skip(offset.unwrap_or(0))
+            .take(limit.unwrap_or(50))
+            .cloned()
+            .collect(),
+    }
 }
 
+/// A workstream by id. With `since` (RFC 3339), only messages sent after it
+/// are included, so a client can fetch just what it hasn't seen.
 #[tauri::command]
-pub fn get_policy(project_id: String, policy_engine: State<'_, PolicyEngine>) -> Option<NebulaPolicy> {
-    policy_engine.get_policy(&project_id)
+pub fn get_workstream(
+    workstream_id: String,
+    since: Option<String>,
+) -> Result<Option<WorkstreamData>, CommandError> {
+    let since = since
+        .map(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map_err(|e| CommandError::invalid_input(format!("Invalid since '{}': {}", s, e)))
+        })
+        .transpose()?;
+
+    let workstreams = WORKSTREAMS.lock_recover();
+    let Some(mut ws) = workstreams.iter().find(|w| w.id == workstream_id).cloned() else {
+        return Ok(None);
+    };
+    if let Some(since) = since {
+        ws.messages
+            .retain(|m| DateTime::parse_from_rfc3339(&m.timestamp).is_ok_and(|t| t > since));
+    }
+    Ok(Some(ws))
+}
+
+fn project_not_found(project_id: &str) -> CommandError {
+    CommandError::new(
+        ErrorCode::ProjectNotFound,
+        format!("Project not found: {}", project_id),
+    )
+}
+
+fn workstream_not_found(workstream_id: &str) -> CommandError {
+    CommandError::new(
+        ErrorCode::WorkstreamNotFound,
+        format!("Workstream not found: {}", workstream_id),
+    )
+}
+
+fn policy_not_found(project_id: &str) -> CommandError {
+    CommandError::new(
+        ErrorCode::PolicyNotFound,
+        format!("No policy found for project: {}", project_id),
+    )
 }
 
 #[tauri::command]
-pub fn update_policy(
+pub fn archive_project(
     project_id: String,
-    policy: NebulaPolicy,
-    policy_engine: State<'_, PolicyEngine>,
-) {
-    policy_engine.set_policy(&project_id, policy);
+    audit_store: State<'_, AuditStore>,
+) -> Result<ProjectData, CommandError> {
+    let mut projects = PROJECTS.lock_recover();
+    let project = projects
+        .iter_mut()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| project_not_found(&project_id))?;
+
+    audit_store.record_event(AuditEvent::new(
+        &project.id,
+        "",
+        "",
+        "project.archived",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "project",
+            "action": "archive",
+            "from_status": project.status
+        }),
+    ))?;
+    project.status = ARCHIVED.to_string();
+
+    let archived = project.clone();
+    save_projects(&projects);
+    Ok(archived)
 }
 
 #[tauri::command]
-pub fn read_vault_note(
-    project_id: String,
-    note_path: String,
+pub fn archive_workstream(
+    workstream_id: String,
+    audit_store: State<'_, AuditStore>,
     vault_manager: State<'_, VaultManager>,
-) -> Result<VaultNote, String> {
-    vault_manager
-        .read_note(&project_id, &note_path)
-        .map_err(|e| e.to_string())
+) -> Result<WorkstreamData, CommandError> {
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let ws = workstreams
+        .iter_mut()
+        .find(|w| w.id == workstream_id)
+        .ok_or_else(|| workstream_not_found(&workstream_id))?;
+
+    audit_store.record_event(AuditEvent::new(
+        &ws.project_id,
+        &ws.id,
+        ws.run_id.as_deref().unwrap_or_default(),
+        "workstream.archived",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "workstream",
+            "action": "archive",
+            "from_status": ws.status
+        }),
+    ))?;
+    ws.status = ARCHIVED.to_string();
+
+    let archived = ws.clone();
+    save_workstreams(&workstreams);
+    write_workstream_note(&vault_manager, &archived);
+    Ok(archived)
 }
 
+/// Remove a project and all of its workstreams. Refused while any of its
+/// workstreams is not archived, so live work can't be dropped by accident.
+/// The audit log and the vault on disk are kept.
 #[tauri::command]
-pub fn write_vault_note(
+pub fn delete_project(
     project_id: String,
-    note_path: String,
-    frontmatter: HashMap<String, serde_json::Value>,
-    content: String,
-    vault_manager: State<'_, VaultManager>,
-) -> Result<(), String> {
-    vault_manager
-        .write_note(&project_id, &note_path, &frontmatter, &content)
-        .map_err(|e| e.to_string())
+    audit_store: State<'_, AuditStore>,
+) -> Result<(), CommandError> {
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let mut projects = PROJECTS.lock_recover();
+    let index = projects
+        .iter()
+        .position(|p| p.id == project_id)
+        .ok_or_else(|| project_not_found(&project_id))?;
+
+    let active: Vec<&str> = workstreams
+        .iter()
+        .filter(|w| w.project_id == project_id && w.status != ARCHIVED)
+        .map(|w| w.id.as_str())
+        .collect();
+    if !active.is_empty() {
+        return Err(CommandError::new(
+            ErrorCode::ProjectNotEmpty,
+            format!(
+                "Project {} still has {} active workstream(s); archive them first",
+                project_id,
+                active.len()
+            ),
+        )
+        .with_details(serde_json::json!({ "active_workstreams": active })));
+    }
+
+    let removed: Vec<String> = workstreams
+        .iter()
+        .filter(|w| w.project_id == project_id)
+        .map(|w| w.id.clone())
+        .collect();
+    audit_store.record_event(AuditEvent::new(
+        &project_id,
+        "",
+        "",
+        "project.deleted",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "project",
+            "action": "delete",
+            "name": projects[index].name,
+            "removed_workstreams": removed
+        }),
+    ))?;
+
+    workstreams.retain(|w| w.project_id != project_id);
+    projects.remove(index);
+    save_workstreams(&workstreams);
+    save_projects(&projects);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn list_vault_notes(
-    project_id: String,
-    directory: String,
-    vault_manager: State<'_, VaultManager>,
-) -> Result<Vec<VaultNoteEntry>, String> {
-    vault_manager
-        .list_notes(&project_id, &directory)
-        .map_err(|e| e.to_string())
+pub fn delete_workstream(
+    workstream_id: String,
+    audit_store: State<'_, AuditStore>,
+) -> Result<(), CommandError> {
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let index = workstreams
+        .iter()
+        .position(|w| w.id == workstream_id)
+        .ok_or_else(|| workstream_not_found(&workstream_id))?;
+
+    let ws = &workstreams[index];
+    audit_store.record_event(AuditEvent::new(
+        &ws.project_id,
+        &ws.id,
+        ws.run_id.as_deref().unwrap_or_default(),
+        "workstream.deleted",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "workstream",
+            "action": "delete",
+            "title": ws.title
+        }),
+    ))?;
+
+    let ws = workstreams.remove(index);
+    save_workstreams(&workstreams);
+
+    let mut projects = PROJECTS.lock_recover();
+    if let Some(project) = projects.iter_mut().find(|p| p.id == ws.project_id) {
+        project.workstreams.retain(|id| *id != ws.id);
+        save_projects(&projects);
+    }
+    Ok(())
 }
 
 #[tauri::command]
-pub fn get_run_record(run_id: String, audit_store: State<'_, AuditStore>) -> Option<RunRecord> {
-    audit_store.get_run(&run_id)
+pub fn create_workstream(
+    project_id: String,
+    title: String,
+    user_request: String,
+    audit_store: State<'_, AuditStore>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<WorkstreamData, CommandError> {
+    let workstream_id = Uuid::new_v4().to_string();
+    let current_phase = "design".to_string();
+
+    // Create a run for this workstream
+    let run_id =
+        audit_store.create_run(&project_id, &workstream_id, &current_phase, &user_request)?;
+
+    let workstream = WorkstreamData {
+        id: workstream_id,
+        project_id: project_id.clone(),
+        title,
+        description: String::new(),
+        status: "draft".to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        user_request,
+        current_phase,
+        messages: Vec::new(),
+        run_id: Some(run_id),
+    };
+
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    workstreams.push(workstream.clone());
+    save_workstreams(&workstreams);
+    write_workstream_note(&vault_manager, &workstream);
+
+    // Update project workstream list
+    let mut projects = PROJECTS.lock_recover();
+    if let Some(project) = projects.iter_mut().find(|p| p.id == project_id) {
+        project.workstreams.push(workstream.id.clone());
+        save_projects(&projects);
+    }
+
+    Ok(workstream)
+}
+
+/// Start a new workstream from an existing one: same description and
+/// request, back at the first phase, with a new run. Messages are copied
+/// only with `copy_messages`. The `workstream.duplicated` event names the
+/// source and hangs off the start of the source's run.
+#[tauri::command]
+pub fn duplicate_workstream(
+    workstream_id: String,
+    new_title: String,
+    copy_messages: Option<bool>,
+    audit_store: State<'_, AuditStore>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<WorkstreamData, CommandError> {
+    duplicate(
+        &workstream_id,
+        new_title,
+        copy_messages.unwrap_or(false),
+        &audit_store,
+        &vault_manager,
+    )
+}
+
+fn duplicate(
+    workstream_id: &str,
+    new_title: String,
+    copy_messages: bool,
+    audit_store: &AuditStore,
+    vault_manager: &VaultManager,
+) -> Result<WorkstreamData, CommandError> {
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let source = workstreams
+        .iter()
+        .find(|w| w.id == workstream_id)
+        .cloned()
+        .ok_or_else(|| workstream_not_found(workstream_id))?;
+
+    let id = Uuid::new_v4().to_string();
+    let current_phase = phase::PHASES[0].to_string();
+    let run_id = audit_store.create_run(
+        &source.project_id,
+        &id,
+        &current_phase,
+        &source.user_request,
+    )?;
+
+    let messages = if copy_messages {
+        source
+            .messages
+            .iter()
+            .map(|m| MessageData {
+                id: Uuid::new_v4().to_string(),
+                ..m.clone()
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let workstream = WorkstreamData {
+        id,
+        project_id: source.project_id.clone(),
+        title: new_title,
+        description: source.description.clone(),
+        status: "draft".to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        user_request: source.user_request.clone(),
+        current_phase,
+        messages,
+        run_id: Some(run_id.clone()),
+    };
+
+    let mut event = AuditEvent::new(
+        &workstream.project_id,
+        &workstream.id,
+        &run_id,
+        "workstream.duplicated",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "workstream",
+            "action": "duplicate",
+            "source_workstream_id": source.id,
+            "source_run_id": source.run_id,
+            "messages_copied": workstream.messages.len()
+        }),
+    );
+    event.parent_event_id = source
+        .run_id
+        .as_deref()
+        .and_then(|run| audit_store.run_start_event(run))
+        .map(|start| start.id);
+    audit_store.record_event(event)?;
+
+    workstreams.push(workstream.clone());
+    save_workstreams(&workstreams);
+    write_workstream_note(vault_manager, &workstream);
+
+    let mut projects = PROJECTS.lock_recover();
+    if let Some(project) = projects.iter_mut().find(|p| p.id == workstream.project_id) {
+        project.workstreams.push(workstream.id.clone());
+        save_projects(&projects);
+    }
+
+    Ok(workstream)
+}
+
+/// Move a workstream back to the same or an earlier working phase, or out
+/// of a terminal state to any phase. This is the only sanctioned backward
+/// transition; a run still going is cancelled and a fresh run is started for
+/// the new iteration. Moving forward is `transition_phase`'s job.
+#[tauri::command]
+pub fn reopen_workstream(
+    workstream_id: String,
+    to_phase: String,
+    reason: Option<String>,
+    audit_store: State<'_, AuditStore>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<WorkstreamData, CommandError> {
+    reopen(
+        &workstream_id,
+        to_phase,
+        reason.unwrap_or_default(),
+        &audit_store,
+        &vault_manager,
+    )
+}
+
+fn reopen(
+    workstream_id: &str,
+    to_phase: String,
+    reason: String,
+    audit_store: &AuditStore,
+    vault_manager: &VaultManager,
+) -> Result<WorkstreamData, CommandError> {
+    if !phase::is_working_phase(&to_phase) {
+        return Err(CommandError::invalid_input(format!(
+            "Unknown phase '{}'; expected one of {}",
+            to_phase,
+            phase::PHASES.join(", ")
+        )));
+    }
+
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let ws = workstreams
+        .iter_mut()
+        .find(|w| w.id == workstream_id)
+        .ok_or_else(|| workstream_not_found(workstream_id))?;
+
+    let was_terminal =
+        ws.current_phase == phase::COMPLETED || phase::is_terminal_status(&ws.status);
+    if !was_terminal && phase::is_after(&to_phase, &ws.current_phase) {
+        return Err(CommandError::invalid_input(format!(
+            "Cannot reopen '{}' forward to '{}'; advance with transition_phase",
+            ws.current_phase, to_phase
+        )));
+    }
+
+    if let Some(previous) = ws.run_id.as_deref() {
+        let running = audit_store
+            .get_run(previous)
+            .is_some_and(|run| run.completed_at.is_none());
+        if running {
+            audit_store.cancel_run(
+                previous,
+                &format!("Reopened at {}", to_phase),
+                AuditActor::user(),
+            )?;
+        }
+    }
+    let run_id = audit_store.create_run(&ws.project_id, &ws.id, &to_phase, &reason)?;
+
+    audit_store.record_event(AuditEvent::new(
+        &ws.project_id,
+        &ws.id,
+        &run_id,
+        "workstream.reopened",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "workstream.transition",
+            "action": "reopen",
+            "from_phase": ws.current_phase,
+            "from_status": ws.status,
+            "was_terminal": was_terminal,
+            "to_phase": to_phase,
+            "previous_run_id": ws.run_id,
+            "reason": reason
+        }),
+    ))?;
+
+    ws.status = phase::status_for_phase(&to_phase).to_string();
+    ws.current_phase = to_phase;
+    ws.run_id = Some(run_id);
+
+    let reopened = ws.clone();
+    save_workstreams(&workstreams);
+    write_workstream_note(vault_manager, &reopened);
+    Ok(reopened)
+}
+
+/// Advance a workstream along the phase machine. The outgoing phase's run is
+/// completed and, unless the workstream is now done, a run for the new phase
+/// is started.
+#[tauri::command]
+pub fn transition_phase(
+    workstream_id: String,
+    to_phase: String,
+    audit_store: State<'_, AuditStore>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<WorkstreamData, CommandError> {
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let ws = workstreams
+        .iter_mut()
+        .find(|w| w.id == workstream_id)
+        .ok_or_else(|| workstream_not_found(&workstream_id))?;
+
+    let machine = phase::PhaseMachine::default();
+    machine
+        .check(&ws.current_phase, &to_phase)
+        .map_err(|reason| {
+            CommandError::invalid_input(reason).with_details(serde_json::json!({
+                "allowed": machine.allowed_from(&ws.current_phase)
+            }))
+        })?;
+
+    let previous_run_id = ws.run_id.clone().unwrap_or_default();
+    audit_store.record_event(AuditEvent::new(
+        &ws.project_id,
+        &ws.id,
+        &previous_run_id,
+        "workstream.phase_changed",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "workstream.transition",
+            "action": "advance",
+            "from_phase": ws.current_phase,
+            "to_phase": to_phase
+        }),
+    ))?;
+
+    let running = audit_store
+        .get_run(&previous_run_id)
+        .is_some_and(|run| run.completed_at.is_none());
+    if running {
+        audit_store.complete_run(&previous_run_id, "completed")?;
+    }
+
+    if to_phase == phase::COMPLETED {
+        ws.status = phase::COMPLETED.to_string();
+    } else {
+        let run_id = audit_store.create_run(&ws.project_id, &ws.id, &to_phase, "")?;
+        ws.run_id = Some(run_id);
+        ws.status = phase::status_for_phase(&to_phase).to_string();
+    }
+    ws.current_phase = to_phase;
+
+    let updated = ws.clone();
+    save_workstreams(&workstreams);
+    write_workstream_note(&vault_manager, &updated);
+    Ok(updated)
+}
+
+/// Set a workstream's status. Moving to a terminal status completes the
+/// workstream's current run with the same status.
+#[tauri::command]
+pub fn update_workstream_status(
+    workstream_id: String,
+    status: String,
+    audit_store: State<'_, AuditStore>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<WorkstreamData, CommandError> {
+    if !phase::STATUSES.contains(&status.as_str()) {
+        return Err(CommandError::invalid_input(format!(
+            "Unknown status '{}'; expected one of {}",
+            status,
+            phase::STATUSES.join(", ")
+        )));
+    }
+
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let ws = workstreams
+        .iter_mut()
+        .find(|w| w.id == workstream_id)
+        .ok_or_else(|| workstream_not_found(&workstream_id))?;
+
+    let run_id = ws.run_id.clone().unwrap_or_default();
+    audit_store.record_event(AuditEvent::new(
+        &ws.project_id,
+        &ws.id,
+        &run_id,
+        "workstream.status_changed",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "workstream.transition",
+            "action": "status",
+            "from_status": ws.status,
+            "to_status": status
+        }),
+    ))?;
+
+    if phase::is_terminal_status(&status) {
+        let running = audit_store
+            .get_run(&run_id)
+            .is_some_and(|run| run.completed_at.is_none());
+        if running {
+            audit_store.complete_run(&run_id, &status)?;
+        }
+        if status == phase::COMPLETED {
+            ws.current_phase = phase::COMPLETED.to_string();
+        }
+    }
+    ws.status = status;
+
+    let updated = ws.clone();
+    save_workstreams(&workstreams);
+    write_workstream_note(&vault_manager, &updated);
+    Ok(updated)
+}
+
+/// Post a message as the user. Empty content and content over the
+/// project's `max_message_length` are rejected.
+#[tauri::command]
+pub fn send_message(
+    workstream_id: String,
+    content: String,
+    audit_store: State<'_, AuditStore>,
+    policy_engine: State<'_, PolicyEngine>,
+    vault_manager: State<'_, VaultManager>,
+    config_store: State<'_, ConfigStore>,
+) -> Result<MessageData, CommandError> {
+    append_message(
+        &workstream_id,
+        &content,
+        None,
+        &audit_store,
+        &policy_engine,
+        &vault_manager,
+        &config_store,
+    )
+}
+
+/// Post a message as an agent. The role must be allowed `chat.post` on the
+/// workstream under the project's policy.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn send_agent_message(
+    workstream_id: String,
+    agent_id: String,
+    agent_name: String,
+    agent_role: String,
+    content: String,
+    audit_store: State<'_, AuditStore>,
+    policy_engine: State<'_, PolicyEngine>,
+    vault_manager: State<'_, VaultManager>,
+    config_store: State<'_, ConfigStore>,
+) -> Result<MessageData, CommandError> {
+    let project_id = WORKSTREAMS
+        .lock_recover()
+        .iter()
+        .find(|w| w.id == workstream_id)
+        .map(|w| w.project_id.clone())
+        .ok_or_else(|| workstream_not_found(&workstream_id))?;
+
+    let decision = decide_permission(
+        &project_id,
+        &workstream_id,
+        &[agent_role.as_str()],
+        "chat.post",
+        "write",
+        &workstream_id,
+        &policy_engine,
+        &audit_store,
+    )?;
+    if !decision.allowed {
+        return Err(CommandError::permission_denied(decision.reason));
+    }
+
+    let actor = AuditActor {
+        role: Some(agent_role),
+        ..AuditActor::agent(&agent_id, &agent_name)
+    };
+    append_message(
+        &workstream_id,
+        &content,
+        Some(actor),
+        &audit_store,
+        &policy_engine,
+        &vault_manager,
+        &config_store,
+    )
+}
+
+/// Store a conversation message and its audit event, attributed to the
+/// workstream's current run, and append it to the workstream's vault note.
+/// Content is checked against the project's length limit and redacted under
+/// its policy first, so the raw text is never persisted.
+fn append_message(
+    workstream_id: &str,
+    content: &str,
+    agent: Option<AuditActor>,
+    audit_store: &AuditStore,
+    policy_engine: &PolicyEngine,
+    vault_manager: &VaultManager,
+    config_store: &ConfigStore,
+) -> Result<MessageData, CommandError> {
+    if content.trim().is_empty() {
+        return Err(CommandError::invalid_input("Message is empty"));
+    }
+
+    let mut workstreams = WORKSTREAMS.lock_recover();
+    let ws = workstreams
+        .iter_mut()
+        .find(|w| w.id == workstream_id)
+        .ok_or_else(|| workstream_not_found(workstream_id))?;
+
+    let max_length = project_config(&ws.project_id, config_store, vault_manager, audit_store)?
+        .max_message_length();
+    let length = content.chars().count();
+    if length > max_length {
+        return Err(CommandError::invalid_input(format!(
+            "Message is {} characters, over the limit of {}",
+            length, max_length
+        ))
+        .with_details(serde_json::json!({ "length": length, "max_length": max_length })));
+    }
+
+    let (content, redacted) = policy_engine.redact_message(&ws.project_id, content);
+
+    let message = MessageData {
+        id: Uuid::new_v4().to_string(),
+        role: if agent.is_some() { "agent" } else { "user" }.to_string(),
+        content,
+        timestamp: Utc::now().to_rfc3339(),
+        agent_id: agent.as_ref().map(|a| a.id.clone()),
+        agent_name: agent.as_ref().map(|a| a.name.clone()),
+        redacted,
+    };
+
+    ws.messages.push(message.clone());
+    adopt_latest_run(ws, audit_store);
+
+    let (event_type, action, actor) = match agent {
+        Some(actor) => ("agent.message", "respond", actor),
+        None => ("user.request", "request", AuditActor::user()),
+    };
+    let event = AuditEvent::new(
+        &ws.project_id,
+        workstream_id,
+        ws.run_id.as_deref().unwrap_or_default(),
+        event_type,
+        actor,
+        serde_json::json!({
+            "kind": event_type,
+            "action": action,
+            "content": message.content,
+            "redacted": redacted
+        }),
+    );
+    audit_store.record_event(event)?;
+    append_transcript(vault_manager, ws, &message);
+    save_workstreams(&workstreams);
+
+    Ok(message)
+}
+
+/// Mirror a workstream to its vault note. The app data dir stays the primary
+/// copy, so a failed vault write is logged rather than failing the command.
+fn write_workstream_note(vault_manager: &VaultManager, ws: &WorkstreamData) {
+    if vault_manager.vault_root(&ws.project_id).is_none() {
+        return;
+    }
+    if let Err(e) = vault_manager.write_note(
+        &ws.project_id,
+        &transcript::note_path(&ws.id),
+        &transcript::frontmatter(ws),
+        &transcript::render_body(ws),
+        None,
+        None,
+    ) {
+        tracing::warn!("Failed to write workstream note for {}: {}", ws.id, e);
+    }
+}
+
+/// Append a message to the workstream's note, writing the whole note if it
+/// doesn't exist yet (e.g. the vault was registered after the workstream)
+fn append_transcript(vault_manager: &VaultManager, ws: &WorkstreamData, message: &MessageData) {
+    if vault_manager.vault_root(&ws.project_id).is_none() {
+        return;
+    }
+    match vault_manager.append_note(
+        &ws.project_id,
+        &transcript::note_path(&ws.id),
+        &transcript::render_message(message),
+    ) {
+        Ok(()) => {}
+        Err(VaultError::NoteNotFound(_)) => write_workstream_note(vault_manager, ws),
+        Err(e) => tracing::warn!("Failed to append to workstream note for {}: {}", ws.id, e),
+    }
+}
+
+#[tauri::command]
+pub fn get_audit_log(
+    project_id: String,
+    limit: Option<usize>,
+    timezone: Option<String>,
+    format: Option<String>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<Vec<AuditEvent>, CommandError> {
+    let mut events = audit_store.get_events(&project_id, limit.unwrap_or(100));
+
+    // Storage stays UTC; only the returned copies are reformatted
+    if timezone.is_some() || format.is_some() {
+        for event in &mut events {
+            event.timestamp = AuditStore::format_timestamp(
+                &event.timestamp,
+                timezone.as_deref(),
+                format.as_deref(),
+            )
+            .map_err(CommandError::invalid_input)?;
+        }
+    }
+
+    Ok(events)
+}
+
+#[tauri::command]
+pub fn query_events(
+    project_id: String,
+    filter: EventFilter,
+    audit_store: State<'_, AuditStore>,
+) -> EventQueryResult {
+    audit_store.query_events(&project_id, &filter)
+}
+
+/// Find a project's events by a value in their payload, newest first.
+/// `json_path` is a dotted selector like `commit.sha` or `files[*].path`;
+/// `match_mode` is `exact` (the default) or `contains`.
+#[tauri::command]
+pub fn search_audit(
+    project_id: String,
+    json_path: String,
+    value: String,
+    match_mode: Option<MatchMode>,
+    event_type: Option<String>,
+    limit: Option<usize>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<Vec<AuditEvent>, CommandError> {
+    Ok(audit_store.search_payloads(
+        &project_id,
+        &json_path,
+        &value,
+        match_mode.unwrap_or_default(),
+        event_type.as_deref(),
+        limit,
+    )?)
+}
+
+/// Page back through a project's events; pass the returned `next_cursor`
+/// as `before` to get the next older page
+#[tauri::command]
+pub fn get_audit_log_page(
+    project_id: String,
+    before: Option<String>,
+    limit: Option<usize>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<EventPage, CommandError> {
+    Ok(audit_store.get_events_page(&project_id, before.as_deref(), limit.unwrap_or(100))?)
+}
+
+#[tauri::command]
+pub fn get_workstream_runs(
+    workstream_id: String,
+    audit_store: State<'_, AuditStore>,
+) -> Vec<RunRecord> {
+    audit_store.runs_for_workstream(&workstream_id)
+}
+
+/// Test, deployment, and duration totals across a project's runs
+#[tauri::command]
+pub fn get_project_stats(project_id: String, audit_store: State<'_, AuditStore>) -> ProjectStats {
+    audit_store.project_stats(&project_id)
+}
+
+#[tauri::command]
+pub fn search_runs(query: RunQuery, audit_store: State<'_, AuditStore>) -> Vec<RunRecord> {
+    audit_store.search_runs(&query)
+}
+
+/// Switch the audit log to another storage backend. The backend holds every
+/// project's history, so every project's policy must grant the role
+/// `audit.migrate`; the migration is then recorded in each project.
+#[tauri::command]
+pub fn migrate_audit_backend(
+    requested_by_role: String,
+    backend: AuditBackendKind,
+    audit_store: State<'_, AuditStore>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<MigrationReport, CommandError> {
+    let project_ids: Vec<String> = PROJECTS
+        .lock_recover()
+        .iter()
+        .map(|p| p.id.clone())
+        .collect();
+    authorize_audit_migration(&project_ids, &requested_by_role, &policy_engine)?;
+
+    let report = audit_store.migrate_to(backend)?;
+    for project_id in &project_ids {
+        audit_store.record_event(AuditEvent::new(
+            project_id,
+            "",
+            "",
+            "audit.migrated",
+            AuditActor::agent_role(&requested_by_role),
+            serde_json::json!({
+                "kind": "audit.maintenance",
+                "action": "migrate",
+                "from": report.from,
+                "to": report.to,
+                "events_migrated": report.events_migrated,
+                "runs_migrated": report.runs_migrated
+            }),
+        ))?;
+    }
+
+    Ok(report)
+}
+
+/// Require `audit.migrate` from every project's policy. With no projects
+/// there is no policy to grant it.
+fn authorize_audit_migration(
+    project_ids: &[String],
+    role: &str,
+    policy_engine: &PolicyEngine,
+) -> Result<(), CommandError> {
+    if project_ids.is_empty() {
+        return Err(CommandError::permission_denied(
+            "No project policy grants audit.migrate",
+        ));
+    }
+    for project_id in project_ids {
+        let decision = policy_engine.evaluate_permission(
+            project_id,
+            &[role],
+            "audit.migrate",
+            "write",
+            "audit",
+        );
+        if !decision.allowed {
+            return Err(CommandError::permission_denied(format!(
+                "Project {}: {}",
+                project_id, decision.reason
+            ))
+            .with_details(serde_json::json!({ "project_id": project_id })));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn verify_audit_chain(
+    audit_store: State<'_, AuditStore>,
+) -> Result<ChainVerification, CommandError> {
+    Ok(audit_store.verify_chain()?)
+}
+
+#[tauri::command]
+pub fn get_audit_backend(audit_store: State<'_, AuditStore>) -> AuditBackendKind {
+    audit_store.backend_kind()
+}
+
+#[tauri::command]
+pub fn get_event_histogram(
+    project_id: String,
+    from: Option<String>,
+    to: Option<String>,
+    event_type_prefix: Option<String>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<HashMap<String, u32>, CommandError> {
+    let parse = |ts: &str| {
+        DateTime::parse_from_rfc3339(ts)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| CommandError::invalid_input(format!("Invalid timestamp '{}': {}", ts, e)))
+    };
+    let time_range = match (from.as_deref(), to.as_deref()) {
+        (None, None) => None,
+        (from, to) => Some((
+            from.map(parse)
+                .transpose()?
+                .unwrap_or(DateTime::<Utc>::MIN_UTC),
+            to.map(parse)
+                .transpose()?
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        )),
+    };
+
+    Ok(audit_store.event_type_histogram(&project_id, time_range, event_type_prefix.as_deref()))
+}
+
+#[tauri::command]
+pub fn import_audit_log(
+    jsonl: String,
+    verify: Option<bool>,
+    merge: Option<bool>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<ImportReport, CommandError> {
+    let mut events = Vec::new();
+    for (line_no, line) in jsonl.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: AuditEvent = serde_json::from_str(line).map_err(|e| {
+            CommandError::invalid_input(format!("Line {}: {}", line_no + 1, e))
+                .with_details(serde_json::json!({ "line": line_no + 1 }))
+        })?;
+        events.push(event);
+    }
+
+    audit_store
+        .import_events(events, verify.unwrap_or(true), merge.unwrap_or(false))
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn dedup_audit_log(
+    project_id: String,
+    requested_by_role: String,
+    audit_store: State<'_, AuditStore>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<DedupReport, CommandError> {
+    let decision = policy_engine.evaluate_permission(
+        &project_id,
+        &[&requested_by_role],
+        "audit.dedup",
+        "delete",
+        "audit",
+    );
+    if !decision.allowed {
+        return Err(CommandError::permission_denied(decision.reason));
+    }
+
+    Ok(audit_store.dedup(&project_id, AuditActor::agent_role(&requested_by_role))?)
+}
+
+#[tauri::command]
+pub fn export_signed_run(
+    run_id: String,
+    format: Option<ExportFormat>,
+    audit_store: State<'_, AuditStore>,
+    key_store: State<'_, SigningKeyStore>,
+) -> Result<SignedExport, CommandError> {
+    let signing_key = key_store.signing_key().map_err(CommandError::internal)?;
+    Ok(audit_store.export_signed(&run_id, format.unwrap_or(ExportFormat::Jsonl), &signing_key)?)
+}
+
+#[tauri::command]
+pub fn get_event_tree(
+    run_id: String,
+    audit_store: State<'_, AuditStore>,
+) -> Result<Vec<EventNode>, CommandError> {
+    Ok(audit_store.event_tree(&run_id)?)
+}
+
+/// Render a run's full audit trail as JSON or Markdown, also writing it to
+/// `output_path` (typically picked with a save dialog) when one is given
+#[tauri::command]
+pub fn export_run_report(
+    run_id: String,
+    format: Option<ReportFormat>,
+    output_path: Option<String>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<String, CommandError> {
+    let report = audit_store
+        .export_run(&run_id)?
+        .render(format.unwrap_or(ReportFormat::Json))
+        .map_err(|e| CommandError::internal(e.to_string()))?;
+
+    if let Some(path) = output_path {
+        persist::write_atomic(Path::new(&path), report.as_bytes())
+            .map_err(|e| CommandError::new(ErrorCode::IoError, e.to_string()))?;
+    }
+
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn verify_exported_run(content: String, signature: String, public_key: String) -> bool {
+    signing::verify_export(content.as_bytes(), &signature, &public_key)
+}
+
+#[tauri::command]
+pub fn get_audit_signing_key(
+    key_store: State<'_, SigningKeyStore>,
+) -> Result<String, CommandError> {
+    key_store.public_key_hex().map_err(CommandError::internal)
+}
+
+#[tauri::command]
+pub fn get_audit_emission_config(
+    project_id: String,
+    audit_store: State<'_, AuditStore>,
+) -> EmissionConfig {
+    audit_store.emission_config(&project_id)
+}
+
+#[tauri::command]
+pub fn configure_audit_emission(
+    project_id: String,
+    config: EmissionConfig,
+    config_store: State<'_, ConfigStore>,
+    vault_manager: State<'_, VaultManager>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<(), CommandError> {
+    update_project_config(
+        &project_id,
+        serde_json::json!({ "emission": config }),
+        &config_store,
+        &vault_manager,
+        &audit_store,
+    )
+    .map(|_| ())
+}
+
+#[tauri::command]
+pub fn get_config(
+    project_id: String,
+    config_store: State<'_, ConfigStore>,
+    vault_manager: State<'_, VaultManager>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<ProjectConfig, CommandError> {
+    project_config(&project_id, &config_store, &vault_manager, &audit_store)
+}
+
+/// The project's config, pushed into the subsystems that use it if this
+/// was its first load
+fn project_config(
+    project_id: &str,
+    config_store: &ConfigStore,
+    vault_manager: &VaultManager,
+    audit_store: &AuditStore,
+) -> Result<ProjectConfig, CommandError> {
+    let root = vault_manager.vault_root(project_id);
+    let (config, loaded) = config_store.get_or_load(project_id, root.as_deref());
+    if loaded {
+        apply_config(project_id, &config, vault_manager, audit_store)?;
+    }
+    Ok(config)
+}
+
+#[tauri::command]
+pub fn update_config(
+    project_id: String,
+    patch: serde_json::Value,
+    config_store: State<'_, ConfigStore>,
+    vault_manager: State<'_, VaultManager>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<ProjectConfig, CommandError> {
+    update_project_config(
+        &project_id,
+        patch,
+        &config_store,
+        &vault_manager,
+        &audit_store,
+    )
+}
+
+/// Validate, persist, and apply a config patch, auditing the change
+fn update_project_config(
+    project_id: &str,
+    patch: serde_json::Value,
+    config_store: &ConfigStore,
+    vault_manager: &VaultManager,
+    audit_store: &AuditStore,
+) -> Result<ProjectConfig, CommandError> {
+    let root = vault_manager.vault_root(project_id);
+    let config = config_store.update(project_id, &patch, root.as_deref())?;
+    apply_config(project_id, &config, vault_manager, audit_store)?;
+
+    audit_store.record_event(AuditEvent::new(
+        project_id,
+        "",
+        "",
+        "config.updated",
+        AuditActor::user(),
+        serde_json::json!({
+            "kind": "config",
+            "action": "update",
+            "patch": patch
+        }),
+    ))?;
+
+    Ok(config)
+}
+
+/// Push a project's settings into the subsystems that consume them
+fn apply_config(
+    project_id: &str,
+    config: &ProjectConfig,
+    vault_manager: &VaultManager,
+    audit_store: &AuditStore,
+) -> Result<(), CommandError> {
+    vault_manager.set_level_rules(project_id, config.template_levels.clone())?;
+    vault_manager.set_ignore_patterns(project_id, &config.ignore_patterns)?;
+    audit_store.configure_emission(project_id, config.emission.clone());
+    audit_store.set_retention(project_id, config.audit_retention_days);
+    Ok(())
+}
+
+/// Load every project's saved config at startup so settings like audit
+/// retention apply before the project is opened
+pub fn load_configs(
+    config_store: &ConfigStore,
+    vault_manager: &VaultManager,
+    audit_store: &AuditStore,
+) {
+    let project_ids: Vec<String> = PROJECTS
+        .lock_recover()
+        .iter()
+        .map(|p| p.id.clone())
+        .collect();
+    for project_id in project_ids {
+        let root = vault_manager.vault_root(&project_id);
+        let (config, loaded) = config_store.get_or_load(&project_id, root.as_deref());
+        if loaded {
+            if let Err(e) = apply_config(&project_id, &config, vault_manager, audit_store) {
+                tracing::warn!("Failed to apply config for {}: {}", project_id, e);
+            }
+        }
+    }
+}
+
+/// Remove audit events past their project's retention window now, rather
+/// than waiting for the scheduled prune. Returns how many were removed.
+#[tauri::command]
+pub fn prune_audit_log(audit_store: State<'_, AuditStore>) -> Result<u32, CommandError> {
+    Ok(audit_store.prune(Utc::now())?)
+}
+
+#[tauri::command]
+pub fn get_policy(project_id: String, policy_engine: State<'_, PolicyEngine>) -> Option<NebulaPolicy> {
+    policy_engine.get_policy(&project_id)
+}
+
+/// Validate and save a new policy version, persisted under the project's vault when it has one.
+/// With `expected_version`, fails with a conflict if the stored version is no longer that one.
+#[tauri::command]
+pub fn update_policy(
+    project_id: String,
+    policy: NebulaPolicy,
+    expected_version: Option<u64>,
+    policy_engine: State<'_, PolicyEngine>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<NebulaPolicy, CommandError> {
+    if let Err(errors) = policy.validate() {
+        return Err(
+            CommandError::new(ErrorCode::InvalidPolicy, "Policy failed validation")
+                .with_details(serde_json::json!(errors)),
+        );
+    }
+
+    let root = vault_manager.vault_root(&project_id);
+    Ok(policy_engine.set_policy(&project_id, policy, expected_version, root.as_deref())?)
+}
+
+/// Load a project's policy from a `.json`, `.yaml`, or `.yml` file, e.g. one
+/// kept under version control, validate it, and save it as a new version.
+/// With `expected_version` the import fails if the policy changed since.
+#[tauri::command]
+pub fn import_policy_from_file(
+    project_id: String,
+    path: String,
+    expected_version: Option<u64>,
+    policy_engine: State<'_, PolicyEngine>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<NebulaPolicy, CommandError> {
+    let path = PathBuf::from(path);
+    let format = PolicyFormat::from_path(&path)?;
+    let content = std::fs::read_to_string(&path).map_err(PolicyFileError::from)?;
+    let mut policy = format.parse(&content)?;
+    policy.project_id.clone_from(&project_id);
+
+    if let Err(errors) = policy.validate() {
+        return Err(
+            CommandError::new(ErrorCode::InvalidPolicy, "Policy failed validation")
+                .with_details(serde_json::json!(errors)),
+        );
+    }
+    let root = vault_manager.vault_root(&project_id);
+    Ok(policy_engine.set_policy(&project_id, policy, expected_version, root.as_deref())?)
+}
+
+/// Write a project's current policy to a `.json`, `.yaml`, or `.yml` file
+#[tauri::command]
+pub fn export_policy_to_file(
+    project_id: String,
+    path: String,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<(), CommandError> {
+    let path = PathBuf::from(path);
+    let format = PolicyFormat::from_path(&path)?;
+    let policy = policy_engine
+        .get_policy(&project_id)
+        .ok_or_else(|| policy_not_found(&project_id))?;
+    let rendered = format.render(&policy)?;
+    persist::write_atomic(&path, rendered.as_bytes()).map_err(PolicyFileError::from)?;
+    Ok(())
+}
+
+/// Compare two stored versions of a project's policy
+#[tauri::command]
+pub fn diff_policy(
+    project_id: String,
+    from_version: u64,
+    to_version: u64,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<PolicyDiff, CommandError> {
+    let root = vault_manager
+        .vault_root(&project_id)
+        .ok_or_else(|| VaultError::VaultNotFound(project_id.clone()))?;
+
+    let load = |version| {
+        PolicyEngine::load_version(&root, version)
+            .map_err(|e| CommandError::new(ErrorCode::IoError, e.to_string()))?
+            .ok_or_else(|| {
+                CommandError::new(
+                    ErrorCode::PolicyVersionNotFound,
+                    format!("Policy version {} not found", version),
+                )
+            })
+    };
+    let from = load(from_version)?;
+    let to = load(to_version)?;
+
+    Ok(PolicyDiff {
+        from_version,
+        to_version,
+        changes: policy::diff_policies(&from, &to),
+    })
+}
+
+#[tauri::command]
+pub fn preview_redaction(
+    project_id: String,
+    sample: String,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<RedactionResult, CommandError> {
+    policy_engine
+        .redact_detailed(&project_id, &sample)
+        .ok_or_else(|| policy_not_found(&project_id))
+}
+
+#[tauri::command]
+pub fn check_provider_access(
+    project_id: String,
+    provider: String,
+    classification: Option<String>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> PolicyDecision {
+    policy_engine.check_provider_access(&project_id, &provider, classification.as_deref())
+}
+
+/// Decide whether `agent_role` may merge to main given the merge gate
+/// results, recording a `gate.evaluated` event against the workstream's run
+#[tauri::command]
+pub fn evaluate_merge(
+    project_id: String,
+    workstream_id: Option<String>,
+    agent_role: String,
+    gate_results: HashMap<String, bool>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<MergeEvaluation, CommandError> {
+    let evaluation = policy_engine
+        .evaluate_merge(&project_id, &agent_role, &gate_results)
+        .ok_or_else(|| policy_not_found(&project_id))?;
+
+    let workstream_id = workstream_id.unwrap_or_default();
+    let run_id = workstream_run_id(&workstream_id);
+
+    audit_store.record_event(AuditEvent::new(
+        &project_id,
+        &workstream_id,
+        &run_id,
+        "gate.evaluated",
+        AuditActor::agent_role(&agent_role),
+        serde_json::json!({
+            "kind": "gate",
+            "stage": "merge",
+            "gate_results": gate_results,
+            "passed": evaluation.gates.passed,
+            "failed_gates": evaluation.gates.failed_gates,
+            "allowed": evaluation.allowed,
+            "reason": evaluation.reason
+        }),
+    ))?;
+
+    Ok(evaluation)
+}
+
+/// The gates guarding a phase with each one's latest result from the audit
+/// log, optionally counting only results recorded for one workstream.
+/// Gates with no recorded result are `pending`.
+#[tauri::command]
+pub fn gates_for_phase(
+    project_id: String,
+    phase: String,
+    workstream_id: Option<String>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<Vec<GateStatus>, CommandError> {
+    if !phase::is_working_phase(&phase) && phase != phase::COMPLETED {
+        return Err(CommandError::invalid_input(format!(
+            "Unknown phase: {}",
+            phase
+        )));
+    }
+    let policy = policy_engine
+        .get_policy(&project_id)
+        .ok_or_else(|| policy_not_found(&project_id))?;
+    let results = audit_store.gate_results(&project_id, workstream_id.as_deref());
+
+    Ok(policy
+        .gates
+        .gates_for_phase(&phase)
+        .iter()
+        .map(|gate| {
+            let result = results.get(&gate.id);
+            GateStatus {
+                id: gate.id.clone(),
+                name: gate.name.clone(),
+                gate_type: gate.gate_type.clone(),
+                required: gate.required,
+                state: match result {
+                    Some(r) if r.passed => GateState::Passed,
+                    Some(_) => GateState::Failed,
+                    None => GateState::Pending,
+                },
+                evaluated_at: result.map(|r| r.evaluated_at.clone()),
+            }
+        })
+        .collect())
+}
+
+/// Canary rollout plan for an environment, starting now
+#[tauri::command]
+pub fn plan_canary(
+    project_id: String,
+    environment: String,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<CanaryPlan, CommandError> {
+    let policy = policy_engine
+        .get_policy(&project_id)
+        .ok_or_else(|| policy_not_found(&project_id))?;
+    policy
+        .deployment
+        .plan_canary(&environment, Utc::now())
+        .map_err(|e| CommandError::new(ErrorCode::InvalidPolicy, e.to_string()))
+}
+
+/// Check live metrics against the project's rollback triggers. A fired
+/// trigger is recorded as `deploy.rollback_triggered`.
+#[tauri::command]
+pub fn evaluate_rollback(
+    project_id: String,
+    workstream_id: Option<String>,
+    environment: String,
+    metrics: HashMap<String, f64>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<Option<RollbackDecision>, CommandError> {
+    let policy = policy_engine
+        .get_policy(&project_id)
+        .ok_or_else(|| policy_not_found(&project_id))?;
+    let decision = policy
+        .deployment
+        .rollback
+        .should_rollback(&metrics)
+        .map_err(|e| {
+            CommandError::new(ErrorCode::InvalidPolicy, "Policy failed validation")
+                .with_details(serde_json::json!([e]))
+        })?;
+
+    if let Some(decision) = &decision {
+        let workstream_id = workstream_id.unwrap_or_default();
+        let run_id = workstream_run_id(&workstream_id);
+
+        audit_store.record_event(AuditEvent::new(
+            &project_id,
+            &workstream_id,
+            &run_id,
+            "deploy.rollback_triggered",
+            AuditActor::user(),
+            serde_json::json!({
+                "kind": "deploy",
+                "environment": environment,
+                "metric": decision.metric,
+                "value": decision.value,
+                "threshold": decision.threshold,
+                "automatic": decision.automatic,
+                "reason": decision.reason
+            }),
+        ))?;
+    }
+
+    Ok(decision)
+}
+
+/// Whether `agent_role` may deploy to `environment`, recorded as a
+/// `deploy.permission_checked` event against the workstream's run
+#[tauri::command]
+pub fn can_deploy(
+    project_id: String,
+    workstream_id: Option<String>,
+    agent_role: String,
+    environment: String,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<PolicyDecision, CommandError> {
+    let decision = policy_engine.can_deploy(&project_id, &agent_role, &environment);
+
+    let workstream_id = workstream_id.unwrap_or_default();
+    audit_store.record_event(AuditEvent::new(
+        &project_id,
+        &workstream_id,
+        &workstream_run_id(&workstream_id),
+        "deploy.permission_checked",
+        AuditActor::agent_role(&agent_role),
+        serde_json::json!({
+            "kind": "deploy",
+            "environment": environment,
+            "allowed": decision.allowed,
+            "reason": decision.reason
+        }),
+    ))?;
+
+    Ok(decision)
+}
+
+/// Redact text bound for an external provider; unchanged if the project has no policy
+#[tauri::command]
+pub fn redact_text(
+    project_id: String,
+    text: String,
+    policy_engine: State<'_, PolicyEngine>,
+) -> String {
+    policy_engine.redact(&project_id, &text)
+}
+
+/// Evaluate a tool permission for an agent holding `agent_roles` and record
+/// the decision against the workstream's current run, so every allow and
+/// deny leaves a trail
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn evaluate_permission(
+    project_id: String,
+    workstream_id: Option<String>,
+    agent_roles: Vec<String>,
+    action: String,
+    operation: String,
+    resource: String,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<PolicyDecision, CommandError> {
+    if agent_roles.is_empty() {
+        return Err(CommandError::invalid_input("agent_roles cannot be empty"));
+    }
+    let agent_roles: Vec<&str> = agent_roles.iter().map(String::as_str).collect();
+    decide_permission(
+        &project_id,
+        &workstream_id.unwrap_or_default(),
+        &agent_roles,
+        &action,
+        &operation,
+        &resource,
+        &policy_engine,
+        &audit_store,
+    )
+}
+
+/// List every rule considered for an action and which one decided it, to
+/// debug a policy. Unlike `evaluate_permission` nothing is recorded.
+#[tauri::command]
+pub fn explain_permission(
+    project_id: String,
+    agent_roles: Vec<String>,
+    action: String,
+    operation: String,
+    resource: String,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<PermissionExplanation, CommandError> {
+    if agent_roles.is_empty() {
+        return Err(CommandError::invalid_input("agent_roles cannot be empty"));
+    }
+    let agent_roles: Vec<&str> = agent_roles.iter().map(String::as_str).collect();
+    Ok(policy_engine.explain_permission(&project_id, &agent_roles, &action, &operation, &resource))
+}
+
+/// Workstreams saved before runs were tracked have no `run_id`; attach the
+/// most recent run the audit log holds for them so new events aren't orphaned
+fn adopt_latest_run(ws: &mut WorkstreamData, audit_store: &AuditStore) {
+    if ws.run_id.is_none() {
+        ws.run_id = audit_store
+            .runs_for_workstream(&ws.id)
+            .pop()
+            .map(|run| run.id);
+    }
+}
+
+/// Current run of a workstream, or empty when it has none or doesn't exist
+fn workstream_run_id(workstream_id: &str) -> String {
+    WORKSTREAMS
+        .lock_recover()
+        .iter()
+        .find(|w| w.id == workstream_id)
+        .and_then(|w| w.run_id.clone())
+        .unwrap_or_default()
+}
+
+/// Evaluate a permission and record the decision against the workstream's run
+#[allow(clippy::too_many_arguments)]
+fn decide_permission(
+    project_id: &str,
+    workstream_id: &str,
+    agent_roles: &[&str],
+    action: &str,
+    operation: &str,
+    resource: &str,
+    policy_engine: &PolicyEngine,
+    audit_store: &AuditStore,
+) -> Result<PolicyDecision, CommandError> {
+    let decision =
+        policy_engine.evaluate_permission(project_id, agent_roles, action, operation, resource);
+
+    let run_id = workstream_run_id(workstream_id);
+    let agent_role = agent_roles.join(", ");
+
+    audit_store.record_event(AuditEvent::new(
+        project_id,
+        workstream_id,
+        &run_id,
+        "policy.decision",
+        AuditActor::agent_role(&agent_role),
+        serde_json::json!({
+            "agent_role": agent_role,
+            "agent_roles": agent_roles,
+            "action": action,
+            "operation": operation,
+            "resource": resource,
+            "allowed": decision.allowed,
+            "reason": decision.reason
+        }),
+    ))?;
+
+    Ok(decision)
+}
+
+/// Deny note access the role isn't cleared for, auditing the denial.
+/// Without a role, notes whose classification has an access rule are denied.
+fn check_note_access(
+    project_id: &str,
+    note_path: &str,
+    role: Option<&str>,
+    operation: &str,
+    classification: Option<&str>,
+    policy_engine: &PolicyEngine,
+    audit_store: &AuditStore,
+) -> Result<(), VaultError> {
+    let decision = policy_engine.check_note_access(project_id, role, classification);
+    if decision.allowed {
+        return Ok(());
+    }
+
+    // Access is denied either way; a failed audit write is still logged loudly
+    let recorded = audit_store.record_event(AuditEvent::new(
+        project_id,
+        "",
+        "",
+        "vault.access_denied",
+        role.map_or_else(AuditActor::user, AuditActor::agent_role),
+        serde_json::json!({
+            "kind": "vault.access",
+            "operation": operation,
+            "note_path": note_path,
+            "classification": classification,
+            "reason": decision.reason
+        }),
+    ));
+    if let Err(e) = recorded {
+        tracing::error!(
+            "Failed to audit denied {} of {}: {}",
+            operation,
+            note_path,
+            e
+        );
+    }
+
+    Err(VaultError::AccessDenied(format!(
+        "{} {}: {}",
+        operation, note_path, decision.reason
+    )))
+}
+
+#[tauri::command]
+pub fn read_vault_note(
+    project_id: String,
+    note_path: String,
+    reader_role: Option<String>,
+    lossy: Option<bool>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<VaultNote, CommandError> {
+    read_note_checked(
+        &project_id,
+        &note_path,
+        reader_role.as_deref(),
+        lossy.unwrap_or(false),
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )
+}
+
+/// A note as sanitized HTML with its frontmatter and table of contents, for
+/// preview. Wikilinks are `<a data-note>` anchors to open with `read_vault_note`.
+#[tauri::command]
+pub fn render_note(
+    project_id: String,
+    note_path: String,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<RenderedNote, CommandError> {
+    let note = read_note_checked(
+        &project_id,
+        &note_path,
+        reader_role.as_deref(),
+        false,
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )?;
+    Ok(vault_manager.render_note(&project_id, note)?)
+}
+
+/// Open a note in Obsidian, returning the `obsidian://` link so the UI can
+/// also offer to copy it
+#[tauri::command]
+pub fn open_note_in_obsidian(
+    project_id: String,
+    note_path: String,
+    app: AppHandle,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<String, CommandError> {
+    let url = vault_manager.note_deep_link(&project_id, &note_path)?;
+    app.opener().open_url(&url, None::<&str>).map_err(|e| {
+        CommandError::new(ErrorCode::IoError, format!("Failed to open {}: {}", url, e))
+    })?;
+    Ok(url)
+}
+
+/// The outcome of reading one note in a batch; exactly one of `note` and
+/// `error` is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteReadResult {
+    pub path: String,
+    pub note: Option<VaultNote>,
+    pub error: Option<CommandError>,
+}
+
+/// Read many notes in one call, in parallel. Results keep the order of
+/// `note_paths`, and a note that can't be read fails only its own entry.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn read_notes(
+    project_id: String,
+    note_paths: Vec<String>,
+    reader_role: Option<String>,
+    lossy: Option<bool>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<Vec<NoteReadResult>, CommandError> {
+    // Fail the whole call up front if the project has no vault
+    vault_manager
+        .vault_root(&project_id)
+        .ok_or_else(|| VaultError::VaultNotFound(project_id.clone()))?;
+
+    let (vault_manager, policy_engine, audit_store) = (
+        vault_manager.inner(),
+        policy_engine.inner(),
+        audit_store.inner(),
+    );
+    Ok(note_paths
+        .into_par_iter()
+        .map(|path| {
+            let read = read_note_checked(
+                &project_id,
+                &path,
+                reader_role.as_deref(),
+                lossy.unwrap_or(false),
+                vault_manager,
+                policy_engine,
+                audit_store,
+            );
+            let (note, error) = match read {
+                Ok(note) => (Some(note), None),
+                Err(e) => (None, Some(e)),
+            };
+            NoteReadResult { path, note, error }
+        })
+        .collect())
+}
+
+/// Read a note, refusing it if `reader_role` may not see its classification
+/// (or, without a role, if its classification is restricted at all)
+fn read_note_checked(
+    project_id: &str,
+    note_path: &str,
+    reader_role: Option<&str>,
+    lossy: bool,
+    vault_manager: &VaultManager,
+    policy_engine: &PolicyEngine,
+    audit_store: &AuditStore,
+) -> Result<VaultNote, CommandError> {
+    let note = vault_manager.read_note_with(project_id, note_path, lossy)?;
+    check_note_access(
+        project_id,
+        note_path,
+        reader_role,
+        "read",
+        note.classification(),
+        policy_engine,
+        audit_store,
+    )?;
+    Ok(note)
+}
+
+/// Whether `role` may read a note, for leaving restricted notes out of
+/// search results, link listings, and exports. A note that can't be read as
+/// text is judged by the default classification.
+fn note_visible(
+    project_id: &str,
+    note_path: &str,
+    role: Option<&str>,
+    vault_manager: &VaultManager,
+    policy_engine: &PolicyEngine,
+) -> bool {
+    let note = vault_manager.read_note(project_id, note_path).ok();
+    let classification = note.as_ref().and_then(|n| n.classification());
+    policy_engine
+        .check_note_access(project_id, role, classification)
+        .allowed
+}
+
+/// The note already at `note_path`, if any, must be cleared for `role` to
+/// write, which covers deleting, restoring, and moving it too. `operation`
+/// names what was attempted in the denial.
+fn check_existing_note_write(
+    project_id: &str,
+    note_path: &str,
+    role: Option<&str>,
+    operation: &str,
+    vault_manager: &VaultManager,
+    policy_engine: &PolicyEngine,
+    audit_store: &AuditStore,
+) -> Result<(), CommandError> {
+    match vault_manager.read_note_with(project_id, note_path, true) {
+        Ok(existing) => check_note_access(
+            project_id,
+            note_path,
+            role,
+            operation,
+            existing.classification(),
+            policy_engine,
+            audit_store,
+        )?,
+        Err(VaultError::NoteNotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+/// Both the note being replaced and the incoming content must be cleared
+/// for `role` to write
+fn check_note_write(
+    project_id: &str,
+    note_path: &str,
+    frontmatter: &HashMap<String, serde_json::Value>,
+    role: Option<&str>,
+    vault_manager: &VaultManager,
+    policy_engine: &PolicyEngine,
+    audit_store: &AuditStore,
+) -> Result<(), CommandError> {
+    check_existing_note_write(
+        project_id,
+        note_path,
+        role,
+        "write",
+        vault_manager,
+        policy_engine,
+        audit_store,
+    )?;
+    check_note_access(
+        project_id,
+        note_path,
+        role,
+        "write",
+        frontmatter.get("classification").and_then(|v| v.as_str()),
+        policy_engine,
+        audit_store,
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn write_vault_note(
+    project_id: String,
+    note_path: String,
+    frontmatter: HashMap<String, serde_json::Value>,
+    content: String,
+    writer_role: Option<String>,
+    expected_last_modified: Option<String>,
+    expected_hash: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<(), CommandError> {
+    check_note_write(
+        &project_id,
+        &note_path,
+        &frontmatter,
+        writer_role.as_deref(),
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )?;
+
+    vault_manager
+        .write_note(
+            &project_id,
+            &note_path,
+            &frontmatter,
+            &content,
+            expected_last_modified.as_deref(),
+            expected_hash.as_deref(),
+        )
+        .map_err(CommandError::from)
+}
+
+/// Write several notes as one transaction: either every note is written or,
+/// on the first failure, the ones already written are rolled back and the
+/// error names the failing note. Every note is checked against `writer_role`
+/// before anything is written.
+#[tauri::command]
+pub fn write_notes(
+    project_id: String,
+    notes: Vec<NoteWrite>,
+    writer_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<(), CommandError> {
+    if notes.is_empty() {
+        return Ok(());
+    }
+    for note in &notes {
+        check_note_write(
+            &project_id,
+            &note.path,
+            &note.frontmatter,
+            writer_role.as_deref(),
+            &vault_manager,
+            &policy_engine,
+            &audit_store,
+        )?;
+    }
+
+    vault_manager
+        .write_notes(&project_id, &notes)
+        .map_err(CommandError::from)
+}
+
+/// Set and remove individual frontmatter keys without rewriting the body.
+/// Both the note's current classification and any new one must allow
+/// `writer_role` to write.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn patch_vault_frontmatter(
+    project_id: String,
+    note_path: String,
+    updates: HashMap<String, serde_json::Value>,
+    removals: Option<Vec<String>>,
+    writer_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<HashMap<String, serde_json::Value>, CommandError> {
+    let existing = vault_manager.read_note(&project_id, &note_path)?;
+    let mut classifications = vec![existing.classification()];
+    if let Some(new) = updates.get("classification").and_then(|v| v.as_str()) {
+        classifications.push(Some(new));
+    }
+    for classification in classifications {
+        check_note_access(
+            &project_id,
+            &note_path,
+            writer_role.as_deref(),
+            "write",
+            classification,
+            &policy_engine,
+            &audit_store,
+        )?;
+    }
+
+    Ok(vault_manager.patch_frontmatter(
+        &project_id,
+        &note_path,
+        &updates,
+        &removals.unwrap_or_default(),
+    )?)
+}
+
+/// List a vault directory. Recursive and `.md`-only unless told otherwise;
+/// directories are included with `is_dir` set so the UI can build a tree.
+#[tauri::command]
+pub fn list_vault_notes(
+    project_id: String,
+    directory: String,
+    recursive: Option<bool>,
+    include_non_md: Option<bool>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<Vec<VaultNoteEntry>, CommandError> {
+    let defaults = ListOptions::default();
+    let options = ListOptions {
+        recursive: recursive.unwrap_or(defaults.recursive),
+        include_non_md: include_non_md.unwrap_or(defaults.include_non_md),
+    };
+    vault_manager
+        .list_notes(&project_id, &directory, &options)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn count_vault_notes(
+    project_id: String,
+    directory: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<usize, CommandError> {
+    vault_manager
+        .count_notes(&project_id, &directory)
+        .map_err(CommandError::from)
+}
+
+/// Move a note to the vault's trash; returns its path inside `.trash/`.
+/// `writer_role` must be cleared to write the note.
+#[tauri::command]
+pub fn delete_vault_note(
+    project_id: String,
+    note_path: String,
+    writer_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<String, CommandError> {
+    let role = writer_role.as_deref();
+    check_existing_note_write(
+        &project_id,
+        &note_path,
+        role,
+        "delete",
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )?;
+    let trash_path = vault_manager.delete_note(&project_id, &note_path)?;
+    audit_store.record_event(AuditEvent::new(
+        &project_id,
+        "",
+        "",
+        "vault.note.deleted",
+        role.map_or_else(AuditActor::user, AuditActor::agent_role),
+        serde_json::json!({
+            "note_path": note_path,
+            "trash_path": trash_path
+        }),
+    ))?;
+    Ok(trash_path)
+}
+
+/// Move a trashed note back; returns the path it was restored to.
+/// `writer_role` must be cleared to write the trashed note.
+#[tauri::command]
+pub fn restore_vault_note(
+    project_id: String,
+    trash_path: String,
+    writer_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<String, CommandError> {
+    let role = writer_role.as_deref();
+    check_existing_note_write(
+        &project_id,
+        &trash_path,
+        role,
+        "restore",
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )?;
+    let note_path = vault_manager.restore_note(&project_id, &trash_path)?;
+    audit_store.record_event(AuditEvent::new(
+        &project_id,
+        "",
+        "",
+        "vault.note.restored",
+        role.map_or_else(AuditActor::user, AuditActor::agent_role),
+        serde_json::json!({
+            "note_path": note_path,
+            "trash_path": trash_path
+        }),
+    ))?;
+    Ok(note_path)
+}
+
+/// Move or rename a note and repoint links to it. `writer_role` must be
+/// cleared to write the note, and the note it replaces with `overwrite`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn move_vault_note(
+    project_id: String,
+    from_path: String,
+    to_path: String,
+    overwrite: Option<bool>,
+    writer_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<MoveReport, CommandError> {
+    let role = writer_role.as_deref();
+    let overwrite = overwrite.unwrap_or(false);
+    check_existing_note_write(
+        &project_id,
+        &from_path,
+        role,
+        "move",
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )?;
+    if overwrite {
+        check_existing_note_write(
+            &project_id,
+            &to_path,
+            role,
+            "overwrite",
+            &vault_manager,
+            &policy_engine,
+            &audit_store,
+        )?;
+    }
+
+    let report = vault_manager.move_note(&project_id, &from_path, &to_path, overwrite)?;
+    audit_store.record_event(AuditEvent::new(
+        &project_id,
+        "",
+        "",
+        "vault.note.moved",
+        role.map_or_else(AuditActor::user, AuditActor::agent_role),
+        serde_json::json!({
+            "from": report.from,
+            "to": report.to,
+            "overwrite": overwrite,
+            "updated": report.updated
+        }),
+    ))?;
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn create_vault_snapshot(
+    project_id: String,
+    label: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<SnapshotInfo, CommandError> {
+    vault_manager
+        .create_snapshot(&project_id, label.as_deref())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn list_vault_snapshots(
+    project_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<Vec<SnapshotInfo>, CommandError> {
+    vault_manager
+        .list_snapshots(&project_id)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn diff_vault_snapshot(
+    project_id: String,
+    snapshot_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<VaultDiff, CommandError> {
+    vault_manager
+        .diff_against_snapshot(&project_id, &snapshot_id)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn list_vault_templates(
+    project_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<Vec<TemplateInfo>, CommandError> {
+    vault_manager
+        .list_templates(&project_id)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub fn get_template_levels(
+    project_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Vec<LevelRule> {
+    vault_manager.level_rules(&project_id)
+}
+
+#[tauri::command]
+pub fn set_template_levels(
+    project_id: String,
+    rules: Vec<LevelRule>,
+    config_store: State<'_, ConfigStore>,
+    vault_manager: State<'_, VaultManager>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<(), CommandError> {
+    update_project_config(
+        &project_id,
+        serde_json::json!({ "template_levels": rules }),
+        &config_store,
+        &vault_manager,
+        &audit_store,
+    )
+    .map(|_| ())
+}
+
+#[tauri::command]
+pub fn resolve_template_level(
+    project_id: String,
+    note_path: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<Option<u8>, CommandError> {
+    let frontmatter = match vault_manager.read_note(&project_id, &note_path) {
+        Ok(note) => Some(note.frontmatter),
+        Err(VaultError::NoteNotFound(_)) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(vault_manager.required_level(&project_id, &note_path, frontmatter.as_ref()))
+}
+
+/// A note's links, which `reader_role` must be cleared to read. Links to
+/// notes the role can't read are left out.
+#[tauri::command]
+pub fn get_note_links(
+    project_id: String,
+    note_path: String,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+    audit_store: State<'_, AuditStore>,
+) -> Result<NoteLinks, CommandError> {
+    let role = reader_role.as_deref();
+    read_note_checked(
+        &project_id,
+        &note_path,
+        role,
+        false,
+        &vault_manager,
+        &policy_engine,
+        &audit_store,
+    )?;
+    let mut links = vault_manager.note_links(&project_id, &note_path)?;
+    let visible = |link: &ResolvedLink| {
+        note_visible(
+            &project_id,
+            &link.path,
+            role,
+            &vault_manager,
+            &policy_engine,
+        )
+    };
+    links.links.retain(visible);
+    links.embeds.retain(visible);
+    Ok(links)
+}
+
+/// Places that link to `target_path`, leaving out notes `reader_role` can't read
+#[tauri::command]
+pub fn find_references(
+    project_id: String,
+    target_path: String,
+    match_by: Option<ReferenceMatch>,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<Vec<Reference>, CommandError> {
+    let mut references = vault_manager.references_to(
+        &project_id,
+        &target_path,
+        match_by.unwrap_or(ReferenceMatch::Basename),
+    )?;
+    references.retain(|r| {
+        note_visible(
+            &project_id,
+            &r.path,
+            reader_role.as_deref(),
+            &vault_manager,
+            &policy_engine,
+        )
+    });
+    Ok(references)
+}
+
+/// Notes linking to `note_path`, leaving out notes `reader_role` can't read
+#[tauri::command]
+pub fn get_backlinks(
+    project_id: String,
+    note_path: String,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<Vec<VaultNoteEntry>, CommandError> {
+    let mut backlinks = vault_manager.backlinks(&project_id, &note_path)?;
+    backlinks.retain(|entry| {
+        note_visible(
+            &project_id,
+            &entry.path,
+            reader_role.as_deref(),
+            &vault_manager,
+            &policy_engine,
+        )
+    });
+    Ok(backlinks)
+}
+
+/// Links that resolve to no note, leaving out sources `reader_role` can't read
+#[tauri::command]
+pub fn get_broken_links(
+    project_id: String,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<Vec<BrokenLink>, CommandError> {
+    let mut broken = vault_manager.broken_links(&project_id)?;
+    broken.retain(|link| {
+        note_visible(
+            &project_id,
+            &link.source,
+            reader_role.as_deref(),
+            &vault_manager,
+            &policy_engine,
+        )
+    });
+    Ok(broken)
+}
+
+/// Notes tagged `tag` in frontmatter or inline; a parent tag also matches
+/// notes with tags nested under it. Notes `reader_role` can't read are left out.
+#[tauri::command]
+pub fn list_notes_by_tag(
+    project_id: String,
+    tag: String,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<Vec<VaultNoteEntry>, CommandError> {
+    let mut notes = vault_manager.notes_by_tag(&project_id, &tag)?;
+    notes.retain(|entry| {
+        note_visible(
+            &project_id,
+            &entry.path,
+            reader_role.as_deref(),
+            &vault_manager,
+            &policy_engine,
+        )
+    });
+    Ok(notes)
+}
+
+#[tauri::command]
+pub fn all_tags(
+    project_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<Vec<TagCount>, CommandError> {
+    vault_manager
+        .all_tags(&project_id)
+        .map_err(CommandError::from)
+}
+
+/// Matches of `query`, leaving out notes `reader_role` can't read
+#[tauri::command]
+pub fn search_vault(
+    project_id: String,
+    query: String,
+    options: Option<SearchOptions>,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<Vec<VaultSearchResult>, CommandError> {
+    let mut results = vault_manager.search(&project_id, &query, &options.unwrap_or_default())?;
+    let mut visible = HashMap::new();
+    results.retain(|r| {
+        *visible.entry(r.path.clone()).or_insert_with(|| {
+            note_visible(
+                &project_id,
+                &r.path,
+                reader_role.as_deref(),
+                &vault_manager,
+                &policy_engine,
+            )
+        })
+    });
+    Ok(results)
+}
+
+/// Notes ranked by relevance to `query`; `limit` defaults to 20. Notes
+/// `reader_role` can't read are left out before the limit is applied.
+#[tauri::command]
+pub fn search_vault_ranked(
+    project_id: String,
+    query: String,
+    limit: Option<usize>,
+    reader_role: Option<String>,
+    vault_manager: State<'_, VaultManager>,
+    policy_engine: State<'_, PolicyEngine>,
+) -> Result<Vec<RankedSearchResult>, CommandError> {
+    let mut results = vault_manager.search_ranked(&project_id, &query, usize::MAX)?;
+    results.retain(|r| {
+        note_visible(
+            &project_id,
+            &r.path,
+            reader_role.as_deref(),
+            &vault_manager,
+            &policy_engine,
+        )
+    });
+    results.truncate(limit.unwrap_or(20));
+    Ok(results)
+}
+
+/// Rebuild the vault's search index, returning the number of notes indexed
+#[tauri::command]
+pub fn reindex_vault(
+    project_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<usize, CommandError> {
+    Ok(vault_manager.reindex(&project_id)?)
+}
+
+/// Sizes and build times of the vault's link, tag, and search indexes
+#[tauri::command]
+pub fn vault_cache_stats(
+    project_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<VaultCacheStats, CommandError> {
+    Ok(vault_manager.cache_stats(&project_id)?)
+}
+
+/// Check the vault for malformed frontmatter, broken links, template
+/// violations, orphaned notes, and duplicate names. Changes nothing.
+#[tauri::command]
+pub fn check_vault_health(
+    project_id: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<VaultHealthReport, CommandError> {
+    Ok(vault_manager.check_health(&project_id)?)
+}
+
+/// Store a file under the vault's `attachments/` folder. The bytes are the
+/// raw request body; the project id and the path under `attachments/` come
+/// in the `project-id` and `attachment-path` headers, URI-encoded.
+#[tauri::command]
+pub fn write_attachment(
+    request: tauri::ipc::Request<'_>,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<AttachmentInfo, CommandError> {
+    let tauri::ipc::InvokeBody::Raw(data) = request.body() else {
+        return Err(CommandError::invalid_input(
+            "Attachment must be sent as raw bytes",
+        ));
+    };
+    let project_id = request_header(&request, "project-id")?;
+    let path = request_header(&request, "attachment-path")?;
+    Ok(vault_manager.write_attachment(&project_id, &path, data.as_slice())?)
+}
+
+fn request_header(request: &tauri::ipc::Request<'_>, name: &str) -> Result<String, CommandError> {
+    let value = request
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| CommandError::invalid_input(format!("Missing {} header", name)))?;
+    urlencoding::decode(value)
+        .map(|v| v.into_owned())
+        .map_err(|e| CommandError::invalid_input(format!("Invalid {} header: {}", name, e)))
+}
+
+/// The raw bytes of a file under `attachments/`
+#[tauri::command]
+pub fn read_attachment(
+    project_id: String,
+    path: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<tauri::ipc::Response, CommandError> {
+    let data = vault_manager.read_attachment(&project_id, &path)?;
+    Ok(tauri::ipc::Response::new(data))
+}
+
+/// Size and detected MIME type of a file under `attachments/`
+#[tauri::command]
+pub fn get_attachment_info(
+    project_id: String,
+    path: String,
+    vault_manager: State<'_, VaultManager>,
+) -> Result<AttachmentInfo, CommandError> {
+    Ok(vault_manager.attachment_info(&project_id, &path)?)
+}
+
+/// Finish a run with `completed`, `failed`, or `cancelled`
+#[tauri::command]
+pub fn complete_run(
+    run_id: String,
+    status: String,
+    audit_store: State<'_, AuditStore>,
+) -> Result<RunRecord, CommandError> {
+    Ok(audit_store.complete_run(&run_id, &status)?)
+}
+
+/// Sent on `run://cancelled` so agents working on the run can stop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunCancellation {
+    pub run_id: String,
+    pub project_id: String,
+    pub workstream_id: String,
+    pub reason: String,
+    pub actor: AuditActor,
+}
+
+/// Cancel a run that is still going, recording `run.cancelled` with the
+/// reason and telling the agent runtime through `run://cancelled`.
+/// `actor` defaults to the user.
+#[tauri::command]
+pub fn cancel_run(
+    run_id: String,
+    reason: String,
+    actor: Option<AuditActor>,
+    app: AppHandle,
+    audit_store: State<'_, AuditStore>,
+) -> Result<RunRecord, CommandError> {
+    let actor = actor.unwrap_or_else(AuditActor::user);
+    let run = audit_store.cancel_run(&run_id, &reason, actor.clone())?;
+
+    let cancellation = RunCancellation {
+        run_id: run.id.clone(),
+        project_id: run.project_id.clone(),
+        workstream_id: run.workstream_id.clone(),
+        reason,
+        actor,
+    };
+    if let Err(e) = app.emit("run://cancelled", &cancellation) {
+        tracing::warn!("Failed to emit run://cancelled: {}", e);
+    }
+    Ok(run)
+}
+
+/// Open a span on a running run so the agent runtime can bracket a
+/// long-running piece of work; returns the span id for `end_span`
+#[tauri::command]
+pub fn start_span(
+    run_id: String,
+    name: String,
+    actor: AuditActor,
+    audit_store: State<'_, AuditStore>,
+) -> Result<String, CommandError> {
+    Ok(audit_store.start_span(&run_id, &name, actor)?)
+}
+
+/// Close a span with `ok`, `error`, or `cancelled`
+#[tauri::command]
+pub fn end_span(
+    span_id: String,
+    status: String,
+    actor: AuditActor,
+    audit_store: State<'_, AuditStore>,
+) -> Result<RunSpan, CommandError> {
+    Ok(audit_store.end_span(&span_id, &status, actor)?)
+}
+
+/// A run's spans in start order, for drawing its timeline
+#[tauri::command]
+pub fn get_run_spans(
+    run_id: String,
+    audit_store: State<'_, AuditStore>,
+) -> Result<Vec<RunSpan>, CommandError> {
+    Ok(audit_store.run_spans(&run_id)?)
+}
+
+#[tauri::command]
+pub fn get_run_record(
+    run_id: String,
+    include_events: Option<bool>,
+    event_type_prefix: Option<String>,
+    audit_store: State<'_, AuditStore>,
+) -> Option<RunRecord> {
+    audit_store.get_run_filtered(
+        &run_id,
+        include_events.unwrap_or(true),
+        event_type_prefix.as_deref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::tests::{engine_with, restricted_notes_policy, sample_policy};
+    use crate::policy::RedactionPattern;
+
+    struct Stores {
+        audit: AuditStore,
+        policy: PolicyEngine,
+        vault: VaultManager,
+        config: ConfigStore,
+    }
+
+    fn stores(policy: PolicyEngine) -> Stores {
+        Stores {
+            audit: AuditStore::new(),
+            policy,
+            vault: VaultManager::new(),
+            config: ConfigStore::new(),
+        }
+    }
+
+    /// A vault for project `p` holding an open note and a `secret` one
+    fn classified_vault(stores: &Stores) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("open.md"), "launch plan").unwrap();
+        std::fs::write(
+            dir.path().join("keys.md"),
+            "---\nclassification: secret\n---\n\nlaunch keys",
+        )
+        .unwrap();
+        stores
+            .vault
+            .register_vault("p", dir.path().to_path_buf())
+            .unwrap();
+        dir
+    }
+
+    /// Add a workstream of project `p` to the shared cache, returning its id
+    fn add_workstream() -> String {
+        let id = Uuid::new_v4().to_string();
+        WORKSTREAMS.lock_recover().push(WorkstreamData {
+            id: id.clone(),
+            project_id: "p".to_string(),
+            title: "Test".to_string(),
+            description: String::new(),
+            status: "active".to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            user_request: String::new(),
+            current_phase: phase::PHASES[0].to_string(),
+            messages: Vec::new(),
+            run_id: None,
+        });
+        id
+    }
+
+    fn post(
+        stores: &Stores,
+        workstream_id: &str,
+        content: &str,
+    ) -> Result<MessageData, CommandError> {
+        append_message(
+            workstream_id,
+            content,
+            None,
+            &stores.audit,
+            &stores.policy,
+            &stores.vault,
+            &stores.config,
+        )
+    }
+
+    fn messages(workstream_id: &str) -> Vec<MessageData> {
+        WORKSTREAMS
+            .lock_recover()
+            .iter()
+            .find(|w| w.id == workstream_id)
+            .map(|w| w.messages.clone())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn append_message_rejects_empty_content() {
+        let stores = stores(PolicyEngine::new());
+        let ws = add_workstream();
+        let err = post(&stores, &ws, "  \n\t").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert!(messages(&ws).is_empty());
+    }
+
+    #[test]
+    fn append_message_rejects_oversized_content() {
+        let stores = stores(PolicyEngine::new());
+        let ws = add_workstream();
+        let content = "x".repeat(crate::config::DEFAULT_MAX_MESSAGE_LENGTH + 1);
+        let err = post(&stores, &ws, &content).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert_eq!(
+            err.details.unwrap()["max_length"],
+            crate::config::DEFAULT_MAX_MESSAGE_LENGTH
+        );
+        assert!(messages(&ws).is_empty());
+    }
+
+    #[test]
+    fn append_message_redacts_before_storing() {
+        let mut policy = sample_policy("p");
+        policy
+            .data_classification
+            .redaction_patterns
+            .push(RedactionPattern {
+                name: "api key".to_string(),
+                pattern: r"sk-[a-z0-9]+".to_string(),
+                replacement: "[REDACTED]".to_string(),
+            });
+        let stores = stores(engine_with(policy));
+        let ws = add_workstream();
+
+        let message = post(&stores, &ws, "my key is sk-abc123").unwrap();
+        assert_eq!(message.content, "my key is [REDACTED]");
+        assert!(message.redacted);
+        assert_eq!(messages(&ws)[0].content, "my key is [REDACTED]");
+        let events = stores.audit.get_events("p", 1);
+        assert_eq!(events[0].payload["content"], "my key is [REDACTED]");
+    }
+
+    #[test]
+    fn append_message_to_unknown_workstream_fails() {
+        let stores = stores(PolicyEngine::new());
+        let err = post(&stores, "no-such-workstream", "hello").unwrap_err();
+        assert_eq!(err.code, ErrorCode::WorkstreamNotFound);
+    }
+
+    #[test]
+    fn restricted_note_reads_need_a_cleared_role() {
+        let stores = stores(engine_with(restricted_notes_policy()));
+        let _vault = classified_vault(&stores);
+        let read = |role| {
+            read_note_checked(
+                "p",
+                "keys.md",
+                role,
+                false,
+                &stores.vault,
+                &stores.policy,
+                &stores.audit,
+            )
+        };
+
+        assert_eq!(read(None).unwrap_err().code, ErrorCode::AccessDenied);
+        assert_eq!(
+            read(Some("coder")).unwrap_err().code,
+            ErrorCode::AccessDenied
+        );
+        assert_eq!(
+            read(Some("security")).unwrap().content.trim(),
+            "launch keys"
+        );
+        let denials = stores.audit.get_events("p", usize::MAX);
+        assert_eq!(denials.len(), 2);
+        assert!(denials
+            .iter()
+            .all(|e| e.event_type == "vault.access_denied"));
+    }
+
+    #[test]
+    fn restricted_notes_are_hidden_from_listings_without_a_role() {
+        let stores = stores(engine_with(restricted_notes_policy()));
+        let _vault = classified_vault(&stores);
+        let visible = |path, role| note_visible("p", path, role, &stores.vault, &stores.policy);
+
+        assert!(visible("open.md", None));
+        assert!(!visible("keys.md", None));
+        assert!(!visible("keys.md", Some("coder")));
+        assert!(visible("keys.md", Some("security")));
+    }
+
+    #[test]
+    fn user_message_lands_in_the_workstreams_run() {
+        let stores = stores(PolicyEngine::new());
+        let ws = add_workstream();
+        let other = add_workstream();
+        let other_run = stores
+            .audit
+            .create_run("p", &other, phase::PHASES[0], "other")
+            .unwrap();
+        let run_id = stores
+            .audit
+            .create_run("p", &ws, phase::PHASES[0], "request")
+            .unwrap();
+
+        post(&stores, &ws, "hello").unwrap();
+
+        let run = stores.audit.get_run(&run_id).unwrap();
+        let message = run
+            .events
+            .iter()
+            .find(|e| e.event_type == "user.request")
+            .expect("message event recorded on the run");
+        assert_eq!(message.payload["content"], "hello");
+        let other_run = stores.audit.get_run(&other_run).unwrap();
+        assert!(other_run
+            .events
+            .iter()
+            .all(|e| e.event_type != "user.request"));
+        let adopted = WORKSTREAMS
+            .lock_recover()
+            .iter()
+            .find(|w| w.id == ws)
+            .and_then(|w| w.run_id.clone());
+        assert_eq!(adopted, Some(run_id));
+    }
+
+    fn project(id: &str, name: &str) -> ProjectData {
+        ProjectData {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: String::new(),
+            created_at: Utc::now().to_rfc3339(),
+            status: "active".to_string(),
+            vault_path: String::new(),
+            workstreams: Vec::new(),
+            imported_ids: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn duplicate_project_name_is_rejected() {
+        let projects = vec![project("a", "Nebula")];
+
+        let err = check_unique_name(&projects, " nebula ", None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ProjectExists);
+        assert_eq!(err.details.unwrap()["existing_project_id"], "a");
+        assert!(check_unique_name(&projects, "Other", None).is_ok());
+    }
+
+    #[test]
+    fn archived_project_name_can_be_reused() {
+        let mut projects = vec![project("a", "Nebula")];
+        projects[0].status = ARCHIVED.to_string();
+
+        assert!(check_unique_name(&projects, "Nebula", None).is_ok());
+    }
+
+    #[test]
+    fn renaming_a_project_to_its_own_name_is_allowed() {
+        let projects = vec![project("a", "Nebula")];
+
+        assert!(check_unique_name(&projects, "NEBULA", Some("a")).is_ok());
+    }
+
+    /// A fresh project holding workstreams with the given statuses, oldest first
+    fn project_with_workstreams(statuses: &[&str]) -> String {
+        let project_id = Uuid::new_v4().to_string();
+        let mut workstreams = WORKSTREAMS.lock_recover();
+        for (i, status) in statuses.iter().enumerate() {
+            workstreams.push(WorkstreamData {
+                id: format!("{}-{}", project_id, i),
+                project_id: project_id.clone(),
+                title: format!("Workstream {}", i),
+                description: String::new(),
+                status: status.to_string(),
+                created_at: format!("2026-01-01T00:00:{:02}Z", i),
+                user_request: String::new(),
+                current_phase: phase::PHASES[0].to_string(),
+                messages: Vec::new(),
+                run_id: None,
+            });
+        }
+        project_id
+    }
+
+    #[test]
+    fn workstream_pages_filter_by_status_newest_first() {
+        let project_id =
+            project_with_workstreams(&["active", "completed", "active", "active", "archived"]);
+
+        let page = get_workstreams_paged(
+            project_id.clone(),
+            Some("active".to_string()),
+            None,
+            None,
+            Some(1),
+            Some(1),
+        );
+        assert_eq!(page.total, 3);
+        let ids: Vec<String> = page.workstreams.into_iter().map(|w| w.id).collect();
+        assert_eq!(ids, vec![format!("{}-2", project_id)]);
+
+        let page = get_workstreams_paged(project_id.clone(), None, None, None, None, None);
+        assert_eq!(page.total, 4);
+        let archived = get_workstreams_paged(
+            project_id,
+            Some(ARCHIVED.to_string()),
+            None,
+            None,
+            None,
+            None,
+        );
+        assert_eq!(archived.total, 1);
+    }
+
+    #[test]
+    fn workstream_page_beyond_the_end_is_empty_with_the_total() {
+        let project_id = project_with_workstreams(&["active", "active"]);
+
+        let page = get_workstreams_paged(project_id, None, None, None, Some(10), Some(5));
+        assert!(page.workstreams.is_empty());
+        assert_eq!(page.total, 2);
+    }
+
+    fn audit_migration_engine(granting: &[&str], refusing: &[&str]) -> PolicyEngine {
+        let engine = PolicyEngine::new();
+        for (project_id, grants) in granting
+            .iter()
+            .map(|p| (p, true))
+            .chain(refusing.iter().map(|p| (p, false)))
+        {
+            let mut policy = sample_policy(project_id);
+            if grants {
+                policy.tool_permissions.role_permissions.insert(
+                    "ops".to_string(),
+                    vec![crate::policy::ToolPermission {
+                        tool_id: "audit.migrate".to_string(),
+                        operations: vec!["write".to_string()],
+                        resource_scope: vec!["audit".to_string()],
+                        deny: false,
+                    }],
+                );
+            }
+            engine.set_policy(project_id, policy, None, None).unwrap();
+        }
+        engine
+    }
+
+    #[test]
+    fn audit_migration_needs_every_projects_clearance() {
+        let ids = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let engine = audit_migration_engine(&["a", "b"], &["c"]);
+
+        assert!(authorize_audit_migration(&ids(&["a", "b"]), "ops", &engine).is_ok());
+        let err = authorize_audit_migration(&ids(&["a", "c", "b"]), "ops", &engine).unwrap_err();
+        assert_eq!(err.code, ErrorCode::PermissionDenied);
+        assert_eq!(err.details.unwrap()["project_id"], "c");
+        // A project without a policy grants nothing
+        assert!(authorize_audit_migration(&ids(&["a", "missing"]), "ops", &engine).is_err());
+        assert!(authorize_audit_migration(&[], "ops", &engine).is_err());
+    }
+
+    #[test]
+    fn project_update_tells_a_null_vault_path_from_a_missing_one() {
+        let parse =
+            |json: serde_json::Value| -> ProjectUpdate { serde_json::from_value(json).unwrap() };
+
+        assert_eq!(parse(serde_json::json!({ "name": "N" })).vault_path, None);
+        assert_eq!(
+            parse(serde_json::json!({ "vault_path": null })).vault_path,
+            Some(None)
+        );
+        assert_eq!(
+            parse(serde_json::json!({ "vault_path": "/v" })).vault_path,
+            Some(Some("/v".to_string()))
+        );
+    }
+
+    #[test]
+    fn duplicated_workstream_hangs_off_the_source_run_start() {
+        let stores = stores(PolicyEngine::new());
+        let source = add_workstream();
+        let source_run = stores
+            .audit
+            .create_run("p", &source, phase::PHASES[0], "request")
+            .unwrap();
+        if let Some(ws) = WORKSTREAMS
+            .lock_recover()
+            .iter_mut()
+            .find(|w| w.id == source)
+        {
+            ws.run_id = Some(source_run.clone());
+        }
+
+        let copy = duplicate(
+            &source,
+            "Copy".to_string(),
+            false,
+            &stores.audit,
+            &stores.vault,
+        )
+        .unwrap();
+
+        let duplicated = stores
+            .audit
+            .get_events("p", usize::MAX)
+            .into_iter()
+            .find(|e| e.event_type == "workstream.duplicated" && e.workstream_id == copy.id)
+            .unwrap();
+        let start = stores.audit.run_start_event(&source_run).unwrap();
+        assert_eq!(duplicated.parent_event_id, Some(start.id));
+        assert_eq!(duplicated.payload["source_workstream_id"], source);
+    }
+
+    #[test]
+    fn restricted_notes_need_a_cleared_role_to_delete_or_restore() {
+        let stores = stores(engine_with(restricted_notes_policy()));
+        let _dir = classified_vault(&stores);
+        let check = |path: &str, role: Option<&str>| {
+            check_existing_note_write(
+                "p",
+                path,
+                role,
+                "delete",
+                &stores.vault,
+                &stores.policy,
+                &stores.audit,
+            )
+        };
+
+        let err = check("keys.md", None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::AccessDenied);
+        let denial = &stores.audit.get_events("p", 1)[0];
+        assert_eq!(denial.event_type, "vault.access_denied");
+        assert_eq!(denial.payload["operation"], "delete");
+        assert!(check("keys.md", Some("security")).is_ok());
+        assert!(check("open.md", None).is_ok());
+
+        let trash_path = stores.vault.delete_note("p", "keys.md").unwrap();
+        assert!(check(&trash_path, None).is_err());
+        assert!(check(&trash_path, Some("security")).is_ok());
+        // A missing note is left for the operation itself to report
+        assert!(check("gone.md", None).is_ok());
+    }
+
+    fn workstream(id: &str) -> WorkstreamData {
+        WORKSTREAMS
+            .lock_recover()
+            .iter()
+            .find(|w| w.id == id)
+            .cloned()
+            .unwrap()
+    }
+
+    /// A workstream of project `p` in `phase` with a running run
+    fn add_workstream_in(stores: &Stores, current_phase: &str) -> (String, String) {
+        let id = add_workstream();
+        let run_id = stores
+            .audit
+            .create_run("p", &id, current_phase, "request")
+            .unwrap();
+        if let Some(ws) = WORKSTREAMS.lock_recover().iter_mut().find(|w| w.id == id) {
+            ws.current_phase = current_phase.to_string();
+            ws.status = phase::status_for_phase(current_phase).to_string();
+            ws.run_id = Some(run_id.clone());
+        }
+        (id, run_id)
+    }
+
+    #[test]
+    fn reopen_cancels_the_running_run() {
+        let stores = stores(PolicyEngine::new());
+        let (id, old_run) = add_workstream_in(&stores, "test");
+
+        let reopened = reopen(
+            &id,
+            "build".to_string(),
+            "flaky".to_string(),
+            &stores.audit,
+            &stores.vault,
+        )
+        .unwrap();
+
+        assert_eq!(reopened.current_phase, "build");
+        assert_ne!(reopened.run_id.as_deref(), Some(old_run.as_str()));
+        assert_eq!(stores.audit.get_run(&old_run).unwrap().status, "cancelled");
+    }
+
+    #[test]
+    fn reopen_refuses_to_skip_forward() {
+        let stores = stores(PolicyEngine::new());
+        let (id, run_id) = add_workstream_in(&stores, "design");
+
+        let err = reopen(
+            &id,
+            "deploy".to_string(),
+            String::new(),
+            &stores.audit,
+            &stores.vault,
+        )
+        .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert_eq!(workstream(&id).current_phase, "design");
+        assert_eq!(stores.audit.get_run(&run_id).unwrap().status, "running");
+
+        // Restarting the current phase is allowed
+        assert!(reopen(
+            &id,
+            "design".to_string(),
+            String::new(),
+            &stores.audit,
+            &stores.vault
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn reopen_from_a_terminal_state_may_go_anywhere() {
+        let stores = stores(PolicyEngine::new());
+        let (id, run_id) = add_workstream_in(&stores, "design");
+        stores.audit.complete_run(&run_id, "failed").unwrap();
+        if let Some(ws) = WORKSTREAMS.lock_recover().iter_mut().find(|w| w.id == id) {
+            ws.status = "failed".to_string();
+        }
+
+        let reopened = reopen(
+            &id,
+            "deploy".to_string(),
+            String::new(),
+            &stores.audit,
+            &stores.vault,
+        )
+        .unwrap();
+        assert_eq!(reopened.current_phase, "deploy");
+        assert_eq!(stores.audit.get_run(&run_id).unwrap().status, "failed");
+    }
+
+    fn add_project(id: &str) {
+        PROJECTS.lock_recover().push(project(id, id));
+    }
+
+    #[test]
+    fn project_edit_is_audited_and_registers_the_vault() {
+        let stores = stores(PolicyEngine::new());
+        add_project("edit-vault");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+
+        let edited = edit_project(
+            "edit-vault",
+            ProjectUpdate {
+                name: Some("Renamed".to_string()),
+                description: None,
+                vault_path: Some(Some(path.clone())),
+            },
+            true,
+            &stores.vault,
+            &stores.audit,
+        )
+        .unwrap();
+
+        assert!(edited.vault_changed);
+        assert_eq!(edited.project.vault_path, path);
+        assert_eq!(
+            stores.vault.vault_root("edit-vault").as_deref(),
+            Some(dir.path())
+        );
+        let event = &stores.audit.get_events("edit-vault", 1)[0];
+        assert_eq!(event.event_type, "project.updated");
+        assert_eq!(event.payload["changes"]["name"]["to"], "Renamed");
+        assert_eq!(event.payload["changes"]["vault_path"]["to"], path.as_str());
+    }
+
+    #[test]
+    fn project_edit_with_a_bad_vault_changes_nothing() {
+        let stores = stores(PolicyEngine::new());
+        add_project("edit-bad-vault");
+
+        let result = edit_project(
+            "edit-bad-vault",
+            ProjectUpdate {
+                name: Some("Renamed".to_string()),
+                description: None,
+                vault_path: Some(Some("/no/such/vault".to_string())),
+            },
+            true,
+            &stores.vault,
+            &stores.audit,
+        );
+
+        assert!(result.is_err());
+        let saved = PROJECTS
+            .lock_recover()
+            .iter()
+            .find(|p| p.id == "edit-bad-vault")
+            .cloned()
+            .unwrap();
+        assert_eq!(saved.name, "edit-bad-vault");
+        assert!(saved.vault_path.is_empty());
+        assert!(stores.audit.get_events("edit-bad-vault", 10).is_empty());
+    }
+
+    /// A bundle of `project` with its workstreams and `audit`'s events for it
+    fn bundle_of(project: ProjectData, audit: &AuditStore) -> ProjectBundle {
+        let workstreams = WORKSTREAMS
+            .lock_recover()
+            .iter()
+            .filter(|w| project.workstreams.contains(&w.id))
+            .cloned()
+            .collect();
+        let mut audit_events = audit.get_events(&project.id, usize::MAX);
+        audit_events.reverse();
+        ProjectBundle {
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            project,
+            workstreams,
+            policy: None,
+            notes: Vec::new(),
+            audit_events,
+        }
+    }
+
+    fn import_into(stores: &Stores, bundle: ProjectBundle) -> ProjectImport {
+        import_bundle(
+            bundle,
+            String::new(),
+            true,
+            &stores.vault,
+            &stores.policy,
+            &stores.audit,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn imported_history_is_read_under_the_new_ids() {
+        let source = stores(PolicyEngine::new());
+        let ws_id = add_workstream();
+        let run_id = source
+            .audit
+            .create_run("p", &ws_id, "build", "ship it")
+            .unwrap();
+        source
+            .audit
+            .record_event(AuditEvent::new(
+                "p",
+                &ws_id,
+                &run_id,
+                "tool.called",
+                AuditActor::agent_role("coder"),
+                serde_json::json!({ "kind": "tool" }),
+            ))
+            .unwrap();
+        if let Some(ws) = WORKSTREAMS
+            .lock_recover()
+            .iter_mut()
+            .find(|w| w.id == ws_id)
+        {
+            ws.run_id = Some(run_id.clone());
+        }
+        let mut original = project("p", "Imported");
+        original.workstreams = vec![ws_id.clone()];
+
+        let target = stores(PolicyEngine::new());
+        let imported = import_into(&target, bundle_of(original, &source.audit));
+        let project_id = imported.project.id.clone();
+        let new_ws = imported.project.workstreams[0].clone();
+        assert_eq!(imported.project.imported_ids["p"], project_id);
+        assert_eq!(imported.project.imported_ids[&ws_id], new_ws);
+
+        let history: Vec<String> = target
+            .audit
+            .get_events(&project_id, usize::MAX)
+            .into_iter()
+            .map(|e| e.event_type)
+            .collect();
+        assert_eq!(history, ["project.imported", "tool.called", "run.started"]);
+        assert_eq!(workstream(&new_ws).run_id.as_deref(), Some(run_id.as_str()));
+        let runs = target.audit.runs_for_workstream(&new_ws);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].id, run_id);
+        let by_project = target.audit.search_runs(&RunQuery {
+            project_id: Some(project_id.clone()),
+            ..Default::default()
+        });
+        assert_eq!(by_project.len(), 1);
+
+        // Importing the import again still reaches the original events
+        let again = stores(PolicyEngine::new());
+        let reimported = import_into(&again, bundle_of(imported.project, &target.audit));
+        assert_eq!(reimported.project.imported_ids["p"], reimported.project.id);
+        let history = again.audit.get_events(&reimported.project.id, usize::MAX);
+        assert!(history.iter().any(|e| e.event_type == "tool.called"));
+        assert_eq!(
+            again
+                .audit
+                .runs_for_workstream(&reimported.project.workstreams[0])
+                .len(),
+            1
+        );
+    }
 }
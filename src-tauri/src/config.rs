@@ -0,0 +1,208 @@
+use crate::emitter::EmissionConfig;
+use crate::persist;
+use crate::sync::{self, MutexExt};
+use crate::vault::LevelRule;
+use globset::GlobBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Where project settings live, relative to the project's vault root
+const CONFIG_FILE: &str = ".nebula/config.json";
+
+/// Upper bound on the audit batch window; longer windows make the UI feel stalled
+const MAX_BATCH_WINDOW_MS: u64 = 60_000;
+
+/// Longest conversation message, in characters, when a project doesn't set one
+pub const DEFAULT_MAX_MESSAGE_LENGTH: usize = 100_000;
+
+/// Per-project settings store.
+/// Holds the settings subsystems read instead of hard-coding defaults, and
+/// persists them to `<vault>/.nebula/config.json` when the project has a vault.
+pub struct ConfigStore {
+    configs: Mutex<HashMap<String, ProjectConfig>>,
+}
+
+impl ConfigStore {
+    pub fn new() -> Self {
+        Self {
+            configs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Locks left poisoned by a panicking holder
+    pub fn poisoned_locks(&self) -> Vec<&'static str> {
+        sync::poisoned([("configs", self.configs.is_poisoned())])
+    }
+
+    /// The project's config, loading it from disk on first access.
+    /// The flag is set when this call did the load, so the caller can push
+    /// the settings into the subsystems that use them.
+    pub fn get_or_load(&self, project_id: &str, root: Option<&Path>) -> (ProjectConfig, bool) {
+        let mut configs = self.configs.lock_recover();
+        let loaded = !configs.contains_key(project_id);
+        let config = Self::cached_or_load(&mut configs, project_id, root);
+        (config.clone(), loaded)
+    }
+
+    /// Drop the cached config so the next access reads it from disk again,
+    /// e.g. after the project moved to another vault
+    pub fn forget(&self, project_id: &str) {
+        self.configs.lock_recover().remove(project_id);
+    }
+
+    fn cached_or_load<'a>(
+        configs: &'a mut HashMap<String, ProjectConfig>,
+        project_id: &str,
+        root: Option<&Path>,
+    ) -> &'a mut ProjectConfig {
+        configs
+            .entry(project_id.to_string())
+            .or_insert_with(|| root.and_then(Self::load).unwrap_or_default())
+    }
+
+    fn load(root: &Path) -> Option<ProjectConfig> {
+        let path = root.join(CONFIG_FILE);
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable config {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Apply a JSON merge patch (RFC 7386) to the project's config, validate
+    /// the result, and persist it. Nothing changes if validation fails.
+    pub fn update(
+        &self,
+        project_id: &str,
+        patch: &serde_json::Value,
+        root: Option<&Path>,
+    ) -> Result<ProjectConfig, ConfigError> {
+        let mut configs = self.configs.lock_recover();
+        let current = Self::cached_or_load(&mut configs, project_id, root);
+
+        let mut merged =
+            serde_json::to_value(&*current).map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        merge_patch(&mut merged, patch);
+        let config: ProjectConfig =
+            serde_json::from_value(merged).map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        config.validate()?;
+
+        if let Some(root) = root {
+            persist::write_json(&root.join(CONFIG_FILE), &config)
+                .map_err(|e| ConfigError::IoError(e.to_string()))?;
+        }
+
+        *current = config.clone();
+        Ok(config)
+    }
+}
+
+/// Settings for a single project. Missing keys take their defaults, so
+/// older config files keep loading as fields are added.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Vault paths (globs) that scans and listings skip
+    pub ignore_patterns: Vec<String>,
+    /// Path-to-template-level mapping, first match wins
+    pub template_levels: Vec<LevelRule>,
+    /// Batching of audit events sent to the frontend
+    pub emission: EmissionConfig,
+    /// Days audit events are kept before pruning; unset keeps them forever
+    pub audit_retention_days: Option<u32>,
+    /// Longest message `send_message` accepts, in characters; unset uses
+    /// `DEFAULT_MAX_MESSAGE_LENGTH`
+    pub max_message_length: Option<usize>,
+}
+
+impl ProjectConfig {
+    pub fn max_message_length(&self) -> usize {
+        self.max_message_length
+            .unwrap_or(DEFAULT_MAX_MESSAGE_LENGTH)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for pattern in &self.ignore_patterns {
+            validate_glob(pattern)?;
+        }
+        for rule in &self.template_levels {
+            validate_glob(&rule.pattern)?;
+            if rule.level > 2 {
+                return Err(ConfigError::Invalid(format!(
+                    "template level for '{}' must be 0, 1, or 2",
+                    rule.pattern
+                )));
+            }
+        }
+        if self.emission.batch_window_ms > MAX_BATCH_WINDOW_MS {
+            return Err(ConfigError::Invalid(format!(
+                "batch_window_ms must be at most {}",
+                MAX_BATCH_WINDOW_MS
+            )));
+        }
+        if self.audit_retention_days == Some(0) {
+            return Err(ConfigError::Invalid(
+                "audit_retention_days must be at least 1".to_string(),
+            ));
+        }
+        if self.max_message_length == Some(0) {
+            return Err(ConfigError::Invalid(
+                "max_message_length must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn validate_glob(pattern: &str) -> Result<(), ConfigError> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|_| ())
+        .map_err(|e| ConfigError::Invalid(format!("{}: {}", pattern, e)))
+}
+
+/// RFC 7386: objects merge recursively, `null` removes a key, anything else replaces
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let target = target.as_object_mut().unwrap();
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(
+                target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                value,
+            );
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+    #[error("IO error: {0}")]
+    IoError(String),
+}
+
+impl Serialize for ConfigError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
@@ -0,0 +1,174 @@
+use crate::audit::AuditEvent;
+use crate::sync::MutexExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// How often the background flusher checks for batches whose window has elapsed
+const FLUSH_TICK: Duration = Duration::from_millis(50);
+
+/// Coalesces audit events into `audit://batch` Tauri events so a burst of
+/// tool calls doesn't flood the UI. Priority event types skip the batch and
+/// go out immediately as `audit://event`. Emission is best-effort: without an
+/// attached app handle nothing is buffered or sent.
+pub struct AuditEmitter {
+    app: Mutex<Option<AppHandle>>,
+    pending: Mutex<HashMap<String, PendingBatch>>,
+    configs: Mutex<HashMap<String, EmissionConfig>>,
+}
+
+struct PendingBatch {
+    opened_at: Instant,
+    events: Vec<AuditEvent>,
+}
+
+impl AuditEmitter {
+    pub fn new() -> Self {
+        Self {
+            app: Mutex::new(None),
+            pending: Mutex::new(HashMap::new()),
+            configs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start emitting to the app and spawn the batch flusher
+    pub fn attach(self: &Arc<Self>, app: AppHandle) {
+        let mut slot = self.app.lock_recover();
+        let first_attach = slot.is_none();
+        *slot = Some(app);
+        drop(slot);
+
+        if first_attach {
+            let emitter = Arc::clone(self);
+            std::thread::spawn(move || loop {
+                std::thread::sleep(FLUSH_TICK);
+                emitter.flush_due();
+            });
+        }
+    }
+
+    pub fn config(&self, project_id: &str) -> EmissionConfig {
+        let configs = self.configs.lock_recover();
+        configs.get(project_id).cloned().unwrap_or_default()
+    }
+
+    pub fn configure(&self, project_id: &str, config: EmissionConfig) {
+        let mut configs = self.configs.lock_recover();
+        configs.insert(project_id.to_string(), config);
+    }
+
+    /// Queue an event for the next batch, or send it now if it's a priority type
+    pub fn enqueue(&self, event: &AuditEvent) {
+        if self.app.lock_recover().is_none() {
+            return;
+        }
+
+        let config = self.config(&event.project_id);
+        if config.batch_window_ms == 0 || config.priority_event_types.contains(&event.event_type) {
+            // Flush first so the UI still sees events in recorded order
+            self.flush_project(&event.project_id);
+            self.emit("audit://event", event);
+            return;
+        }
+
+        let mut pending = self.pending.lock_recover();
+        pending
+            .entry(event.project_id.clone())
+            .or_insert_with(|| PendingBatch {
+                opened_at: Instant::now(),
+                events: Vec::new(),
+            })
+            .events
+            .push(event.clone());
+    }
+
+    /// Emit every batch whose window has elapsed
+    pub fn flush_due(&self) {
+        let due: Vec<String> = {
+            let pending = self.pending.lock_recover();
+            pending
+                .iter()
+                .filter(|(project_id, batch)| {
+                    let window = Duration::from_millis(self.config(project_id).batch_window_ms);
+                    batch.opened_at.elapsed() >= window
+                })
+                .map(|(project_id, _)| project_id.clone())
+                .collect()
+        };
+
+        for project_id in due {
+            self.flush_project(&project_id);
+        }
+    }
+
+    /// Emit every pending batch regardless of its window, e.g. at shutdown
+    pub fn flush_all(&self) {
+        let batches: Vec<(String, PendingBatch)> = {
+            let mut pending = self.pending.lock_recover();
+            pending.drain().collect()
+        };
+
+        for (project_id, batch) in batches {
+            self.emit_batch(&project_id, batch.events);
+        }
+    }
+
+    fn flush_project(&self, project_id: &str) {
+        let batch = {
+            let mut pending = self.pending.lock_recover();
+            pending.remove(project_id)
+        };
+
+        if let Some(batch) = batch {
+            self.emit_batch(project_id, batch.events);
+        }
+    }
+
+    fn emit_batch(&self, project_id: &str, events: Vec<AuditEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        self.emit(
+            "audit://batch",
+            AuditBatch {
+                project_id: project_id.to_string(),
+                events,
+            },
+        );
+    }
+
+    fn emit<S: Serialize + Clone>(&self, name: &str, payload: S) {
+        let app = self.app.lock_recover();
+        if let Some(app) = app.as_ref() {
+            if let Err(e) = app.emit(name, payload) {
+                tracing::warn!("Failed to emit {}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// Per-project batching settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmissionConfig {
+    /// How long events accumulate before a batch is sent; 0 disables batching
+    pub batch_window_ms: u64,
+    /// Event types that are always sent immediately
+    pub priority_event_types: Vec<String>,
+}
+
+impl Default for EmissionConfig {
+    fn default() -> Self {
+        Self {
+            batch_window_ms: 250,
+            priority_event_types: vec!["run.failed".to_string(), "run.cancelled".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditBatch {
+    pub project_id: String,
+    pub events: Vec<AuditEvent>,
+}
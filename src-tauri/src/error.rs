@@ -0,0 +1,198 @@
+use crate::audit::{AuditError, MigrateError};
+use crate::config::ConfigError;
+use crate::policy::{PolicyFileError, PolicyWriteError};
+use crate::vault::{BatchWriteError, VaultError};
+use serde::{Deserialize, Serialize};
+
+/// Machine-readable reason a command failed, so the UI can pick a recovery
+/// action without parsing the message
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    VaultNotFound,
+    InvalidVaultPath,
+    InvalidPath,
+    NoteNotFound,
+    NoteExists,
+    NotTextNote,
+    TemplateViolation,
+    Conflict,
+    SnapshotNotFound,
+    PolicyNotFound,
+    PolicyVersionNotFound,
+    InvalidPolicy,
+    RunNotFound,
+    SpanNotFound,
+    ProjectNotFound,
+    ProjectNotEmpty,
+    ProjectExists,
+    WorkstreamNotFound,
+    AccessDenied,
+    PermissionDenied,
+    InvalidPattern,
+    InvalidInput,
+    InvalidConfig,
+    StorageUnavailable,
+    AuditWriteFailed,
+    VerificationFailed,
+    IoError,
+    Internal,
+}
+
+/// Error returned across the command boundary. `message` is for display;
+/// `details` carries structured context where a caller might need it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn permission_denied(reason: impl Into<String>) -> Self {
+        Self::new(ErrorCode::PermissionDenied, reason)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<VaultError> for CommandError {
+    fn from(e: VaultError) -> Self {
+        let code = match &e {
+            VaultError::VaultNotFound(_) => ErrorCode::VaultNotFound,
+            VaultError::NoteNotFound(_) => ErrorCode::NoteNotFound,
+            VaultError::NoteExists(_) => ErrorCode::NoteExists,
+            VaultError::IoError(_) => ErrorCode::IoError,
+            VaultError::AccessDenied(_) => ErrorCode::AccessDenied,
+            VaultError::InvalidPattern(_) => ErrorCode::InvalidPattern,
+            VaultError::SnapshotNotFound(_) => ErrorCode::SnapshotNotFound,
+            VaultError::NotTextNote(_) => ErrorCode::NotTextNote,
+            VaultError::InvalidVaultPath(_) => ErrorCode::InvalidVaultPath,
+            VaultError::InvalidPath(_) => ErrorCode::InvalidPath,
+            VaultError::InvalidFrontmatter(_) => ErrorCode::InvalidInput,
+            VaultError::TemplateViolation(_) => ErrorCode::TemplateViolation,
+            VaultError::Conflict {
+                on_disk_modified,
+                on_disk_hash,
+            } => {
+                return Self::new(ErrorCode::Conflict, e.to_string()).with_details(
+                    serde_json::json!({
+                        "on_disk_modified": on_disk_modified,
+                        "on_disk_hash": on_disk_hash
+                    }),
+                );
+            }
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<BatchWriteError> for CommandError {
+    fn from(e: BatchWriteError) -> Self {
+        let message = e.to_string();
+        let cause = CommandError::from(e.error);
+        Self::new(cause.code, message).with_details(serde_json::json!({
+            "index": e.index,
+            "path": e.path,
+            "rollback_failures": e.rollback_failures,
+            "cause": cause.details
+        }))
+    }
+}
+
+impl From<MigrateError> for CommandError {
+    fn from(e: MigrateError) -> Self {
+        let code = match &e {
+            MigrateError::StorageUnavailable => ErrorCode::StorageUnavailable,
+            MigrateError::IoError(_) => ErrorCode::IoError,
+            MigrateError::Verification(_) => ErrorCode::VerificationFailed,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<ConfigError> for CommandError {
+    fn from(e: ConfigError) -> Self {
+        let code = match &e {
+            ConfigError::Invalid(_) => ErrorCode::InvalidConfig,
+            ConfigError::IoError(_) => ErrorCode::IoError,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<PolicyWriteError> for CommandError {
+    fn from(e: PolicyWriteError) -> Self {
+        match &e {
+            PolicyWriteError::VersionConflict { expected, current } => {
+                Self::new(ErrorCode::Conflict, e.to_string()).with_details(serde_json::json!({
+                    "expected_version": expected,
+                    "current_version": current,
+                }))
+            }
+            PolicyWriteError::IoError(_) => Self::new(ErrorCode::IoError, e.to_string()),
+        }
+    }
+}
+
+impl From<PolicyFileError> for CommandError {
+    fn from(e: PolicyFileError) -> Self {
+        let code = match &e {
+            PolicyFileError::UnsupportedFormat(_) => ErrorCode::InvalidInput,
+            PolicyFileError::Parse { line, column, .. } => {
+                return Self::new(ErrorCode::InvalidPolicy, e.to_string())
+                    .with_details(serde_json::json!({ "line": line, "column": column }));
+            }
+            PolicyFileError::Serialize(_) => ErrorCode::Internal,
+            PolicyFileError::IoError(_) => ErrorCode::IoError,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<AuditError> for CommandError {
+    fn from(e: AuditError) -> Self {
+        let code = match &e {
+            AuditError::InvalidEvent(_)
+            | AuditError::DuplicateEvent(_)
+            | AuditError::InvalidCursor(_)
+            | AuditError::InvalidSelector(_)
+            | AuditError::InvalidStatus(_) => ErrorCode::InvalidInput,
+            AuditError::RunAlreadyCompleted(_) | AuditError::SpanAlreadyEnded(_) => {
+                ErrorCode::Conflict
+            }
+            AuditError::Persist(_) => ErrorCode::AuditWriteFailed,
+            AuditError::RunNotFound(_) => ErrorCode::RunNotFound,
+            AuditError::SpanNotFound(_) => ErrorCode::SpanNotFound,
+            AuditError::Serialize(_) => ErrorCode::Internal,
+        };
+        Self::new(code, e.to_string())
+    }
+}
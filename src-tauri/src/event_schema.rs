@@ -0,0 +1,132 @@
+//! Known audit event types and the payload fields each must carry.
+//! `AuditStore::record_event` checks every event against this registry. An
+//! event that doesn't match is still recorded, so nothing is lost, but the
+//! problem is logged and noted in its payload under `unvalidated`. That way
+//! a typo like `test.pased` stands out instead of silently not counting.
+
+use crate::audit::AuditEvent;
+use std::collections::HashMap;
+
+/// Payload key holding the reason an event failed validation
+pub const UNVALIDATED_KEY: &str = "unvalidated";
+
+/// Every known event type with its required payload fields. New kinds are
+/// added here.
+const KNOWN_KINDS: &[(&str, &[&str])] = &[
+    // Runs
+    ("run.started", &["kind", "status"]),
+    ("run.completed", &["kind", "status"]),
+    ("run.failed", &["kind", "status"]),
+    ("run.cancelled", &["kind", "status"]),
+    ("span.started", &["kind", "name"]),
+    ("span.ended", &["kind", "name", "status", "duration_ms"]),
+    // Projects and workstreams
+    ("project.updated", &["kind", "action", "changes"]),
+    ("project.imported", &["kind", "action", "source_project_id"]),
+    ("project.archived", &["kind", "action"]),
+    ("project.deleted", &["kind", "action"]),
+    ("workstream.archived", &["kind", "action"]),
+    ("workstream.deleted", &["kind", "action"]),
+    ("workstream.reopened", &["kind", "action", "to_phase"]),
+    (
+        "workstream.duplicated",
+        &["kind", "action", "source_workstream_id"],
+    ),
+    (
+        "workstream.phase_changed",
+        &["kind", "action", "from_phase", "to_phase"],
+    ),
+    (
+        "workstream.status_changed",
+        &["kind", "action", "from_status", "to_status"],
+    ),
+    ("user.request", &["kind", "content"]),
+    ("user.approval", &["kind"]),
+    ("user.rejection", &["kind"]),
+    ("agent.message", &["kind", "content"]),
+    // Agent work, counted in run summaries
+    ("agent.decision", &["kind"]),
+    ("agent.delegation", &["kind"]),
+    ("agent.handoff", &["kind"]),
+    ("tool.call", &["kind"]),
+    ("tool.result", &["kind"]),
+    ("code.read", &["kind"]),
+    ("code.write", &["kind"]),
+    ("code.commit", &["kind"]),
+    ("code.branch.create", &["kind"]),
+    ("code.merge", &["kind"]),
+    ("code.rollback", &["kind"]),
+    ("test.started", &["kind"]),
+    ("test.passed", &["kind"]),
+    ("test.failed", &["kind"]),
+    ("test.report", &["kind"]),
+    ("documentation.read", &["kind"]),
+    ("documentation.write", &["kind"]),
+    ("documentation.consolidated", &["kind"]),
+    ("linear.issue.created", &["kind"]),
+    ("linear.issue.updated", &["kind"]),
+    ("figma.design.ingested", &["kind"]),
+    // Gates, deployments, and policy
+    ("gate.evaluated", &["kind", "passed"]),
+    ("gate.passed", &["kind"]),
+    ("gate.failed", &["kind"]),
+    ("deploy.started", &["kind"]),
+    ("deploy.progressed", &["kind"]),
+    ("deploy.completed", &["kind"]),
+    ("deploy.rolled-back", &["kind"]),
+    ("deploy.failed", &["kind"]),
+    (
+        "deploy.rollback_triggered",
+        &["kind", "environment", "metric"],
+    ),
+    (
+        "deploy.permission_checked",
+        &["kind", "environment", "allowed"],
+    ),
+    ("policy.decision", &["action", "resource", "allowed"]),
+    ("policy.evaluated", &["kind"]),
+    ("policy.violation", &["kind"]),
+    ("config.updated", &["kind", "patch"]),
+    // Vault
+    ("vault.access_denied", &["kind", "operation", "note_path"]),
+    ("vault.note.deleted", &["note_path", "trash_path"]),
+    ("vault.note.restored", &["note_path", "trash_path"]),
+    ("vault.note.moved", &["from", "to", "updated"]),
+    // Audit log maintenance
+    ("audit.migrated", &["kind", "action"]),
+    ("audit.deduplicated", &["kind", "action"]),
+    ("audit.pruned", &["kind", "action", "removed"]),
+];
+
+/// Required payload fields per event type, built from `KNOWN_KINDS`
+pub struct EventSchema {
+    kinds: HashMap<&'static str, &'static [&'static str]>,
+}
+
+impl EventSchema {
+    pub fn new() -> Self {
+        Self {
+            kinds: KNOWN_KINDS.iter().copied().collect(),
+        }
+    }
+
+    /// Why an event doesn't match its registered shape, if it doesn't
+    pub fn check(&self, event: &AuditEvent) -> Result<(), String> {
+        let Some(required) = self.kinds.get(event.event_type.as_str()) else {
+            return Err(format!("unknown event type '{}'", event.event_type));
+        };
+        let Some(payload) = event.payload.as_object() else {
+            return Err("payload is not an object".to_string());
+        };
+        let missing: Vec<&str> = required
+            .iter()
+            .copied()
+            .filter(|field| !payload.contains_key(*field))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("payload is missing {}", missing.join(", ")))
+        }
+    }
+}
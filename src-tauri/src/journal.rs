@@ -0,0 +1,232 @@
+//! Durable backends for the audit log.
+//! The in-memory `AuditStore` is always the working copy; a journal decides
+//! whether and where events are also written so they survive a restart.
+
+use crate::audit::AuditEvent;
+use crate::chain;
+use crate::persist::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const EVENTS_FILE: &str = "events.jsonl";
+const BACKEND_FILE: &str = "backend.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditBackendKind {
+    /// Events live only in memory and are lost on exit
+    #[default]
+    Memory,
+    /// Events are appended to `events.jsonl` in the audit data directory
+    Jsonl,
+}
+
+/// The active backend plus the directory it persists to
+pub struct AuditJournal {
+    kind: AuditBackendKind,
+    dir: Option<PathBuf>,
+}
+
+impl AuditJournal {
+    pub fn new() -> Self {
+        Self {
+            kind: AuditBackendKind::Memory,
+            dir: None,
+        }
+    }
+
+    pub fn kind(&self) -> AuditBackendKind {
+        self.kind
+    }
+
+    /// The attached data directory, if any
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Point the journal at a data directory and return the backend that was
+    /// last selected there, or JSONL for a fresh directory. The journal stays
+    /// in memory mode until `activate`, so loading the existing log doesn't
+    /// re-append it.
+    pub fn attach(&mut self, dir: PathBuf) -> AuditBackendKind {
+        let kind = std::fs::read_to_string(dir.join(BACKEND_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or(AuditBackendKind::Jsonl);
+        self.dir = Some(dir);
+        kind
+    }
+
+    pub fn activate(&mut self, kind: AuditBackendKind) {
+        self.kind = kind;
+    }
+
+    /// Record the selected backend so the next launch reopens it
+    pub fn save_kind(&self, kind: AuditBackendKind) -> std::io::Result<()> {
+        let Some(dir) = self.dir.as_ref() else {
+            return Ok(());
+        };
+        write_atomic(
+            &dir.join(BACKEND_FILE),
+            serde_json::to_string(&kind)?.as_bytes(),
+        )
+    }
+
+    /// Append events to the active backend
+    pub fn append(&self, events: &[AuditEvent]) -> std::io::Result<()> {
+        match self.events_path() {
+            Some(path) if !events.is_empty() => append_lines(&path, events),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace the active backend's contents, e.g. after maintenance removed events
+    pub fn rewrite(&self, events: &[AuditEvent]) -> std::io::Result<()> {
+        match self.events_path() {
+            Some(path) => Self::write_events(&path, events),
+            None => Ok(()),
+        }
+    }
+
+    fn events_path(&self) -> Option<PathBuf> {
+        match self.kind {
+            AuditBackendKind::Memory => None,
+            AuditBackendKind::Jsonl => self.dir.as_ref().map(|d| d.join(EVENTS_FILE)),
+        }
+    }
+
+    /// Path of the JSONL log in the attached directory, whatever the active backend
+    pub fn jsonl_path(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|d| d.join(EVENTS_FILE))
+    }
+
+    /// Write a complete log through a temp file and rename, so an interrupted
+    /// write never leaves a half-written or duplicated log behind
+    pub fn write_events(path: &Path, events: &[AuditEvent]) -> std::io::Result<()> {
+        let mut out = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut out, event)?;
+            out.push(b'\n');
+        }
+        write_atomic(path, &out)
+    }
+
+    /// Read a JSONL log. A torn trailing line from a crash is ignored; an
+    /// unreadable line anywhere else is skipped and its position reported.
+    pub fn read_events(path: &Path) -> std::io::Result<JournalRead> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(JournalRead::default()),
+            Err(e) => return Err(e),
+        };
+
+        let lines: Vec<(usize, &str)> = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect();
+        let mut read = JournalRead::default();
+        for (n, &(idx, line)) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(event) => read.events.push(event),
+                Err(e) if n + 1 == lines.len() => {
+                    tracing::warn!("Ignoring torn final audit line {}: {}", idx + 1, e)
+                }
+                Err(e) => {
+                    tracing::error!("Skipping unreadable audit line {}: {}", idx + 1, e);
+                    read.unreadable_at.get_or_insert(read.events.len());
+                }
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// A JSONL log as read back from disk
+#[derive(Debug, Default)]
+pub struct JournalRead {
+    pub events: Vec<AuditEvent>,
+    /// Position in `events` where the first unreadable line, other than a
+    /// torn final one, was skipped
+    pub unreadable_at: Option<usize>,
+}
+
+impl JournalRead {
+    /// The first position where the log can't be trusted: an unreadable
+    /// line or a broken hash link, whichever comes first
+    pub fn first_broken(&self) -> Option<usize> {
+        [self.unreadable_at, chain::first_broken_link(&self.events)]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+}
+
+fn append_lines(path: &Path, events: &[AuditEvent]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    for event in events {
+        serde_json::to_writer(&mut file, event)?;
+        file.write_all(b"\n")?;
+    }
+    file.sync_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditActor;
+
+    fn write_log(lines: &[&str]) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(EVENTS_FILE);
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        (dir, path)
+    }
+
+    fn event_line(event_type: &str) -> String {
+        let event = AuditEvent::new(
+            "p",
+            "w",
+            "r",
+            event_type,
+            AuditActor::user(),
+            serde_json::json!({}),
+        );
+        serde_json::to_string(&event).unwrap()
+    }
+
+    #[test]
+    fn torn_final_line_is_ignored() {
+        let (a, b) = (event_line("a"), event_line("b"));
+        let (_dir, path) = write_log(&[&a, &b, "{\"id\":\"tor", ""]);
+
+        let read = AuditJournal::read_events(&path).unwrap();
+        assert_eq!(read.events.len(), 2);
+        assert_eq!(read.unreadable_at, None);
+    }
+
+    #[test]
+    fn unreadable_line_mid_file_is_reported() {
+        let (a, b) = (event_line("a"), event_line("b"));
+        let (_dir, path) = write_log(&[&a, "not json", &b]);
+
+        let read = AuditJournal::read_events(&path).unwrap();
+        assert_eq!(read.events.len(), 2);
+        assert_eq!(read.unreadable_at, Some(1));
+    }
+
+    #[test]
+    fn missing_log_reads_as_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let read = AuditJournal::read_events(&dir.path().join(EVENTS_FILE)).unwrap();
+        assert!(read.events.is_empty());
+        assert_eq!(read.first_broken(), None);
+    }
+}
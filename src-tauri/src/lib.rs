@@ -1,51 +1,216 @@
 use tauri::Manager;
 
+mod chain;
 mod commands;
-mod policy;
+mod config;
+mod emitter;
+mod error;
+mod event_schema;
+mod journal;
+mod links;
+mod payload_query;
+mod persist;
+mod phase;
+/// Public so `benches/policy.rs` can drive the engine
+pub mod policy;
+mod render;
+mod search_index;
+mod signing;
+mod subscription;
+mod sync;
+mod tags;
+mod templates;
+mod transcript;
 mod audit;
 mod vault;
+mod watcher;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_window_state::Builder::default().build())
         .setup(|app| {
-            let _handle = app.handle().clone();
+            let handle = app.handle().clone();
             tracing_subscriber::fmt::init();
             tracing::info!("Nebula IDE starting...");
 
+            let data_dir = app.path().app_data_dir();
+            if let Err(e) = &data_dir {
+                tracing::warn!(
+                    "No app data dir; persistence and audit signing disabled: {}",
+                    e
+                );
+            }
+
             // Initialize the audit log
             let audit_store = audit::AuditStore::new();
+            if let Ok(dir) = &data_dir {
+                audit_store.attach_storage(dir.join("audit"));
+            }
+            audit_store.attach_app_handle(handle);
             app.manage(audit_store);
 
+            // Initialize the export signing key store
+            let key_store = signing::SigningKeyStore::new();
+            if let Ok(dir) = &data_dir {
+                key_store.set_key_path(dir.join("audit-signing.key"));
+            }
+            app.manage(key_store);
+
             // Initialize the policy engine
             let policy_engine = policy::PolicyEngine::new();
-            app.manage(policy_engine);
+
+            // Initialize the per-project config store
+            let config_store = config::ConfigStore::new();
 
             // Initialize the vault manager
             let vault_manager = vault::VaultManager::new();
+            vault_manager.attach_app_handle(app.handle().clone());
+
+            // Restore projects and workstreams, re-registering their vaults,
+            // loading each vault's saved policy, and mapping imported history
+            if let Ok(dir) = &data_dir {
+                commands::load_state(
+                    dir.clone(),
+                    &vault_manager,
+                    &policy_engine,
+                    &app.state::<audit::AuditStore>(),
+                );
+            }
+            commands::load_configs(
+                &config_store,
+                &vault_manager,
+                &app.state::<audit::AuditStore>(),
+            );
+            app.manage(config_store);
             app.manage(vault_manager);
+            app.manage(policy_engine);
+
+            // Prune audit events past their project's retention window
+            let prune_handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                let audit_store = prune_handle.state::<audit::AuditStore>();
+                if let Err(e) = audit_store.prune(chrono::Utc::now()) {
+                    tracing::warn!("Failed to prune audit log: {}", e);
+                }
+                std::thread::sleep(audit::PRUNE_INTERVAL);
+            });
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_projects,
+            commands::system_health,
+            commands::get_projects_with_stats,
+            commands::get_projects_paged,
             commands::create_project,
+            commands::register_vault,
             commands::get_project,
+            commands::update_project,
+            commands::export_project,
+            commands::import_project,
             commands::get_workstreams,
+            commands::get_workstreams_paged,
+            commands::get_workstream,
+            commands::archive_project,
+            commands::archive_workstream,
+            commands::delete_project,
+            commands::delete_workstream,
             commands::create_workstream,
+            commands::duplicate_workstream,
+            commands::reopen_workstream,
+            commands::transition_phase,
+            commands::update_workstream_status,
             commands::send_message,
+            commands::send_agent_message,
             commands::get_audit_log,
+            commands::get_audit_log_page,
+            commands::query_events,
+            commands::search_audit,
+            commands::import_audit_log,
+            commands::dedup_audit_log,
+            commands::prune_audit_log,
+            commands::get_event_histogram,
+            commands::migrate_audit_backend,
+            commands::get_audit_backend,
+            commands::verify_audit_chain,
+            commands::export_signed_run,
+            commands::get_event_tree,
+            commands::export_run_report,
+            commands::verify_exported_run,
+            commands::get_audit_signing_key,
+            commands::get_audit_emission_config,
+            commands::configure_audit_emission,
+            commands::get_config,
+            commands::update_config,
             commands::get_policy,
             commands::update_policy,
+            commands::import_policy_from_file,
+            commands::export_policy_to_file,
+            commands::diff_policy,
+            commands::preview_redaction,
+            commands::redact_text,
+            commands::check_provider_access,
+            commands::evaluate_merge,
+            commands::gates_for_phase,
+            commands::plan_canary,
+            commands::evaluate_rollback,
+            commands::can_deploy,
+            commands::evaluate_permission,
+            commands::explain_permission,
             commands::read_vault_note,
+            commands::render_note,
+            commands::read_notes,
+            commands::open_note_in_obsidian,
             commands::write_vault_note,
+            commands::write_notes,
+            commands::patch_vault_frontmatter,
             commands::list_vault_notes,
+            commands::count_vault_notes,
+            commands::delete_vault_note,
+            commands::restore_vault_note,
+            commands::move_vault_note,
+            commands::create_vault_snapshot,
+            commands::list_vault_snapshots,
+            commands::diff_vault_snapshot,
+            commands::list_vault_templates,
+            commands::get_note_links,
+            commands::find_references,
+            commands::search_vault,
+            commands::search_vault_ranked,
+            commands::reindex_vault,
+            commands::vault_cache_stats,
+            commands::check_vault_health,
+            commands::write_attachment,
+            commands::read_attachment,
+            commands::get_attachment_info,
+            commands::get_backlinks,
+            commands::get_broken_links,
+            commands::list_notes_by_tag,
+            commands::all_tags,
+            commands::get_template_levels,
+            commands::set_template_levels,
+            commands::resolve_template_level,
             commands::get_run_record,
+            commands::complete_run,
+            commands::cancel_run,
+            commands::start_span,
+            commands::end_span,
+            commands::get_run_spans,
+            commands::search_runs,
+            commands::get_workstream_runs,
+            commands::get_project_stats,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running Nebula");
+        .build(tauri::generate_context!())
+        .expect("error while running Nebula")
+        .run(|app, event| {
+            if let tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit = event {
+                // Don't drop audit events still waiting in a batch window
+                app.state::<audit::AuditStore>().flush_emitter();
+            }
+        });
 }
@@ -0,0 +1,510 @@
+//! Link extraction for vault notes.
+//! Parses Obsidian `[[wikilinks]]`, `![[embeds]]`, markdown links, and external
+//! URLs out of a note body, and resolves link targets to vault-relative paths.
+
+use crate::vault::CacheStats;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+pub(crate) static WIKILINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(!?)\[\[([^\[\]\n]+?)\]\]").unwrap());
+
+static MD_LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(!?)\[[^\]\n]*\]\(<?([^)\s>]+)>?(?:\s+"[^"]*")?\)"#).unwrap());
+
+static HTML_PATH_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\b(?:src|href)\s*=\s*["']([^"']+)["']"#).unwrap());
+
+static URL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"https?://[^\s<>()\[\]"']+"#).unwrap());
+
+/// A `[[target#heading|alias]]` reference, or `![[...]]` when `embed` is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WikiLink {
+    pub target: String,
+    pub heading: Option<String>,
+    pub alias: Option<String>,
+    pub embed: bool,
+    pub line: usize,
+}
+
+/// Extract every wikilink and embed from a note body, in document order
+pub fn extract_wikilinks(body: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    for (idx, line) in body.lines().enumerate() {
+        for cap in WIKILINK_RE.captures_iter(line) {
+            let (target, heading, alias) = split_wikilink(&cap[2]);
+            links.push(WikiLink {
+                target: target.to_string(),
+                heading: heading.map(str::to_string),
+                alias: alias.map(str::to_string),
+                embed: &cap[1] == "!",
+                line: idx + 1,
+            });
+        }
+    }
+    links
+}
+
+/// Split the inside of `[[...]]` into its target, heading, and alias
+pub(crate) fn split_wikilink(inner: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (target, alias) = match inner.split_once('|') {
+        Some((t, a)) => (t, Some(a.trim())),
+        None => (inner, None),
+    };
+    let (target, heading) = match target.split_once('#') {
+        Some((t, h)) => (t, Some(h.trim())),
+        None => (target, None),
+    };
+    (target.trim(), heading, alias)
+}
+
+/// Replace the target of each wikilink or embed for which `retarget`
+/// returns a new one, leaving any `#heading` and `|alias` as written.
+/// Returns the new body and how many links changed.
+pub fn rewrite_wikilinks(
+    body: &str,
+    mut retarget: impl FnMut(&str) -> Option<String>,
+) -> (String, usize) {
+    let mut changed = 0;
+    let rewritten = WIKILINK_RE.replace_all(body, |cap: &regex::Captures| {
+        let inner = &cap[2];
+        let split = inner.find(['#', '|']).unwrap_or(inner.len());
+        let (target, rest) = inner.split_at(split);
+        match retarget(target.trim()) {
+            Some(new_target) if !target.trim().is_empty() => {
+                changed += 1;
+                format!("{}[[{}{}]]", &cap[1], new_target, rest)
+            }
+            _ => cap[0].to_string(),
+        }
+    });
+    (rewritten.into_owned(), changed)
+}
+
+/// A path reference that isn't a wikilink: `[text](path)`, `![alt](path)`,
+/// or an HTML `src`/`href` attribute
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathLink {
+    pub target: String,
+    pub embed: bool,
+    pub html: bool,
+    pub line: usize,
+}
+
+/// Extract markdown and HTML path references, skipping external URLs and
+/// in-page `#anchors`. Targets are percent-decoded and have any `#fragment`
+/// removed.
+pub fn extract_path_links(body: &str) -> Vec<PathLink> {
+    let mut links = Vec::new();
+    for (idx, line) in body.lines().enumerate() {
+        let markdown = MD_LINK_RE
+            .captures_iter(line)
+            .map(|cap| (cap.get(2).unwrap().as_str(), &cap[1] == "!", false));
+        let html = HTML_PATH_RE
+            .captures_iter(line)
+            .map(|cap| (cap.get(1).unwrap().as_str(), false, true));
+
+        for (target, embed, is_html) in markdown.chain(html) {
+            let target = target.split('#').next().unwrap_or_default();
+            if target.is_empty() || has_scheme(target) {
+                continue;
+            }
+            links.push(PathLink {
+                target: percent_decode(target),
+                embed: embed || (is_html && line.to_lowercase().contains("<img")),
+                html: is_html,
+                line: idx + 1,
+            });
+        }
+    }
+    links
+}
+
+fn has_scheme(target: &str) -> bool {
+    match target.split_once(':') {
+        Some((scheme, _)) => {
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+')
+        }
+        None => false,
+    }
+}
+
+fn percent_decode(target: &str) -> String {
+    let bytes = target.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Resolve a relative path reference against the directory of the note it
+/// appears in. A leading `/` is taken as vault-relative. Returns `None` when
+/// the path climbs above the vault root.
+pub fn resolve_relative(source_note: &str, target: &str) -> Option<String> {
+    let mut parts: Vec<&str> = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut dir: Vec<&str> = source_note.split('/').collect();
+        dir.pop();
+        dir
+    };
+
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            s => parts.push(s),
+        }
+    }
+
+    Some(parts.join("/"))
+}
+
+/// Extract external `http(s)://` URLs from a note body
+pub fn extract_urls(body: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for m in URL_RE.find_iter(body) {
+        let url = m
+            .as_str()
+            .trim_end_matches(['.', ',', ';', ':'])
+            .to_string();
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// Resolves link targets the way Obsidian does: by vault-relative path
+/// (with or without `.md`) or by bare file name.
+pub struct LinkResolver {
+    by_path: HashMap<String, String>,
+    by_name: HashMap<String, String>,
+}
+
+impl LinkResolver {
+    /// Index every file under the vault root, skipping hidden directories
+    pub fn build(vault_root: &Path) -> Self {
+        let mut resolver = Self {
+            by_path: HashMap::new(),
+            by_name: HashMap::new(),
+        };
+        resolver.index_dir(vault_root, vault_root);
+        resolver
+    }
+
+    fn index_dir(&mut self, dir: &Path, vault_root: &Path) {
+        let Ok(read_dir) = std::fs::read_dir(dir) else {
+            return;
+        };
+
+        let mut paths: Vec<_> = read_dir.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            let hidden = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.index_dir(&path, vault_root);
+                continue;
+            }
+
+            let relative = path
+                .strip_prefix(vault_root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let file_name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+
+            if let Some(stem) = relative.strip_suffix(".md") {
+                self.by_path.insert(stem.to_lowercase(), relative.clone());
+                let name = file_name.trim_end_matches(".md").to_lowercase();
+                self.by_name.entry(name).or_insert_with(|| relative.clone());
+            }
+            self.by_path
+                .insert(relative.to_lowercase(), relative.clone());
+            self.by_name
+                .entry(file_name.to_lowercase())
+                .or_insert(relative);
+        }
+    }
+
+    /// Whether the file at a vault-relative path was indexed
+    pub fn contains(&self, path: &str) -> bool {
+        self.by_path.contains_key(&path.to_lowercase())
+    }
+
+    /// Resolve a link target to a vault-relative path, if the file exists
+    pub fn resolve(&self, target: &str) -> Option<&str> {
+        let key = target.trim_start_matches('/').to_lowercase();
+        self.by_path
+            .get(&key)
+            .or_else(|| self.by_name.get(&key))
+            .map(String::as_str)
+    }
+}
+
+/// Every wikilink in a vault resolved once, so backlink queries don't rescan
+/// the vault. Each note's links are kept with a hash of its content, so a
+/// rewritten note is only reparsed if it changed, and only its own backlink
+/// entries are touched. Creating or deleting a note re-resolves the stored
+/// links of every note, without reading any of them again.
+pub struct LinkIndex {
+    vault_root: PathBuf,
+    resolver: LinkResolver,
+    sources: HashMap<String, SourceLinks>,
+    backlinks: HashMap<String, BTreeSet<String>>,
+    built_at: String,
+    updated_at: Option<String>,
+}
+
+/// The links found in one note and the paths they resolved to
+struct SourceLinks {
+    hash: String,
+    links: Vec<WikiLink>,
+    targets: BTreeSet<String>,
+}
+
+impl LinkIndex {
+    /// Index the wikilinks and embeds in `notes` (vault-relative paths)
+    pub fn build(vault_root: &Path, notes: &[String]) -> Self {
+        let mut index = Self {
+            vault_root: vault_root.to_path_buf(),
+            resolver: LinkResolver::build(vault_root),
+            sources: HashMap::new(),
+            backlinks: HashMap::new(),
+            built_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: None,
+        };
+        for source in notes {
+            index.reparse(source);
+        }
+        index
+    }
+
+    /// Bring the index up to date after `path` was written, created, or
+    /// removed. Attachments only matter as link targets.
+    pub fn update(&mut self, path: &str) {
+        let exists = self.vault_root.join(path).is_file();
+        if !exists {
+            if let Some(old) = self.sources.remove(path) {
+                self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+                for target in &old.targets {
+                    self.remove_backlink(target, path);
+                }
+            }
+        } else if path.ends_with(".md") {
+            self.reparse(path);
+        }
+
+        // A new or removed file can change what any link resolves to
+        if exists != self.resolver.contains(path) {
+            self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+            self.resolver = LinkResolver::build(&self.vault_root);
+            self.backlinks.clear();
+            let sources: Vec<String> = self.sources.keys().cloned().collect();
+            for source in sources {
+                self.resolve_source(&source);
+            }
+        }
+    }
+
+    /// Re-read one note, replacing its links if its content changed
+    fn reparse(&mut self, source: &str) {
+        let Ok(content) = std::fs::read_to_string(self.vault_root.join(source)) else {
+            return;
+        };
+        let hash = hex::encode(Sha256::digest(content.as_bytes()));
+        if self.sources.get(source).is_some_and(|s| s.hash == hash) {
+            return;
+        }
+
+        self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+        let links = extract_wikilinks(&content)
+            .into_iter()
+            // `[[#heading]]` points back into the note itself
+            .filter(|link| !link.target.is_empty())
+            .collect();
+        if let Some(old) = self.sources.insert(
+            source.to_string(),
+            SourceLinks {
+                hash,
+                links,
+                targets: BTreeSet::new(),
+            },
+        ) {
+            for target in &old.targets {
+                self.remove_backlink(target, source);
+            }
+        }
+        self.resolve_source(source);
+    }
+
+    /// Resolve a note's stored links and record it as a backlink of each target
+    fn resolve_source(&mut self, source: &str) {
+        let Some(entry) = self.sources.get_mut(source) else {
+            return;
+        };
+        entry.targets = entry
+            .links
+            .iter()
+            .filter_map(|link| self.resolver.resolve(&link.target))
+            .filter(|target| *target != source)
+            .map(str::to_string)
+            .collect();
+        for target in &entry.targets {
+            self.backlinks
+                .entry(target.clone())
+                .or_default()
+                .insert(source.to_string());
+        }
+    }
+
+    fn remove_backlink(&mut self, target: &str, source: &str) {
+        if let Some(sources) = self.backlinks.get_mut(target) {
+            sources.remove(source);
+            if sources.is_empty() {
+                self.backlinks.remove(target);
+            }
+        }
+    }
+
+    /// Resolve a link target to a vault-relative path, if the file exists
+    pub fn resolve(&self, target: &str) -> Option<&str> {
+        self.resolver.resolve(target)
+    }
+
+    /// Notes linking to `target`, sorted by path
+    pub fn backlinks(&self, target: &str) -> impl Iterator<Item = &str> {
+        self.backlinks
+            .get(target)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Whether any of the note's links resolve to another file
+    pub fn links_out(&self, source: &str) -> bool {
+        self.sources
+            .get(source)
+            .is_some_and(|entry| !entry.targets.is_empty())
+    }
+
+    /// Links that resolve to no file, sorted by source and line
+    pub fn broken(&self) -> Vec<BrokenLink> {
+        let mut broken: Vec<BrokenLink> = self
+            .sources
+            .iter()
+            .flat_map(|(source, entry)| {
+                entry
+                    .links
+                    .iter()
+                    .filter(|link| self.resolver.resolve(&link.target).is_none())
+                    .map(|link| BrokenLink {
+                        source: source.clone(),
+                        target: link.target.clone(),
+                        line: link.line,
+                    })
+            })
+            .collect();
+        broken.sort_by(|a, b| a.source.cmp(&b.source).then(a.line.cmp(&b.line)));
+        broken
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            notes: self.sources.len(),
+            entries: self.backlinks.values().map(BTreeSet::len).sum(),
+            built_at: self.built_at.clone(),
+            updated_at: self.updated_at.clone(),
+        }
+    }
+}
+
+/// A wikilink whose target doesn't exist in the vault
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrokenLink {
+    pub source: String,
+    pub target: String,
+    pub line: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retarget_old(target: &str) -> Option<String> {
+        match target {
+            "old" => Some("new".to_string()),
+            "notes/old" => Some("archive/new".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rewrites_bare_and_folder_qualified_links() {
+        let (body, changed) = rewrite_wikilinks(
+            "See [[old]] and [[notes/old]], not [[other]].",
+            retarget_old,
+        );
+        assert_eq!(body, "See [[new]] and [[archive/new]], not [[other]].");
+        assert_eq!(changed, 2);
+    }
+
+    #[test]
+    fn keeps_aliases_and_headings() {
+        let (body, changed) = rewrite_wikilinks(
+            "[[old|the old one]] [[old#Setup]] [[notes/old#Setup|setup]]",
+            retarget_old,
+        );
+        assert_eq!(
+            body,
+            "[[new|the old one]] [[new#Setup]] [[archive/new#Setup|setup]]"
+        );
+        assert_eq!(changed, 3);
+    }
+
+    #[test]
+    fn rewrites_embeds_as_embeds() {
+        let (body, changed) = rewrite_wikilinks("![[old]] ![[old#Diagram]]", retarget_old);
+        assert_eq!(body, "![[new]] ![[new#Diagram]]");
+        assert_eq!(changed, 2);
+    }
+
+    #[test]
+    fn leaves_heading_only_links_alone() {
+        let (body, changed) = rewrite_wikilinks("[[#Setup]] [[ old ]]", retarget_old);
+        assert_eq!(body, "[[#Setup]] [[new]]");
+        assert_eq!(changed, 1);
+    }
+}
@@ -0,0 +1,106 @@
+//! Selectors over audit event payloads, for `AuditStore::search_payloads`.
+//! A selector is a dotted path from the payload root such as `commit.sha`,
+//! `files[0].path`, or `files[*].path`. `*` stands for every field or item at
+//! its level, and a leading `$` or `$.` is accepted and ignored.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A parsed payload selector
+#[derive(Debug, Clone)]
+pub struct PayloadPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Field(String),
+    Index(usize),
+    Any,
+}
+
+impl PayloadPath {
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let selector = selector.trim();
+        let rest = match selector.strip_prefix('$') {
+            Some(rest) => rest.strip_prefix('.').unwrap_or(rest),
+            None => selector,
+        };
+
+        let mut segments = Vec::new();
+        if rest.is_empty() {
+            return Ok(Self { segments });
+        }
+        for part in rest.split('.') {
+            let (name, mut brackets) = part.split_at(part.find('[').unwrap_or(part.len()));
+            match name {
+                "" if brackets.is_empty() => {
+                    return Err(format!("empty segment in '{}'", selector));
+                }
+                "" => {}
+                "*" => segments.push(Segment::Any),
+                name => segments.push(Segment::Field(name.to_string())),
+            }
+            while let Some(inner) = brackets.strip_prefix('[') {
+                let Some(end) = inner.find(']') else {
+                    return Err(format!("unclosed '[' in '{}'", selector));
+                };
+                segments.push(match &inner[..end] {
+                    "*" => Segment::Any,
+                    index => Segment::Index(index.parse().map_err(|_| {
+                        format!("'{}' is not an array index in '{}'", index, selector)
+                    })?),
+                });
+                brackets = &inner[end + 1..];
+            }
+            if !brackets.is_empty() {
+                return Err(format!("unexpected '{}' in '{}'", brackets, selector));
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    /// Every value the path selects in `root`; empty when it isn't there
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|value| match (segment, value) {
+                    (Segment::Field(name), Value::Object(map)) => {
+                        map.get(name).into_iter().collect()
+                    }
+                    (Segment::Index(i), Value::Array(items)) => items.get(*i).into_iter().collect(),
+                    (Segment::Any, Value::Object(map)) => map.values().collect(),
+                    (Segment::Any, Value::Array(items)) => items.iter().collect(),
+                    _ => Vec::new(),
+                })
+                .collect();
+        }
+        current
+    }
+}
+
+/// How a selected value is compared with the search value
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// The whole string equals the value; numbers and booleans match when
+    /// the value parses to the same number or boolean
+    #[default]
+    Exact,
+    /// The string contains the value
+    Contains,
+}
+
+impl MatchMode {
+    pub fn matches(self, leaf: &Value, wanted: &str) -> bool {
+        match (self, leaf) {
+            (MatchMode::Exact, Value::String(s)) => s == wanted,
+            (MatchMode::Exact, Value::Number(n)) => wanted.parse::<f64>().ok() == n.as_f64(),
+            (MatchMode::Exact, Value::Bool(b)) => wanted.parse::<bool>().ok() == Some(*b),
+            (MatchMode::Contains, Value::String(s)) => s.contains(wanted),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! The five-phase lifecycle every workstream moves through:
+//! Design → Build → Test → Deploy → Document, then `completed`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Working phases, in order
+pub const PHASES: [&str; 5] = ["design", "build", "test", "deploy", "document"];
+
+/// Phase a workstream sits in once all work is done
+pub const COMPLETED: &str = "completed";
+
+/// Statuses a workstream can be set to
+pub const STATUSES: [&str; 9] = [
+    "draft",
+    "planning",
+    "in-progress",
+    "testing",
+    "deploying",
+    "paused",
+    "completed",
+    "failed",
+    "cancelled",
+];
+
+pub fn is_working_phase(phase: &str) -> bool {
+    PHASES.contains(&phase)
+}
+
+/// Whether working phase `phase` comes after working phase `other`
+pub fn is_after(phase: &str, other: &str) -> bool {
+    let position = |p: &str| PHASES.iter().position(|&q| q == p);
+    matches!((position(phase), position(other)), (Some(a), Some(b)) if a > b)
+}
+
+pub fn is_terminal_status(status: &str) -> bool {
+    crate::audit::RUN_END_STATUSES.contains(&status)
+}
+
+/// Workstream status that corresponds to actively working a phase
+pub fn status_for_phase(phase: &str) -> &'static str {
+    match phase {
+        "design" => "planning",
+        "test" => "testing",
+        "deploy" => "deploying",
+        _ => "in-progress",
+    }
+}
+
+/// Which phases a workstream may move to from each phase. Phases missing
+/// from the map have no forward transitions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseMachine {
+    pub transitions: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for PhaseMachine {
+    /// Each working phase leads to the next, and the last one to `completed`
+    fn default() -> Self {
+        let order = PHASES.iter().chain(std::iter::once(&COMPLETED));
+        let transitions = order
+            .clone()
+            .zip(order.skip(1))
+            .map(|(from, to)| (from.to_string(), vec![to.to_string()]))
+            .collect();
+        Self { transitions }
+    }
+}
+
+impl PhaseMachine {
+    pub fn allowed_from(&self, phase: &str) -> &[String] {
+        self.transitions
+            .get(phase)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Check a move from `from` to `to`, naming the legal next phases if it isn't one
+    pub fn check(&self, from: &str, to: &str) -> Result<(), String> {
+        let allowed = self.allowed_from(from);
+        if allowed.iter().any(|p| p == to) {
+            return Ok(());
+        }
+        Err(if allowed.is_empty() {
+            format!("Cannot move from '{}': no transitions out of it", from)
+        } else {
+            format!(
+                "Cannot move from '{}' to '{}'; allowed: {}",
+                from,
+                to,
+                allowed.join(", ")
+            )
+        })
+    }
+}
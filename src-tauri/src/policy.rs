@@ -1,39 +1,230 @@
+use crate::persist;
+use crate::sync::{self, MutexExt};
+use chrono::{DateTime, Utc};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Mutex;
 
+/// Latest policy, relative to the project's vault root
+const POLICY_FILE: &str = ".nebula/policy.json";
+/// Every saved version as `v{n}.json`, never overwritten
+const POLICY_HISTORY_DIR: &str = ".nebula/policy_history";
+/// Slack when comparing traffic fractions and metric values
+const FRACTION_TOLERANCE: f64 = 1e-9;
+
 /// Policy engine — enforces what agents can do per project.
 /// Machine-enforced, versioned policy file per project.
 pub struct PolicyEngine {
     policies: Mutex<HashMap<String, NebulaPolicy>>,
+    compiled: Mutex<HashMap<String, CompiledPolicy>>,
+}
+
+impl Default for PolicyEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PolicyEngine {
     pub fn new() -> Self {
         Self {
             policies: Mutex::new(HashMap::new()),
+            compiled: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Locks left poisoned by a panicking holder
+    pub fn poisoned_locks(&self) -> Vec<&'static str> {
+        sync::poisoned([
+            ("policies", self.policies.is_poisoned()),
+            ("compiled", self.compiled.is_poisoned()),
+        ])
+    }
+
     pub fn get_policy(&self, project_id: &str) -> Option<NebulaPolicy> {
-        let policies = self.policies.lock().unwrap();
+        let policies = self.policies.lock_recover();
         policies.get(project_id).cloned()
     }
 
-    pub fn set_policy(&self, project_id: &str, policy: NebulaPolicy) {
-        let mut policies = self.policies.lock().unwrap();
+    /// Store a new version of the project's policy. The version is one past
+    /// the current one and `updated_at` is stamped; with a vault root the
+    /// version is also written to the policy history and as the latest policy.
+    /// With `expected_version` the write only goes through if that is still
+    /// the current version (0 for none yet), so concurrent editors can't
+    /// silently overwrite each other.
+    pub fn set_policy(
+        &self,
+        project_id: &str,
+        mut policy: NebulaPolicy,
+        expected_version: Option<u64>,
+        root: Option<&Path>,
+    ) -> Result<NebulaPolicy, PolicyWriteError> {
+        let mut policies = self.policies.lock_recover();
+        let current = policies
+            .get(project_id)
+            .and_then(|p| p.version.parse::<u64>().ok())
+            .unwrap_or(0);
+        if let Some(expected) = expected_version.filter(|v| *v != current) {
+            return Err(PolicyWriteError::VersionConflict { expected, current });
+        }
+        let version = current + 1;
+        policy.version = version.to_string();
+        policy.updated_at = Utc::now().to_rfc3339();
+
+        if let Some(root) = root {
+            let history = root
+                .join(POLICY_HISTORY_DIR)
+                .join(format!("v{}.json", version));
+            persist::write_json(&history, &policy)?;
+            persist::write_json(&root.join(POLICY_FILE), &policy)?;
+        }
+
+        self.install(&mut policies, project_id, policy.clone());
+        Ok(policy)
+    }
+
+    /// Load the latest saved policy from a project's vault, if there is one
+    pub fn load_policy(&self, project_id: &str, root: &Path) {
+        match persist::read_json::<NebulaPolicy>(&root.join(POLICY_FILE)) {
+            Ok(Some(policy)) => {
+                let mut policies = self.policies.lock_recover();
+                self.install(&mut policies, project_id, policy);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to load policy for {}: {}", project_id, e),
+        }
+    }
+
+    /// Read a stored policy version from the vault's history
+    pub fn load_version(root: &Path, version: u64) -> std::io::Result<Option<NebulaPolicy>> {
+        persist::read_json(
+            &root
+                .join(POLICY_HISTORY_DIR)
+                .join(format!("v{}.json", version)),
+        )
+    }
+
+    fn install(
+        &self,
+        policies: &mut HashMap<String, NebulaPolicy>,
+        project_id: &str,
+        policy: NebulaPolicy,
+    ) {
+        let compiled = CompiledPolicy::compile(&policy);
         policies.insert(project_id.to_string(), policy);
+
+        let mut compiled_policies = self.compiled.lock_recover();
+        compiled_policies.insert(project_id.to_string(), compiled);
     }
 
+    /// Evaluate an action for an agent holding `agent_roles`, each together
+    /// with every role it inherits from
     pub fn evaluate_permission(
         &self,
         project_id: &str,
-        agent_role: &str,
+        agent_roles: &[&str],
         action: &str,
+        operation: &str,
         resource: &str,
     ) -> PolicyDecision {
-        let policies = self.policies.lock().unwrap();
-        let policy = match policies.get(project_id) {
+        let compiled = self.compiled.lock_recover();
+        let Some(policy) = compiled.get(project_id) else {
+            return PolicyDecision {
+                allowed: false,
+                reason: "No policy found for project".to_string(),
+            };
+        };
+
+        // Same order as `explain_permission`, stopping at the first match
+        let roles = policy.expand_roles(agent_roles);
+        let decisive = policy
+            .ordered_rules(&roles)
+            .find(|(_, rule)| rule.matches(action, operation, resource));
+        let (allowed, reason) = match decisive {
+            Some((role, rule)) => (!rule.deny, rule.reason(role, agent_roles)),
+            None => (
+                false,
+                no_match_reason(agent_roles, action, operation, resource),
+            ),
+        };
+        PolicyDecision { allowed, reason }
+    }
+
+    /// Every rule considered for an action, in the order they're checked:
+    /// denies from the roles and the defaults, then role permissions, then
+    /// default permissions. The first rule matching the tool, the operation,
+    /// and a scope decides. Read-only; nothing is recorded.
+    pub fn explain_permission(
+        &self,
+        project_id: &str,
+        agent_roles: &[&str],
+        action: &str,
+        operation: &str,
+        resource: &str,
+    ) -> PermissionExplanation {
+        let compiled = self.compiled.lock_recover();
+        let Some(policy) = compiled.get(project_id) else {
+            return PermissionExplanation {
+                allowed: false,
+                reason: "No policy found for project".to_string(),
+                roles: Vec::new(),
+                rules: Vec::new(),
+            };
+        };
+
+        let roles = policy.expand_roles(agent_roles);
+        let mut decision: Option<(bool, String)> = None;
+        let mut rules = Vec::new();
+        for (role, rule) in policy.ordered_rules(&roles) {
+            let tool_matched = rule.tool.matches(action);
+            let operation_matched = rule.matches_operation(operation);
+            let scope_matched = rule.scopes.iter().any(|s| s.matches(resource));
+            let decisive = decision.is_none() && tool_matched && operation_matched && scope_matched;
+            if decisive {
+                decision = Some((!rule.deny, rule.reason(role, agent_roles)));
+            }
+            rules.push(EvaluatedRule {
+                tool_id: rule.tool_id.clone(),
+                operations: rule.operations.clone(),
+                resource_scope: rule.resource_scope.clone(),
+                deny: rule.deny,
+                role: role.map(str::to_string),
+                tool_matched,
+                operation_matched,
+                scope_matched,
+                decisive,
+            });
+        }
+
+        let (allowed, reason) = decision.unwrap_or_else(|| {
+            (
+                false,
+                no_match_reason(agent_roles, action, operation, resource),
+            )
+        });
+        PermissionExplanation {
+            allowed,
+            reason,
+            roles: roles.into_iter().map(str::to_string).collect(),
+            rules,
+        }
+    }
+
+    /// Check whether a role may write `path` in `repository`.
+    /// A denied path in any scope covering the repository wins over every
+    /// allowed path; with no matching scope the policy's `default_access` decides.
+    pub fn evaluate_write(
+        &self,
+        project_id: &str,
+        agent_role: &str,
+        repository: &str,
+        path: &str,
+    ) -> PolicyDecision {
+        let compiled = self.compiled.lock_recover();
+        let policy = match compiled.get(project_id) {
             Some(p) => p,
             None => {
                 return PolicyDecision {
@@ -43,61 +234,738 @@ impl PolicyEngine {
             }
         };
 
-        // Check tool permissions
-        if let Some(role_perms) = policy.tool_permissions.role_permissions.get(agent_role) {
-            for perm in role_perms {
-                if matches_tool(action, &perm.tool_id) && matches_scope(resource, &perm.resource_scope)
-                {
-                    return PolicyDecision {
-                        allowed: true,
-                        reason: format!("Allowed by role permission for {}", agent_role),
-                    };
-                }
-            }
-        }
+        let scopes: Vec<_> = policy
+            .write_scopes
+            .iter()
+            .filter(|scope| scope.repository.matches(repository))
+            .collect();
 
-        // Check default permissions
-        for perm in &policy.tool_permissions.default_permissions {
-            if matches_tool(action, &perm.tool_id) && matches_scope(resource, &perm.resource_scope) {
+        for scope in &scopes {
+            if let Some((pattern, _)) = scope.denied.iter().find(|(_, m)| m.matches(path)) {
                 return PolicyDecision {
-                    allowed: true,
-                    reason: "Allowed by default permission".to_string(),
+                    allowed: false,
+                    reason: format!("Denied by path rule {} in {}", pattern, repository),
                 };
             }
         }
 
+        if scopes.iter().any(|scope| {
+            scope.roles.iter().any(|r| r == agent_role)
+                && scope.allowed.iter().any(|m| m.matches(path))
+        }) {
+            return PolicyDecision {
+                allowed: true,
+                reason: format!("Allowed by write scope for {}", agent_role),
+            };
+        }
+
+        if policy.default_write {
+            return PolicyDecision {
+                allowed: true,
+                reason: "Allowed by default repository access".to_string(),
+            };
+        }
+
         PolicyDecision {
             allowed: false,
             reason: format!(
-                "No matching permission for agent={}, action={}, resource={}",
-                agent_role, action, resource
+                "No write scope for agent={}, repository={}, path={}",
+                agent_role, repository, path
+            ),
+        }
+    }
+
+    /// Check whether a role may access a vault note of the given classification.
+    /// Notes without a classification fall back to `default_classification`;
+    /// classifications with no access rule are open to every role. Without a
+    /// role, only classifications with no access rule are open.
+    pub fn check_note_access(
+        &self,
+        project_id: &str,
+        role: Option<&str>,
+        classification: Option<&str>,
+    ) -> PolicyDecision {
+        let policies = self.policies.lock_recover();
+        let policy = match (policies.get(project_id), role) {
+            (Some(p), _) => p,
+            (None, Some(_)) => {
+                return PolicyDecision {
+                    allowed: false,
+                    reason: "No policy found for project".to_string(),
+                }
+            }
+            (None, None) => {
+                return PolicyDecision {
+                    allowed: true,
+                    reason: "No policy found for project".to_string(),
+                }
+            }
+        };
+
+        let data_policy = &policy.data_classification;
+        let classification = classification.unwrap_or(&data_policy.default_classification);
+
+        match data_policy
+            .note_access
+            .iter()
+            .find(|rule| rule.classification == classification)
+        {
+            None => PolicyDecision {
+                allowed: true,
+                reason: format!("No note access rule for classification {}", classification),
+            },
+            Some(rule) => match role {
+                Some(role) if rule.allowed_roles.iter().any(|r| r == role) => PolicyDecision {
+                    allowed: true,
+                    reason: format!("Role {} is cleared for {} notes", role, classification),
+                },
+                Some(role) => PolicyDecision {
+                    allowed: false,
+                    reason: format!("Role {} is not cleared for {} notes", role, classification),
+                },
+                None => PolicyDecision {
+                    allowed: false,
+                    reason: format!("{} notes require a cleared role", classification),
+                },
+            },
+        }
+    }
+
+    /// Check whether data of a classification may be sent to a provider.
+    /// Data without a classification falls back to `default_classification`;
+    /// providers with no rule are denied. The reason carries the provider's
+    /// retention and encryption constraints.
+    pub fn check_provider_access(
+        &self,
+        project_id: &str,
+        provider: &str,
+        classification: Option<&str>,
+    ) -> PolicyDecision {
+        let policies = self.policies.lock_recover();
+        let policy = match policies.get(project_id) {
+            Some(p) => p,
+            None => {
+                return PolicyDecision {
+                    allowed: false,
+                    reason: "No policy found for project".to_string(),
+                }
+            }
+        };
+
+        let data_policy = &policy.data_classification;
+        let classification = classification.unwrap_or(&data_policy.default_classification);
+
+        let Some(rule) = data_policy
+            .provider_rules
+            .iter()
+            .find(|rule| rule.provider == provider)
+        else {
+            return PolicyDecision {
+                allowed: false,
+                reason: format!("No data rule for provider {}", provider),
+            };
+        };
+
+        let constraints = format!(
+            "retention {} days, encryption {}",
+            rule.data_retention_days,
+            if rule.encryption_required {
+                "required"
+            } else {
+                "not required"
+            }
+        );
+
+        if rule
+            .allowed_classifications
+            .iter()
+            .any(|c| c == classification)
+        {
+            PolicyDecision {
+                allowed: true,
+                reason: format!(
+                    "Provider {} may receive {} data ({})",
+                    provider, classification, constraints
+                ),
+            }
+        } else {
+            PolicyDecision {
+                allowed: false,
+                reason: format!(
+                    "Provider {} may not receive {} data ({})",
+                    provider, classification, constraints
+                ),
+            }
+        }
+    }
+
+    /// Check supplied gate results against the project's merge gates. A
+    /// required gate that failed or has no result fails the evaluation;
+    /// optional gates are reported but never block. `None` without a policy.
+    pub fn evaluate_merge_gates(
+        &self,
+        project_id: &str,
+        gate_results: &HashMap<String, bool>,
+    ) -> Option<GateEvaluation> {
+        let policies = self.policies.lock_recover();
+        let policy = policies.get(project_id)?;
+
+        let mut evaluation = GateEvaluation {
+            passed: true,
+            failed_gates: Vec::new(),
+            failed_optional_gates: Vec::new(),
+        };
+        for gate in &policy.gates.merge_gates {
+            if gate_results.get(&gate.id).copied().unwrap_or(false) {
+                continue;
+            }
+            if gate.required {
+                evaluation.passed = false;
+                evaluation.failed_gates.push(gate.id.clone());
+            } else {
+                evaluation.failed_optional_gates.push(gate.id.clone());
+            }
+        }
+        Some(evaluation)
+    }
+
+    /// Decide a merge to main: the merge gates must pass and the role must be
+    /// allowed by `agents.merge_to_main`. `None` without a policy.
+    pub fn evaluate_merge(
+        &self,
+        project_id: &str,
+        agent_role: &str,
+        gate_results: &HashMap<String, bool>,
+    ) -> Option<MergeEvaluation> {
+        let gates = self.evaluate_merge_gates(project_id, gate_results)?;
+        let permission = self.get_policy(project_id)?.agents.merge_to_main;
+
+        let role_allowed = permission.allowed
+            && permission
+                .allowed_agent_roles
+                .iter()
+                .any(|r| r == agent_role);
+        let reason = if !gates.passed {
+            format!("Required gates failed: {}", gates.failed_gates.join(", "))
+        } else if !permission.allowed {
+            "Merging to main is disabled by policy".to_string()
+        } else if !role_allowed {
+            format!("Role {} may not merge to main", agent_role)
+        } else if permission.require_approval {
+            "Gates passed; merge requires approval".to_string()
+        } else {
+            "Gates passed".to_string()
+        };
+
+        Some(MergeEvaluation {
+            allowed: gates.passed && role_allowed,
+            reason,
+            requires_approval: permission.require_approval,
+            approvers: permission.approvers,
+            gates,
+        })
+    }
+
+    /// Whether `agent_role` may deploy to `environment`. The environment must
+    /// be enabled and its deploy permission must list the role; an allowed
+    /// deploy that needs approval names the approvers in the reason.
+    pub fn can_deploy(
+        &self,
+        project_id: &str,
+        agent_role: &str,
+        environment: &str,
+    ) -> PolicyDecision {
+        let policies = self.policies.lock_recover();
+        let Some(policy) = policies.get(project_id) else {
+            return PolicyDecision {
+                allowed: false,
+                reason: "No policy found for project".to_string(),
+            };
+        };
+        let deny = |reason: String| PolicyDecision {
+            allowed: false,
+            reason,
+        };
+
+        match policy.deployment.environments.get(environment) {
+            Some(env) if env.enabled => {}
+            Some(_) => return deny(format!("Environment {} is disabled", environment)),
+            None => return deny(format!("Unknown environment {}", environment)),
+        }
+        let Some(permission) = policy.agents.deploy_permissions.get(environment) else {
+            return deny(format!(
+                "No deploy permission for environment {}",
+                environment
+            ));
+        };
+        if !permission.allowed {
+            return deny(format!(
+                "Deploying to {} is disabled by policy",
+                environment
+            ));
+        }
+        if !permission
+            .allowed_agent_roles
+            .iter()
+            .any(|r| r == agent_role)
+        {
+            return deny(format!(
+                "Role {} may not deploy to {}",
+                agent_role, environment
+            ));
+        }
+
+        PolicyDecision {
+            allowed: true,
+            reason: if permission.require_approval {
+                format!(
+                    "Deploy to {} requires approval from: {}",
+                    environment,
+                    permission.approvers.join(", ")
+                )
+            } else {
+                format!("Role {} may deploy to {}", agent_role, environment)
+            },
+        }
+    }
+
+    /// Redact a conversation message if the project's policy enables it.
+    /// Returns the text to store and whether anything was replaced.
+    pub fn redact_message(&self, project_id: &str, text: &str) -> (String, bool) {
+        let enabled = {
+            let compiled = self.compiled.lock_recover();
+            compiled.get(project_id).is_some_and(|p| p.redact_messages)
+        };
+        if !enabled {
+            return (text.to_string(), false);
+        }
+
+        match self.redact_detailed(project_id, text) {
+            Some(result) => {
+                let redacted = !result.hits.is_empty();
+                (result.redacted, redacted)
+            }
+            None => (text.to_string(), false),
+        }
+    }
+
+    /// Apply the project's redaction patterns to `text`.
+    /// Text is returned unchanged when the project has no policy.
+    pub fn redact(&self, project_id: &str, text: &str) -> String {
+        match self.redact_detailed(project_id, text) {
+            Some(result) => result.redacted,
+            None => text.to_string(),
+        }
+    }
+
+    /// Apply the project's redaction patterns and report what each one matched.
+    /// Patterns run in declaration order; each match span is given in byte
+    /// offsets of the text as it stood when that pattern ran.
+    pub fn redact_detailed(&self, project_id: &str, text: &str) -> Option<RedactionResult> {
+        let compiled = self.compiled.lock_recover();
+        let patterns = &compiled.get(project_id)?.redactions;
+
+        let mut redacted = text.to_string();
+        let mut hits = Vec::new();
+        let mut matches = Vec::new();
+
+        for pattern in patterns {
+            let re = &pattern.regex;
+            let mut count = 0;
+            for m in re.find_iter(&redacted) {
+                count += 1;
+                matches.push(RedactionMatch {
+                    pattern: pattern.name.clone(),
+                    start: m.start(),
+                    end: m.end(),
+                    matched: m.as_str().to_string(),
+                });
+            }
+
+            if count > 0 {
+                redacted = re
+                    .replace_all(&redacted, pattern.replacement.as_str())
+                    .into_owned();
+            }
+            hits.push(RedactionHit {
+                pattern: pattern.name.clone(),
+                count,
+            });
+        }
+
+        Some(RedactionResult {
+            redacted,
+            hits,
+            matches,
+        })
+    }
+}
+
+/// Field-level changes between two policies. `version` and `updated_at` are
+/// bookkeeping and never reported, so identical content gives no changes.
+pub fn diff_policies(from: &NebulaPolicy, to: &NebulaPolicy) -> Vec<PolicyChange> {
+    let mut from = serde_json::to_value(from).unwrap_or_default();
+    let mut to = serde_json::to_value(to).unwrap_or_default();
+    for value in [&mut from, &mut to] {
+        if let Some(obj) = value.as_object_mut() {
+            obj.remove("version");
+            obj.remove("updated_at");
+        }
+    }
+
+    let mut changes = Vec::new();
+    diff_values("", &from, &to, &mut changes);
+    changes
+}
+
+fn diff_values(
+    path: &str,
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    changes: &mut Vec<PolicyChange>,
+) {
+    use serde_json::Value;
+
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                match new.get(key) {
+                    Some(new_value) => diff_values(&child(key), old_value, new_value, changes),
+                    None => changes.push(PolicyChange::removed(child(key), old_value)),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    changes.push(PolicyChange::added(child(key), new_value));
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                let item = format!("{}[{}]", path, i);
+                match (old.get(i), new.get(i)) {
+                    (Some(o), Some(n)) => diff_values(&item, o, n, changes),
+                    (Some(o), None) => changes.push(PolicyChange::removed(item, o)),
+                    (None, Some(n)) => changes.push(PolicyChange::added(item, n)),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if old != new => changes.push(PolicyChange {
+            path: path.to_string(),
+            kind: ChangeKind::Modified,
+            old_value: Some(old.clone()),
+            new_value: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// Tool permissions pre-parsed into matchers so evaluation does no string
+/// manipulation. Rebuilt whenever a project's policy is set.
+struct CompiledPolicy {
+    role_rules: HashMap<String, Vec<CompiledRule>>,
+    role_parents: HashMap<String, Vec<String>>,
+    default_rules: Vec<CompiledRule>,
+    write_scopes: Vec<CompiledWriteScope>,
+    default_write: bool,
+    redactions: Vec<CompiledRedaction>,
+    redact_messages: bool,
+}
+
+impl CompiledPolicy {
+    fn compile(policy: &NebulaPolicy) -> Self {
+        let tool_permissions = &policy.tool_permissions;
+        let data_policy = &policy.data_classification;
+        Self {
+            role_rules: tool_permissions
+                .role_permissions
+                .iter()
+                .map(|(role, perms)| (role.clone(), perms.iter().map(CompiledRule::new).collect()))
+                .collect(),
+            role_parents: tool_permissions.role_inheritance.clone(),
+            default_rules: tool_permissions
+                .default_permissions
+                .iter()
+                .map(CompiledRule::new)
+                .collect(),
+            write_scopes: policy
+                .repositories
+                .write_scopes
+                .iter()
+                .map(CompiledWriteScope::new)
+                .collect(),
+            default_write: policy.repositories.default_access == "write",
+            redactions: data_policy
+                .redaction_patterns
+                .iter()
+                .filter_map(CompiledRedaction::new)
+                .collect(),
+            redact_messages: data_policy.redact_messages,
+        }
+    }
+
+    /// `roles` followed by every role they inherit from, each once. Cycles are
+    /// rejected by validation, but are also safe here.
+    fn expand_roles<'a>(&'a self, roles: &[&'a str]) -> Vec<&'a str> {
+        let mut expanded: Vec<&str> = Vec::new();
+        let mut queue: std::collections::VecDeque<&str> = roles.iter().copied().collect();
+        while let Some(role) = queue.pop_front() {
+            if expanded.contains(&role) {
+                continue;
+            }
+            expanded.push(role);
+            queue.extend(
+                self.role_parents
+                    .get(role)
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str),
+            );
+        }
+        expanded
+    }
+
+    /// The rules that apply to `roles`, in evaluation order: denies from the
+    /// roles and the defaults, then role allows, then default allows. Denies
+    /// win over any allow.
+    fn ordered_rules<'a>(
+        &'a self,
+        roles: &'a [&'a str],
+    ) -> impl Iterator<Item = (Option<&'a str>, &'a CompiledRule)> + 'a {
+        let all_rules = roles
+            .iter()
+            .flat_map(move |&role| {
+                self.role_rules
+                    .get(role)
+                    .into_iter()
+                    .flatten()
+                    .map(move |rule| (Some(role), rule))
+            })
+            .chain(self.default_rules.iter().map(|rule| (None, rule)));
+        all_rules
+            .clone()
+            .filter(|(_, rule)| rule.deny)
+            .chain(all_rules.filter(|(_, rule)| !rule.deny))
+    }
+}
+
+fn no_match_reason(agent_roles: &[&str], action: &str, operation: &str, resource: &str) -> String {
+    format!(
+        "No matching permission for agent={}, action={}, operation={}, resource={}",
+        agent_roles.join(", "),
+        action,
+        operation,
+        resource
+    )
+}
+
+/// A redaction pattern with its regex built. Invalid patterns are dropped
+/// at compile time with a warning, so redaction never fails on them.
+struct CompiledRedaction {
+    name: String,
+    regex: Regex,
+    replacement: String,
+}
+
+impl CompiledRedaction {
+    fn new(pattern: &RedactionPattern) -> Option<Self> {
+        match Regex::new(&pattern.pattern) {
+            Ok(regex) => Some(Self {
+                name: pattern.name.clone(),
+                regex,
+                replacement: pattern.replacement.clone(),
+            }),
+            Err(e) => {
+                tracing::warn!("Skipping invalid redaction pattern {}: {}", pattern.name, e);
+                None
+            }
+        }
+    }
+}
+
+struct CompiledRule {
+    tool_id: String,
+    tool: ToolMatcher,
+    operations: Vec<String>,
+    resource_scope: Vec<String>,
+    scopes: Vec<ScopeMatcher>,
+    deny: bool,
+}
+
+impl CompiledRule {
+    fn new(perm: &ToolPermission) -> Self {
+        Self {
+            tool_id: perm.tool_id.clone(),
+            tool: ToolMatcher::new(&perm.tool_id),
+            operations: perm.operations.clone(),
+            resource_scope: perm.resource_scope.clone(),
+            scopes: perm
+                .resource_scope
+                .iter()
+                .map(|s| ScopeMatcher::new(s))
+                .collect(),
+            deny: perm.deny,
+        }
+    }
+
+    /// Whether the rule covers `operation`; `*` covers every operation
+    fn matches_operation(&self, operation: &str) -> bool {
+        self.operations
+            .iter()
+            .any(|op| op == "*" || op == operation)
+    }
+
+    fn matches(&self, action: &str, operation: &str, resource: &str) -> bool {
+        self.tool.matches(action)
+            && self.matches_operation(operation)
+            && self.scopes.iter().any(|s| s.matches(resource))
+    }
+
+    /// Why this rule decided an evaluation for `role`, or for the agent's
+    /// roles when it is a default rule
+    fn reason(&self, role: Option<&str>, agent_roles: &[&str]) -> String {
+        match (self.deny, role) {
+            (true, Some(role)) => format!("Denied by rule {} for {}", self.tool_id, role),
+            (true, None) => format!(
+                "Denied by rule {} for {}",
+                self.tool_id,
+                agent_roles.join(", ")
             ),
+            (false, Some(role)) => format!("Allowed by role permission for {}", role),
+            (false, None) => "Allowed by default permission".to_string(),
+        }
+    }
+}
+
+/// A repository write scope with its path globs compiled. Denied paths keep
+/// their pattern so a rejection can name the rule.
+struct CompiledWriteScope {
+    repository: ScopeMatcher,
+    allowed: Vec<ScopeMatcher>,
+    denied: Vec<(String, ScopeMatcher)>,
+    roles: Vec<String>,
+}
+
+impl CompiledWriteScope {
+    fn new(scope: &RepositoryWriteScope) -> Self {
+        Self {
+            repository: ScopeMatcher::new(&scope.repository_pattern),
+            allowed: scope
+                .allowed_paths
+                .iter()
+                .map(|p| ScopeMatcher::new(p))
+                .collect(),
+            denied: scope
+                .denied_paths
+                .iter()
+                .map(|p| (p.clone(), ScopeMatcher::new(p)))
+                .collect(),
+            roles: scope.allowed_agent_roles.clone(),
         }
     }
 }
 
-fn matches_tool(action: &str, pattern: &str) -> bool {
-    if pattern.ends_with(".*") {
-        let prefix = &pattern[..pattern.len() - 2];
-        action.starts_with(prefix)
-    } else {
-        action == pattern
+/// `tool.*` matches any action under the `tool` prefix; anything else is exact
+pub(crate) enum ToolMatcher {
+    Exact(String),
+    Prefix(String),
+}
+
+impl ToolMatcher {
+    pub(crate) fn new(pattern: &str) -> Self {
+        match pattern.strip_suffix(".*") {
+            Some(prefix) => ToolMatcher::Prefix(prefix.to_string()),
+            None => ToolMatcher::Exact(pattern.to_string()),
+        }
+    }
+
+    pub(crate) fn matches(&self, action: &str) -> bool {
+        match self {
+            ToolMatcher::Exact(pattern) => action == pattern,
+            ToolMatcher::Prefix(prefix) => action.starts_with(prefix.as_str()),
+        }
     }
 }
 
-fn matches_scope(resource: &str, scopes: &[String]) -> bool {
-    for scope in scopes {
-        if scope == "**" {
-            return true;
+/// Glob over resource paths: `*` and `?` stay within one path segment, `**`
+/// crosses segments, and a scope with no wildcards matches only itself.
+/// A scope that isn't a valid glob is matched literally.
+enum ScopeMatcher {
+    Glob(GlobMatcher),
+    Literal(String),
+}
+
+impl ScopeMatcher {
+    fn new(scope: &str) -> Self {
+        match GlobBuilder::new(scope).literal_separator(true).build() {
+            Ok(glob) => ScopeMatcher::Glob(glob.compile_matcher()),
+            Err(e) => {
+                tracing::warn!("Matching invalid scope glob {} literally: {}", scope, e);
+                ScopeMatcher::Literal(scope.to_string())
+            }
         }
-        if resource.starts_with(scope.trim_end_matches("**")) {
-            return true;
+    }
+
+    fn matches(&self, resource: &str) -> bool {
+        match self {
+            ScopeMatcher::Glob(glob) => glob.is_match(resource),
+            ScopeMatcher::Literal(scope) => resource == scope,
         }
-        if resource == scope {
-            return true;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyDiff {
+    pub from_version: u64,
+    pub to_version: u64,
+    pub changes: Vec<PolicyChange>,
+}
+
+/// One changed field; `path` uses dots for keys and `[i]` for list items,
+/// e.g. `repositories.write_scopes[0].allowed_paths`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+}
+
+impl PolicyChange {
+    fn added(path: String, value: &serde_json::Value) -> Self {
+        Self {
+            path,
+            kind: ChangeKind::Added,
+            old_value: None,
+            new_value: Some(value.clone()),
         }
     }
-    false
+
+    fn removed(path: String, value: &serde_json::Value) -> Self {
+        Self {
+            path,
+            kind: ChangeKind::Removed,
+            old_value: Some(value.clone()),
+            new_value: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,6 +974,91 @@ pub struct PolicyDecision {
     pub reason: String,
 }
 
+/// The decision for an action together with every rule that was considered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionExplanation {
+    pub allowed: bool,
+    pub reason: String,
+    /// The agent's roles and every role they inherit from
+    pub roles: Vec<String>,
+    pub rules: Vec<EvaluatedRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluatedRule {
+    pub tool_id: String,
+    pub operations: Vec<String>,
+    pub resource_scope: Vec<String>,
+    pub deny: bool,
+    /// Role the rule came from; `None` for a default permission
+    pub role: Option<String>,
+    pub tool_matched: bool,
+    pub operation_matched: bool,
+    pub scope_matched: bool,
+    /// Whether this rule decided the outcome
+    pub decisive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateEvaluation {
+    pub passed: bool,
+    /// Required gates that failed or had no result
+    pub failed_gates: Vec<String>,
+    pub failed_optional_gates: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GateState {
+    Passed,
+    Failed,
+    /// Never evaluated
+    Pending,
+}
+
+/// A gate that applies to a phase and its latest recorded result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateStatus {
+    pub id: String,
+    pub name: String,
+    pub gate_type: String,
+    pub required: bool,
+    pub state: GateState,
+    /// When the result was recorded; `None` while pending
+    pub evaluated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeEvaluation {
+    pub allowed: bool,
+    pub reason: String,
+    /// Set when an allowed merge still needs sign-off from `approvers`
+    pub requires_approval: bool,
+    pub approvers: Vec<String>,
+    pub gates: GateEvaluation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionResult {
+    pub redacted: String,
+    pub hits: Vec<RedactionHit>,
+    pub matches: Vec<RedactionMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionHit {
+    pub pattern: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionMatch {
+    pub pattern: String,
+    pub start: usize,
+    pub end: usize,
+    pub matched: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NebulaPolicy {
     pub version: String,
@@ -122,6 +1075,295 @@ pub struct NebulaPolicy {
     pub tool_permissions: ToolPermissionPolicy,
 }
 
+impl NebulaPolicy {
+    /// Check everything that would otherwise only fail when the policy is
+    /// used: patterns compile, fractions lie in 0.0–1.0, approval has
+    /// approvers, tool permissions list operations, and gate ids are unique.
+    /// All problems are reported at once.
+    pub fn validate(&self) -> Result<(), Vec<PolicyValidationError>> {
+        let mut errors = Vec::new();
+        let mut error =
+            |field: String, message: String| errors.push(PolicyValidationError { field, message });
+
+        for (i, pattern) in self
+            .data_classification
+            .redaction_patterns
+            .iter()
+            .enumerate()
+        {
+            if let Err(e) = Regex::new(&pattern.pattern) {
+                error(
+                    format!("data_classification.redaction_patterns[{}].pattern", i),
+                    e.to_string(),
+                );
+            }
+        }
+
+        let mut check_glob = |field: String, pattern: &str| {
+            if let Err(e) = GlobBuilder::new(pattern).literal_separator(true).build() {
+                error(field, e.to_string());
+            }
+        };
+        if !self.repositories.branch_pattern.is_empty() {
+            check_glob(
+                "repositories.branch_pattern".to_string(),
+                &self.repositories.branch_pattern,
+            );
+        }
+        for (i, scope) in self.repositories.write_scopes.iter().enumerate() {
+            for (j, path) in scope.allowed_paths.iter().enumerate() {
+                check_glob(
+                    format!("repositories.write_scopes[{}].allowed_paths[{}]", i, j),
+                    path,
+                );
+            }
+            for (j, path) in scope.denied_paths.iter().enumerate() {
+                check_glob(
+                    format!("repositories.write_scopes[{}].denied_paths[{}]", i, j),
+                    path,
+                );
+            }
+        }
+
+        let mut check_fraction = |field: String, value: f64| {
+            if !(0.0..=1.0).contains(&value) {
+                error(field, format!("{} is outside 0.0–1.0", value));
+            }
+        };
+        for (name, env) in &self.deployment.environments {
+            check_fraction(
+                format!("deployment.environments.{}.max_blast_radius", name),
+                env.max_blast_radius,
+            );
+        }
+        for (i, step) in self
+            .deployment
+            .progressive_delivery
+            .canary_steps
+            .iter()
+            .enumerate()
+        {
+            check_fraction(
+                format!("deployment.progressive_delivery.canary_steps[{}]", i),
+                *step,
+            );
+        }
+
+        let mut check_approvers = |field: String, permission: &AgentPermission| {
+            if permission.require_approval && permission.approvers.is_empty() {
+                error(
+                    field,
+                    "require_approval is set but approvers is empty".to_string(),
+                );
+            }
+        };
+        check_approvers(
+            "agents.merge_to_main".to_string(),
+            &self.agents.merge_to_main,
+        );
+        for (name, permission) in &self.agents.deploy_permissions {
+            check_approvers(format!("agents.deploy_permissions.{}", name), permission);
+        }
+
+        for (i, trigger) in self.deployment.rollback.triggers.iter().enumerate() {
+            if TriggerCondition::parse(&trigger.condition).is_none() {
+                error(
+                    format!("deployment.rollback.triggers[{}].condition", i),
+                    format!("unknown condition '{}'", trigger.condition),
+                );
+            }
+        }
+
+        // A rule without operations can never match
+        let tool_permissions = &self.tool_permissions;
+        let mut role_names: Vec<&String> = tool_permissions.role_permissions.keys().collect();
+        role_names.sort();
+        let rules = tool_permissions
+            .default_permissions
+            .iter()
+            .enumerate()
+            .map(|(i, perm)| (format!("tool_permissions.default_permissions[{}]", i), perm))
+            .chain(role_names.into_iter().flat_map(|role| {
+                tool_permissions.role_permissions[role]
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, perm)| {
+                        (
+                            format!("tool_permissions.role_permissions.{}[{}]", role, i),
+                            perm,
+                        )
+                    })
+            }));
+        for (field, perm) in rules {
+            if perm.operations.is_empty() {
+                error(
+                    format!("{}.operations", field),
+                    "no operations listed; use \"*\" for all".to_string(),
+                );
+            }
+        }
+
+        let inheritance = &self.tool_permissions.role_inheritance;
+        let mut roles: Vec<&String> = inheritance.keys().collect();
+        roles.sort();
+        for role in roles {
+            for parent in &inheritance[role] {
+                let known = self.tool_permissions.role_permissions.contains_key(parent)
+                    || inheritance.contains_key(parent);
+                if !known {
+                    error(
+                        format!("tool_permissions.role_inheritance.{}", role),
+                        format!("unknown parent role '{}'", parent),
+                    );
+                }
+            }
+        }
+        for cycle in inheritance_cycles(inheritance) {
+            error(
+                format!("tool_permissions.role_inheritance.{}", cycle[0]),
+                format!("inheritance cycle {}", cycle.join(" -> ")),
+            );
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for gate in self
+            .gates
+            .merge_gates
+            .iter()
+            .chain(&self.gates.deploy_gates)
+        {
+            if !seen.insert(gate.id.as_str()) {
+                error(
+                    "gates".to_string(),
+                    format!("duplicate gate id {}", gate.id),
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Each cycle in a role inheritance map, as the path from a role back to itself
+fn inheritance_cycles(inheritance: &HashMap<String, Vec<String>>) -> Vec<Vec<String>> {
+    fn visit<'a>(
+        role: &'a str,
+        inheritance: &'a HashMap<String, Vec<String>>,
+        path: &mut Vec<&'a str>,
+        visited: &mut std::collections::HashSet<&'a str>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        if let Some(at) = path.iter().position(|r| *r == role) {
+            let mut cycle: Vec<String> = path[at..].iter().map(|r| r.to_string()).collect();
+            cycle.push(role.to_string());
+            cycles.push(cycle);
+            return;
+        }
+        if !visited.insert(role) {
+            return;
+        }
+        path.push(role);
+        for parent in inheritance.get(role).into_iter().flatten() {
+            visit(parent, inheritance, path, visited, cycles);
+        }
+        path.pop();
+    }
+
+    let mut roles: Vec<&String> = inheritance.keys().collect();
+    roles.sort();
+    let mut cycles = Vec::new();
+    let mut visited = std::collections::HashSet::new();
+    for role in roles {
+        visit(
+            role,
+            inheritance,
+            &mut Vec::new(),
+            &mut visited,
+            &mut cycles,
+        );
+    }
+    cycles
+}
+
+/// On-disk policy file format, chosen by extension
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyFormat {
+    Json,
+    Yaml,
+}
+
+impl PolicyFormat {
+    /// `.json`, or `.yaml`/`.yml`, ignoring case
+    pub fn from_path(path: &Path) -> Result<Self, PolicyFileError> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            _ => Err(PolicyFileError::UnsupportedFormat(
+                path.display().to_string(),
+            )),
+        }
+    }
+
+    pub fn parse(self, content: &str) -> Result<NebulaPolicy, PolicyFileError> {
+        match self {
+            Self::Json => serde_json::from_str(content).map_err(|e| PolicyFileError::Parse {
+                line: e.line(),
+                column: e.column(),
+                message: e.to_string(),
+            }),
+            Self::Yaml => serde_yaml::from_str(content).map_err(|e| {
+                let location = e.location();
+                PolicyFileError::Parse {
+                    line: location.as_ref().map_or(0, |l| l.line()),
+                    column: location.as_ref().map_or(0, |l| l.column()),
+                    message: e.to_string(),
+                }
+            }),
+        }
+    }
+
+    pub fn render(self, policy: &NebulaPolicy) -> Result<String, PolicyFileError> {
+        let rendered = match self {
+            Self::Json => serde_json::to_string_pretty(policy).map_err(|e| e.to_string()),
+            Self::Yaml => serde_yaml::to_string(policy).map_err(|e| e.to_string()),
+        };
+        rendered.map_err(PolicyFileError::Serialize)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyFileError {
+    #[error("Unsupported policy file {0}; expected .json, .yaml, or .yml")]
+    UnsupportedFormat(String),
+    /// `line` and `column` are 1-based; 0 when the parser gave no position
+    #[error("Failed to parse policy at line {line}, column {column}: {message}")]
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("Failed to serialize policy: {0}")]
+    Serialize(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// A policy field that failed validation, by its dotted path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyValidationError {
+    pub field: String,
+    pub message: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentPolicy {
     pub merge_to_main: AgentPermission,
@@ -160,6 +1402,115 @@ pub struct DeploymentPolicy {
     pub rollback: RollbackPolicy,
 }
 
+impl DeploymentPolicy {
+    /// Lay out a canary rollout for `environment` starting at `starts_at`.
+    /// Steps run `step_interval` seconds apart, must increase to exactly 1.0,
+    /// and no step may shift more than the environment's `max_blast_radius`
+    /// of traffic over the one before it.
+    pub fn plan_canary(
+        &self,
+        environment: &str,
+        starts_at: DateTime<Utc>,
+    ) -> Result<CanaryPlan, CanaryPlanError> {
+        let env = self
+            .environments
+            .get(environment)
+            .ok_or_else(|| CanaryPlanError::UnknownEnvironment(environment.to_string()))?;
+        if !env.enabled {
+            return Err(CanaryPlanError::Disabled(environment.to_string()));
+        }
+        if env.deployment_strategy != "canary" {
+            return Err(CanaryPlanError::NotCanary(
+                environment.to_string(),
+                env.deployment_strategy.clone(),
+            ));
+        }
+
+        let delivery = &self.progressive_delivery;
+        let mut steps = Vec::with_capacity(delivery.canary_steps.len());
+        let mut previous = 0.0;
+        for (index, &traffic) in delivery.canary_steps.iter().enumerate() {
+            if traffic <= previous {
+                return Err(CanaryPlanError::InvalidSteps(format!(
+                    "step {} ({}) does not increase on {}",
+                    index, traffic, previous
+                )));
+            }
+            // Tolerate float noise like 0.4 - 0.3 > 0.1
+            if traffic - previous > env.max_blast_radius + FRACTION_TOLERANCE {
+                return Err(CanaryPlanError::BlastRadiusExceeded {
+                    step: index,
+                    shift: traffic - previous,
+                    max: env.max_blast_radius,
+                });
+            }
+
+            let offset_seconds = delivery.step_interval * index as u64;
+            steps.push(CanaryStep {
+                index,
+                traffic,
+                offset_seconds,
+                starts_at: (starts_at + chrono::Duration::seconds(offset_seconds as i64))
+                    .to_rfc3339(),
+                metrics: delivery.evaluation_metrics.clone(),
+            });
+            previous = traffic;
+        }
+        if (previous - 1.0).abs() > FRACTION_TOLERANCE {
+            return Err(CanaryPlanError::InvalidSteps(format!(
+                "last step is {}, not 1.0",
+                previous
+            )));
+        }
+
+        Ok(CanaryPlan {
+            environment: environment.to_string(),
+            starts_at: starts_at.to_rfc3339(),
+            steps,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryPlan {
+    pub environment: String,
+    pub starts_at: String,
+    pub steps: Vec<CanaryStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryStep {
+    pub index: usize,
+    /// Fraction of traffic on the new release during this step
+    pub traffic: f64,
+    /// Seconds after the rollout starts
+    pub offset_seconds: u64,
+    pub starts_at: String,
+    pub metrics: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyWriteError {
+    #[error("Policy is at version {current}, not the expected {expected}")]
+    VersionConflict { expected: u64, current: u64 },
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CanaryPlanError {
+    #[error("Unknown environment: {0}")]
+    UnknownEnvironment(String),
+    #[error("Environment {0} is disabled")]
+    Disabled(String),
+    #[error("Environment {0} deploys with {1}, not canary")]
+    NotCanary(String, String),
+    #[error("Invalid canary steps: {0}")]
+    InvalidSteps(String),
+    #[error("Canary step {step} shifts {shift} of traffic, over the blast radius of {max}")]
+    BlastRadiusExceeded { step: usize, shift: f64, max: f64 },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvironmentPolicy {
     pub enabled: bool,
@@ -191,12 +1542,130 @@ pub struct RollbackTrigger {
     pub window: u64,
 }
 
+impl RollbackPolicy {
+    /// The first trigger whose metric crosses its threshold. Metrics missing
+    /// from `metrics` don't fire; a trigger with an unknown condition is an
+    /// error rather than a silent miss. With `auto_rollback` off, a fired
+    /// trigger only asks for manual approval.
+    pub fn should_rollback(
+        &self,
+        metrics: &HashMap<String, f64>,
+    ) -> Result<Option<RollbackDecision>, PolicyValidationError> {
+        for (i, trigger) in self.triggers.iter().enumerate() {
+            let condition = TriggerCondition::parse(&trigger.condition).ok_or_else(|| {
+                PolicyValidationError {
+                    field: format!("deployment.rollback.triggers[{}].condition", i),
+                    message: format!("unknown condition '{}'", trigger.condition),
+                }
+            })?;
+            let Some(&value) = metrics.get(&trigger.metric) else {
+                continue;
+            };
+            if !condition.holds(value, trigger.threshold) {
+                continue;
+            }
+
+            let mut reason = format!(
+                "{} is {} ({} {} over {}s)",
+                trigger.metric,
+                value,
+                condition.symbol(),
+                trigger.threshold,
+                trigger.window
+            );
+            if !self.auto_rollback {
+                reason.push_str("; manual approval required");
+            }
+            return Ok(Some(RollbackDecision {
+                metric: trigger.metric.clone(),
+                value,
+                threshold: trigger.threshold,
+                window: trigger.window,
+                automatic: self.auto_rollback,
+                reason,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+/// Comparison a rollback trigger applies to its metric
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerCondition {
+    GreaterThan,
+    GreaterOrEqual,
+    LessThan,
+    LessOrEqual,
+    Equals,
+    NotEquals,
+}
+
+impl TriggerCondition {
+    /// Accepts the operator or its spelled-out name, e.g. `>` or `greater_than`
+    pub fn parse(condition: &str) -> Option<Self> {
+        Some(match condition.trim() {
+            ">" | "greater_than" => Self::GreaterThan,
+            ">=" | "greater_than_or_equal" => Self::GreaterOrEqual,
+            "<" | "less_than" => Self::LessThan,
+            "<=" | "less_than_or_equal" => Self::LessOrEqual,
+            "==" | "=" | "equals" => Self::Equals,
+            "!=" | "not_equals" => Self::NotEquals,
+            _ => return None,
+        })
+    }
+
+    pub fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::GreaterThan => value > threshold,
+            Self::GreaterOrEqual => value >= threshold,
+            Self::LessThan => value < threshold,
+            Self::LessOrEqual => value <= threshold,
+            Self::Equals => (value - threshold).abs() <= FRACTION_TOLERANCE,
+            Self::NotEquals => (value - threshold).abs() > FRACTION_TOLERANCE,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::GreaterThan => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::LessThan => "<",
+            Self::LessOrEqual => "<=",
+            Self::Equals => "==",
+            Self::NotEquals => "!=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackDecision {
+    pub metric: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub window: u64,
+    /// False when policy wants a person to approve the rollback
+    pub automatic: bool,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatePolicy {
     pub merge_gates: Vec<Gate>,
     pub deploy_gates: Vec<Gate>,
 }
 
+impl GatePolicy {
+    /// Gates that guard leaving a phase: merge gates while code is being
+    /// built and tested, deploy gates while deploying, none otherwise
+    pub fn gates_for_phase(&self, phase: &str) -> &[Gate] {
+        match phase {
+            "build" | "test" => &self.merge_gates,
+            "deploy" => &self.deploy_gates,
+            _ => &[],
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Gate {
     pub id: String,
@@ -211,6 +1680,22 @@ pub struct DataClassificationPolicy {
     pub default_classification: String,
     pub provider_rules: Vec<ProviderDataRule>,
     pub redaction_patterns: Vec<RedactionPattern>,
+    #[serde(default)]
+    pub note_access: Vec<NoteAccessRule>,
+    /// Apply `redaction_patterns` to conversation messages as well as tool payloads
+    #[serde(default = "default_redact_messages")]
+    pub redact_messages: bool,
+}
+
+fn default_redact_messages() -> bool {
+    true
+}
+
+/// Restricts vault notes carrying a `classification` frontmatter value to the listed roles
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteAccessRule {
+    pub classification: String,
+    pub allowed_roles: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -232,11 +1717,533 @@ pub struct RedactionPattern {
 pub struct ToolPermissionPolicy {
     pub default_permissions: Vec<ToolPermission>,
     pub role_permissions: HashMap<String, Vec<ToolPermission>>,
+    /// Parent roles per role; a role gets every permission of its ancestors
+    #[serde(default)]
+    pub role_inheritance: HashMap<String, Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolPermission {
     pub tool_id: String,
+    /// Operations granted or denied, such as `read` or `write`; `*` is all
     pub operations: Vec<String>,
     pub resource_scope: Vec<String>,
+    /// Forbid matching actions instead of allowing them; denies take precedence
+    #[serde(default)]
+    pub deny: bool,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A policy with nothing granted, for tests to fill in what they need
+    pub(crate) fn sample_policy(project_id: &str) -> NebulaPolicy {
+        serde_json::from_value(serde_json::json!({
+            "version": "0",
+            "project_id": project_id,
+            "name": "Test policy",
+            "description": "",
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+            "agents": {
+                "merge_to_main": {
+                    "allowed": false,
+                    "allowed_agent_roles": [],
+                    "require_approval": false,
+                    "approvers": []
+                },
+                "deploy_permissions": {},
+                "max_concurrent_runs": 1
+            },
+            "repositories": {
+                "default_access": "read",
+                "write_scopes": [],
+                "auto_merge_branches": [],
+                "branch_pattern": ""
+            },
+            "deployment": {
+                "environments": {},
+                "progressive_delivery": {
+                    "canary_steps": [],
+                    "step_interval": 60,
+                    "evaluation_metrics": []
+                },
+                "rollback": {
+                    "auto_rollback": false,
+                    "triggers": [],
+                    "rollback_timeout": 300
+                }
+            },
+            "gates": { "merge_gates": [], "deploy_gates": [] },
+            "data_classification": {
+                "default_classification": "internal",
+                "provider_rules": [],
+                "redaction_patterns": []
+            },
+            "tool_permissions": {
+                "default_permissions": [],
+                "role_permissions": {}
+            }
+        }))
+        .unwrap()
+    }
+
+    /// An engine holding `policy` for project `p`
+    pub(crate) fn engine_with(policy: NebulaPolicy) -> PolicyEngine {
+        let engine = PolicyEngine::new();
+        engine.set_policy("p", policy, None, None).unwrap();
+        engine
+    }
+
+    #[test]
+    fn set_policy_with_a_stale_expected_version_conflicts() {
+        let engine = PolicyEngine::new();
+        let first = engine.set_policy("p", sample_policy("p"), Some(0), None);
+        let second = engine.set_policy("p", sample_policy("p"), Some(0), None);
+
+        assert_eq!(first.unwrap().version, "1");
+        assert!(matches!(
+            second.unwrap_err(),
+            PolicyWriteError::VersionConflict {
+                expected: 0,
+                current: 1
+            }
+        ));
+        assert_eq!(engine.get_policy("p").unwrap().version, "1");
+    }
+
+    #[test]
+    fn set_policy_without_an_expected_version_always_writes() {
+        let engine = engine_with(sample_policy("p"));
+        let updated = engine.set_policy("p", sample_policy("p"), None, None);
+        assert_eq!(updated.unwrap().version, "2");
+    }
+
+    /// `secret` notes restricted to the `security` role
+    pub(crate) fn restricted_notes_policy() -> NebulaPolicy {
+        let mut policy = sample_policy("p");
+        policy.data_classification.note_access.push(NoteAccessRule {
+            classification: "secret".to_string(),
+            allowed_roles: vec!["security".to_string()],
+        });
+        policy
+    }
+
+    #[test]
+    fn note_access_checks_the_role_against_the_rule() {
+        let engine = engine_with(restricted_notes_policy());
+        assert!(
+            engine
+                .check_note_access("p", Some("security"), Some("secret"))
+                .allowed
+        );
+        assert!(
+            !engine
+                .check_note_access("p", Some("coder"), Some("secret"))
+                .allowed
+        );
+        assert!(engine.check_note_access("p", Some("coder"), None).allowed);
+    }
+
+    #[test]
+    fn note_access_without_a_role_is_denied_for_restricted_notes() {
+        let engine = engine_with(restricted_notes_policy());
+        assert!(!engine.check_note_access("p", None, Some("secret")).allowed);
+        assert!(
+            engine
+                .check_note_access("p", None, Some("internal"))
+                .allowed
+        );
+        assert!(engine.check_note_access("p", None, None).allowed);
+        assert!(
+            PolicyEngine::new()
+                .check_note_access("p", None, None)
+                .allowed
+        );
+    }
+
+    #[test]
+    fn scope_double_star_crosses_segments() {
+        let scope = ScopeMatcher::new("src/**");
+        assert!(scope.matches("src/a/b.rs"));
+        assert!(scope.matches("src/main.rs"));
+        assert!(!scope.matches("tests/a.rs"));
+
+        let scope = ScopeMatcher::new("src/**/*.rs");
+        assert!(scope.matches("src/a/b/c.rs"));
+        assert!(!scope.matches("src/a/b/c.ts"));
+    }
+
+    #[test]
+    fn scope_star_stays_within_a_segment() {
+        let scope = ScopeMatcher::new("packages/*/src");
+        assert!(scope.matches("packages/web/src"));
+        assert!(!scope.matches("packages/web/nested/src"));
+    }
+
+    #[test]
+    fn scope_question_mark_matches_one_character() {
+        let scope = ScopeMatcher::new("v?/notes.md");
+        assert!(scope.matches("v1/notes.md"));
+        assert!(!scope.matches("v10/notes.md"));
+        assert!(!scope.matches("v/notes.md"));
+    }
+
+    #[test]
+    fn scope_without_wildcards_matches_exactly() {
+        let scope = ScopeMatcher::new("src/lib.rs");
+        assert!(scope.matches("src/lib.rs"));
+        assert!(!scope.matches("src/lib.rs.bak"));
+        assert!(!scope.matches("other/src/lib.rs"));
+    }
+
+    #[test]
+    fn write_scopes_use_globs() {
+        let mut policy = sample_policy("p");
+        policy.repositories.write_scopes.push(RepositoryWriteScope {
+            repository_pattern: "nebula".to_string(),
+            allowed_paths: vec!["src/**/*.rs".to_string()],
+            denied_paths: vec!["src/secrets/**".to_string()],
+            allowed_agent_roles: vec!["coder".to_string()],
+        });
+        let engine = engine_with(policy);
+
+        assert!(
+            engine
+                .evaluate_write("p", "coder", "nebula", "src/a/b.rs")
+                .allowed
+        );
+        assert!(
+            !engine
+                .evaluate_write("p", "coder", "nebula", "src/a/b.ts")
+                .allowed
+        );
+        assert!(
+            !engine
+                .evaluate_write("p", "coder", "nebula", "src/secrets/k.rs")
+                .allowed
+        );
+        assert!(
+            !engine
+                .evaluate_write("p", "tester", "nebula", "src/a/b.rs")
+                .allowed
+        );
+    }
+
+    fn redacting(patterns: &[(&str, &str, &str)]) -> PolicyEngine {
+        let mut policy = sample_policy("p");
+        policy.data_classification.redaction_patterns = patterns
+            .iter()
+            .map(|(name, pattern, replacement)| RedactionPattern {
+                name: name.to_string(),
+                pattern: pattern.to_string(),
+                replacement: replacement.to_string(),
+            })
+            .collect();
+        engine_with(policy)
+    }
+
+    #[test]
+    fn overlapping_patterns_run_in_declaration_order() {
+        let engine = redacting(&[
+            ("aws_key", r"AKIA[A-Z0-9]{4}", "[AWS]"),
+            ("upper", r"[A-Z]{4,}", "[CAPS]"),
+        ]);
+
+        let result = engine
+            .redact_detailed("p", "key AKIAABCD and HELLO")
+            .unwrap();
+        assert_eq!(result.redacted, "key [AWS] and [CAPS]");
+        assert_eq!(result.hits[0].count, 1);
+        // The key was already replaced, so the broader pattern only sees HELLO
+        assert_eq!(result.hits[1].count, 1);
+        assert_eq!(result.matches.len(), 2);
+        assert_eq!(result.matches[1].matched, "HELLO");
+    }
+
+    #[test]
+    fn redaction_replacements_can_use_capture_groups() {
+        let engine = redacting(&[("password", r"(?P<key>password)=\S+", "$key=[REDACTED]")]);
+
+        assert_eq!(
+            engine.redact("p", "login password=hunter2 ok"),
+            "login password=[REDACTED] ok"
+        );
+        let numbered = redacting(&[("token", r"(token):\s*\w+", "$1: ***")]);
+        assert_eq!(numbered.redact("p", "token: abc123"), "token: ***");
+    }
+
+    #[test]
+    fn redaction_without_matches_leaves_text_alone() {
+        let engine = redacting(&[("aws_key", r"AKIA[A-Z0-9]{16}", "[AWS]")]);
+
+        let result = engine.redact_detailed("p", "nothing here").unwrap();
+        assert_eq!(result.redacted, "nothing here");
+        assert_eq!(result.hits[0].count, 0);
+        assert!(result.matches.is_empty());
+        assert_eq!(engine.redact("other", "AKIA"), "AKIA");
+    }
+
+    #[test]
+    fn provider_without_a_rule_is_denied() {
+        let mut policy = sample_policy("p");
+        policy
+            .data_classification
+            .provider_rules
+            .push(ProviderDataRule {
+                provider: "anthropic".to_string(),
+                allowed_classifications: vec!["public".to_string(), "internal".to_string()],
+                data_retention_days: 0,
+                encryption_required: true,
+            });
+        let engine = engine_with(policy);
+
+        let decision = engine.check_provider_access("p", "other", Some("public"));
+        assert!(!decision.allowed);
+        assert!(decision.reason.contains("No data rule"));
+        assert!(
+            !engine
+                .check_provider_access("unknown", "anthropic", None)
+                .allowed
+        );
+    }
+
+    #[test]
+    fn provider_rule_limits_classifications() {
+        let mut policy = sample_policy("p");
+        policy
+            .data_classification
+            .provider_rules
+            .push(ProviderDataRule {
+                provider: "anthropic".to_string(),
+                allowed_classifications: vec!["internal".to_string()],
+                data_retention_days: 30,
+                encryption_required: false,
+            });
+        let engine = engine_with(policy);
+
+        assert!(
+            engine
+                .check_provider_access("p", "anthropic", Some("internal"))
+                .allowed
+        );
+        // Falls back to the default classification, which is internal
+        assert!(engine.check_provider_access("p", "anthropic", None).allowed);
+        assert!(
+            !engine
+                .check_provider_access("p", "anthropic", Some("secret"))
+                .allowed
+        );
+    }
+
+    fn gated_engine() -> PolicyEngine {
+        let gate = |id: &str, required: bool| Gate {
+            id: id.to_string(),
+            name: id.to_string(),
+            gate_type: "check".to_string(),
+            required,
+            config: HashMap::new(),
+        };
+        let mut policy = sample_policy("p");
+        policy.gates.merge_gates = vec![gate("tests", true), gate("lint", false)];
+        engine_with(policy)
+    }
+
+    #[test]
+    fn failed_optional_gate_still_passes() {
+        let engine = gated_engine();
+        let results = HashMap::from([("tests".to_string(), true), ("lint".to_string(), false)]);
+
+        let evaluation = engine.evaluate_merge_gates("p", &results).unwrap();
+        assert!(evaluation.passed);
+        assert!(evaluation.failed_gates.is_empty());
+        assert_eq!(evaluation.failed_optional_gates, vec!["lint".to_string()]);
+    }
+
+    #[test]
+    fn missing_required_gate_result_fails() {
+        let engine = gated_engine();
+        let results = HashMap::from([("lint".to_string(), true)]);
+
+        let evaluation = engine.evaluate_merge_gates("p", &results).unwrap();
+        assert!(!evaluation.passed);
+        assert_eq!(evaluation.failed_gates, vec!["tests".to_string()]);
+        assert!(evaluation.failed_optional_gates.is_empty());
+    }
+
+    fn deploy_engine(require_approval: bool) -> PolicyEngine {
+        let mut policy = sample_policy("p");
+        policy.deployment.environments.insert(
+            "production".to_string(),
+            EnvironmentPolicy {
+                enabled: true,
+                auto_deploy_allowed: false,
+                required_gates: Vec::new(),
+                max_blast_radius: 0.1,
+                deployment_strategy: "canary".to_string(),
+            },
+        );
+        policy.agents.deploy_permissions.insert(
+            "production".to_string(),
+            AgentPermission {
+                allowed: true,
+                allowed_agent_roles: vec!["deployer".to_string()],
+                require_approval,
+                approvers: vec!["alice".to_string()],
+            },
+        );
+        engine_with(policy)
+    }
+
+    #[test]
+    fn deploying_to_an_unknown_environment_is_denied() {
+        let decision = deploy_engine(false).can_deploy("p", "deployer", "staging");
+        assert!(!decision.allowed);
+        assert_eq!(decision.reason, "Unknown environment staging");
+    }
+
+    #[test]
+    fn deploy_approval_is_reported_in_the_reason() {
+        let decision = deploy_engine(true).can_deploy("p", "deployer", "production");
+        assert!(decision.allowed);
+        assert_eq!(
+            decision.reason,
+            "Deploy to production requires approval from: alice"
+        );
+
+        let decision = deploy_engine(false).can_deploy("p", "deployer", "production");
+        assert_eq!(decision.reason, "Role deployer may deploy to production");
+        assert!(
+            !deploy_engine(true)
+                .can_deploy("p", "coder", "production")
+                .allowed
+        );
+    }
+
+    fn permission(tool_id: &str, operations: &[&str], scope: &str, deny: bool) -> ToolPermission {
+        ToolPermission {
+            tool_id: tool_id.to_string(),
+            operations: operations.iter().map(|o| o.to_string()).collect(),
+            resource_scope: vec![scope.to_string()],
+            deny,
+        }
+    }
+
+    fn inheriting_policy() -> NebulaPolicy {
+        let mut policy = sample_policy("p");
+        let tools = &mut policy.tool_permissions;
+        tools.role_permissions.insert(
+            "reader".to_string(),
+            vec![permission("git", &["read"], "**", false)],
+        );
+        tools.role_permissions.insert(
+            "writer".to_string(),
+            vec![permission("git", &["write"], "src/**", false)],
+        );
+        tools.role_permissions.insert(
+            "auditor".to_string(),
+            vec![permission("git", &["write"], "src/secrets/**", true)],
+        );
+        tools
+            .role_inheritance
+            .insert("writer".to_string(), vec!["reader".to_string()]);
+        policy
+    }
+
+    #[test]
+    fn role_inheritance_cycles_fail_validation() {
+        let mut policy = inheriting_policy();
+        let inheritance = &mut policy.tool_permissions.role_inheritance;
+        inheritance.insert("reader".to_string(), vec!["writer".to_string()]);
+
+        let errors = policy.validate().unwrap_err();
+        assert!(errors
+            .iter()
+            .any(|e| e.message.starts_with("inheritance cycle")));
+        assert!(inheriting_policy().validate().is_ok());
+    }
+
+    #[test]
+    fn inherited_permissions_allow() {
+        let engine = engine_with(inheriting_policy());
+
+        let decision = engine.evaluate_permission("p", &["writer"], "git", "read", "README.md");
+        assert!(decision.allowed);
+        assert_eq!(decision.reason, "Allowed by role permission for reader");
+        assert!(
+            !engine
+                .evaluate_permission("p", &["reader"], "git", "write", "src/a.rs")
+                .allowed
+        );
+    }
+
+    #[test]
+    fn permissions_combine_across_roles_with_denies_first() {
+        let engine = engine_with(inheriting_policy());
+
+        assert!(
+            engine
+                .evaluate_permission("p", &["writer", "auditor"], "git", "write", "src/a.rs")
+                .allowed
+        );
+        let decision = engine.evaluate_permission(
+            "p",
+            &["writer", "auditor"],
+            "git",
+            "write",
+            "src/secrets/key.rs",
+        );
+        assert!(!decision.allowed);
+        assert_eq!(decision.reason, "Denied by rule git for auditor");
+    }
+
+    #[test]
+    fn cyclic_inheritance_still_terminates_when_installed() {
+        let mut policy = inheriting_policy();
+        let inheritance = &mut policy.tool_permissions.role_inheritance;
+        inheritance.insert("reader".to_string(), vec!["writer".to_string()]);
+        let engine = engine_with(policy);
+
+        let explanation = engine.explain_permission("p", &["reader"], "git", "write", "src/a.rs");
+        assert!(explanation.allowed);
+        assert_eq!(explanation.roles, vec!["reader", "writer"]);
+    }
+
+    #[test]
+    fn read_only_grant_denies_write() {
+        let mut policy = sample_policy("p");
+        policy.tool_permissions.default_permissions.push(permission(
+            "vault",
+            &["read"],
+            "**",
+            false,
+        ));
+        let engine = engine_with(policy);
+
+        assert!(
+            engine
+                .evaluate_permission("p", &["coder"], "vault", "read", "notes/a.md")
+                .allowed
+        );
+        let decision = engine.evaluate_permission("p", &["coder"], "vault", "write", "notes/a.md");
+        assert!(!decision.allowed);
+        assert!(decision.reason.starts_with("No matching permission"));
+    }
+
+    #[test]
+    fn wildcard_operation_grants_everything() {
+        let mut policy = sample_policy("p");
+        policy
+            .tool_permissions
+            .default_permissions
+            .push(permission("vault", &["*"], "**", false));
+        let engine = engine_with(policy);
+
+        assert!(
+            engine
+                .evaluate_permission("p", &["coder"], "vault", "write", "notes/a.md")
+                .allowed
+        );
+    }
 }
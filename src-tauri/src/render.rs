@@ -0,0 +1,202 @@
+//! Markdown rendering for note previews.
+//! Turns a note body into sanitized HTML, with `[[wikilinks]]` as anchors the
+//! UI can intercept, and lists its headings for a table of contents. Notes
+//! can carry arbitrary HTML, so everything goes through an allowlist
+//! sanitizer before it reaches the webview.
+
+use crate::links::{split_wikilink, WIKILINK_RE};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd, TextMergeStream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+const HEADING_TAGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Default ammonia allowlist plus wikilink anchors, heading ids, and
+/// task list checkboxes. Scripts, event handlers, and `javascript:` URLs
+/// are still stripped.
+static SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .add_tag_attributes("a", &["data-note", "data-heading"])
+        .add_allowed_classes("a", &["wikilink", "embed", "broken"])
+        .add_tags(&["input"])
+        .add_tag_attributes("input", &["checked", "disabled"])
+        .set_tag_attribute_value("input", "type", "checkbox");
+    for tag in HEADING_TAGS {
+        builder.add_tag_attributes(tag, &["id"]);
+    }
+    builder
+});
+
+/// A heading, with the `id` its rendered element carries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+}
+
+/// A note ready to preview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedNote {
+    pub path: String,
+    pub name: String,
+    /// Sanitized; safe to insert into the page as is
+    pub html: String,
+    pub frontmatter: HashMap<String, serde_json::Value>,
+    pub toc: Vec<TocEntry>,
+}
+
+/// Render a note body to sanitized HTML and collect its headings.
+/// Each wikilink becomes `<a class="wikilink" data-note="...">`, where
+/// `data-note` is the path `resolve` gives for the target, or the target as
+/// written with a `broken` class when it resolves to nothing. Links inside
+/// code are left alone.
+pub fn render_markdown(
+    body: &str,
+    resolve: impl Fn(&str) -> Option<String>,
+) -> (String, Vec<TocEntry>) {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+
+    let mut events = Vec::new();
+    let mut in_code_block = false;
+    for event in TextMergeStream::new(Parser::new_ext(body, options)) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) if !in_code_block => {
+                push_wikilinks(&mut events, text, &resolve);
+                continue;
+            }
+            _ => {}
+        }
+        events.push(event);
+    }
+    let toc = anchor_headings(&mut events);
+
+    let mut raw = String::new();
+    html::push_html(&mut raw, events.into_iter());
+    (SANITIZER.clean(&raw).to_string(), toc)
+}
+
+/// Push `text` as text events, with each wikilink in it as an anchor
+fn push_wikilinks<'a>(
+    events: &mut Vec<Event<'a>>,
+    text: CowStr<'a>,
+    resolve: &impl Fn(&str) -> Option<String>,
+) {
+    let mut rest = 0;
+    let mut pieces = Vec::new();
+    for cap in WIKILINK_RE.captures_iter(&text) {
+        let whole = cap.get(0).expect("group 0 always matches");
+        if whole.start() > rest {
+            pieces.push(Event::Text(text[rest..whole.start()].to_string().into()));
+        }
+        rest = whole.end();
+
+        let (target, heading, alias) = split_wikilink(&cap[2]);
+        let resolved = resolve(target);
+        let mut class = String::from("wikilink");
+        if &cap[1] == "!" {
+            class.push_str(" embed");
+        }
+        if resolved.is_none() {
+            class.push_str(" broken");
+        }
+        let mut open = format!(
+            "<a class=\"{}\" data-note=\"{}\"",
+            class,
+            escape_attr(resolved.as_deref().unwrap_or(target))
+        );
+        if let Some(heading) = heading {
+            open.push_str(&format!(" data-heading=\"{}\"", escape_attr(heading)));
+        }
+        open.push('>');
+
+        let label = alias.unwrap_or_else(|| cap[2].split('|').next().unwrap_or_default().trim());
+        pieces.push(Event::InlineHtml(open.into()));
+        pieces.push(Event::Text(label.to_string().into()));
+        pieces.push(Event::InlineHtml("</a>".into()));
+    }
+
+    if pieces.is_empty() {
+        events.push(Event::Text(text));
+        return;
+    }
+    if rest < text.len() {
+        pieces.push(Event::Text(text[rest..].to_string().into()));
+    }
+    events.extend(pieces);
+}
+
+/// Give every heading an `id` slug, unique within the note, and list them
+fn anchor_headings(events: &mut [Event]) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    let mut used: HashMap<String, usize> = HashMap::new();
+    let mut i = 0;
+    while i < events.len() {
+        let Event::Start(Tag::Heading { level, .. }) = &events[i] else {
+            i += 1;
+            continue;
+        };
+        let level = *level as u8;
+
+        let mut text = String::new();
+        let mut end = i + 1;
+        while end < events.len() && !matches!(events[end], Event::End(TagEnd::Heading(_))) {
+            if let Event::Text(t) | Event::Code(t) = &events[end] {
+                text.push_str(t);
+            }
+            end += 1;
+        }
+
+        let slug = slugify(&text);
+        let seen = used.entry(slug.clone()).or_insert(0);
+        let anchor = match *seen {
+            0 => slug,
+            n => format!("{}-{}", slug, n),
+        };
+        *seen += 1;
+
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+            *id = Some(anchor.clone().into());
+        }
+        toc.push(TocEntry {
+            level,
+            text: text.trim().to_string(),
+            anchor,
+        });
+        i = end;
+    }
+    toc
+}
+
+/// Lowercase letters and digits, with runs of anything else as one `-`
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
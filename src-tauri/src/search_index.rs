@@ -0,0 +1,333 @@
+//! Ranked full-text search over a vault.
+//! Each note's term counts for its title, frontmatter, and body are stored as
+//! one small file under `.nebula/index/`, so a write only rewrites that note's
+//! entry. Queries are scored with BM25 over a field-weighted term frequency.
+
+use crate::persist;
+use crate::vault::CacheStats;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Index entries, relative to the vault root
+pub const INDEX_DIR: &str = ".nebula/index";
+
+/// Weight of a term occurring in the title, frontmatter, and body
+const FIELD_WEIGHTS: [f64; 3] = [3.0, 1.5, 1.0];
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+const SNIPPET_CONTEXT: usize = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedNote {
+    path: String,
+    /// Note mtime when indexed, to catch edits made while Nebula wasn't running
+    modified: Option<String>,
+    /// Occurrences of each term in the title, frontmatter, and body
+    terms: HashMap<String, [u32; 3]>,
+    length: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedSearchResult {
+    pub path: String,
+    pub title: String,
+    pub score: f64,
+    pub snippet: String,
+    /// `[start, end)` character ranges of query terms within `snippet`
+    pub highlights: Vec<[usize; 2]>,
+}
+
+pub struct SearchIndex {
+    root: PathBuf,
+    notes: HashMap<String, IndexedNote>,
+    postings: HashMap<String, HashSet<String>>,
+    total_length: u64,
+    built_at: String,
+    updated_at: Option<String>,
+}
+
+impl SearchIndex {
+    /// Load the stored index, re-indexing notes that changed since they were
+    /// indexed and dropping entries for notes that are gone. `notes` lists
+    /// every note now in the vault; unreadable entries are rebuilt.
+    pub fn open(root: &Path, notes: &[String]) -> Self {
+        let mut index = Self::empty(root);
+        let mut stored: HashMap<String, IndexedNote> = HashMap::new();
+        if let Ok(entries) = std::fs::read_dir(root.join(INDEX_DIR)) {
+            for entry in entries.flatten() {
+                match persist::read_json::<IndexedNote>(&entry.path()) {
+                    Ok(Some(note)) => {
+                        stored.insert(note.path.clone(), note);
+                    }
+                    _ => {
+                        let _ = std::fs::remove_file(entry.path());
+                    }
+                }
+            }
+        }
+
+        let present: HashSet<&str> = notes.iter().map(String::as_str).collect();
+        for path in stored.keys().filter(|p| !present.contains(p.as_str())) {
+            let _ = std::fs::remove_file(index.entry_path(path));
+        }
+        for path in notes {
+            match stored.remove(path) {
+                Some(note) if note.modified == modified(&root.join(path)) => index.insert(note),
+                _ => index.update(path),
+            }
+        }
+        index
+    }
+
+    /// Discard the stored index and index every note from scratch
+    pub fn rebuild(root: &Path, notes: &[String]) -> std::io::Result<Self> {
+        match std::fs::remove_dir_all(root.join(INDEX_DIR)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let mut index = Self::empty(root);
+        for path in notes {
+            index.update(path);
+        }
+        Ok(index)
+    }
+
+    fn empty(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            notes: HashMap::new(),
+            postings: HashMap::new(),
+            total_length: 0,
+            built_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            notes: self.notes.len(),
+            entries: self.postings.values().map(HashSet::len).sum(),
+            built_at: self.built_at.clone(),
+            updated_at: self.updated_at.clone(),
+        }
+    }
+
+    /// Re-index one note, or drop it if it no longer exists
+    pub fn update(&mut self, path: &str) {
+        self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+        self.remove(path);
+        let full_path = self.root.join(path);
+        let Ok(content) = std::fs::read_to_string(&full_path) else {
+            let _ = std::fs::remove_file(self.entry_path(path));
+            return;
+        };
+
+        let (frontmatter, body) = crate::vault::parse_frontmatter(&content);
+        let title = title(path);
+        let frontmatter_text = frontmatter
+            .values()
+            .map(value_text)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut terms: HashMap<String, [u32; 3]> = HashMap::new();
+        let mut length = 0;
+        for (field, text) in [&title, &frontmatter_text, &body].into_iter().enumerate() {
+            for term in tokenize(text) {
+                terms.entry(term).or_default()[field] += 1;
+                length += 1;
+            }
+        }
+
+        let note = IndexedNote {
+            path: path.to_string(),
+            modified: modified(&full_path),
+            terms,
+            length,
+        };
+        if let Err(e) = persist::write_json(&self.entry_path(path), &note) {
+            tracing::warn!("Failed to store search index entry for {}: {}", path, e);
+        }
+        self.insert(note);
+    }
+
+    fn insert(&mut self, note: IndexedNote) {
+        for term in note.terms.keys() {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(note.path.clone());
+        }
+        self.total_length += u64::from(note.length);
+        self.notes.insert(note.path.clone(), note);
+    }
+
+    fn remove(&mut self, path: &str) {
+        let Some(note) = self.notes.remove(path) else {
+            return;
+        };
+        for term in note.terms.keys() {
+            if let Some(paths) = self.postings.get_mut(term) {
+                paths.remove(path);
+                if paths.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        self.total_length -= u64::from(note.length);
+    }
+
+    fn entry_path(&self, path: &str) -> PathBuf {
+        self.root.join(INDEX_DIR).join(format!(
+            "{}.json",
+            hex::encode(Sha256::digest(path.as_bytes()))
+        ))
+    }
+
+    /// Notes matching any query term, best first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<RankedSearchResult> {
+        let terms: Vec<String> = tokenize(query)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if terms.is_empty() || self.notes.is_empty() {
+            return Vec::new();
+        }
+
+        let count = self.notes.len() as f64;
+        let average_length = (self.total_length as f64 / count).max(1.0);
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        for term in &terms {
+            let Some(paths) = self.postings.get(term) else {
+                continue;
+            };
+            let df = paths.len() as f64;
+            let idf = (1.0 + (count - df + 0.5) / (df + 0.5)).ln();
+            for path in paths {
+                let note = &self.notes[path];
+                let counts = note.terms[term];
+                let tf: f64 = counts
+                    .iter()
+                    .zip(FIELD_WEIGHTS)
+                    .map(|(&n, weight)| f64::from(n) * weight)
+                    .sum();
+                let norm = 1.0 - BM25_B + BM25_B * f64::from(note.length) / average_length;
+                *scores.entry(path).or_default() +=
+                    idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm);
+            }
+        }
+
+        let mut ranked: Vec<(&str, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(path, score)| {
+                let (snippet, highlights) = self.snippet(path, &terms);
+                RankedSearchResult {
+                    path: path.to_string(),
+                    title: title(path),
+                    score,
+                    snippet,
+                    highlights,
+                }
+            })
+            .collect()
+    }
+
+    /// The first body line containing a query term, trimmed around it
+    fn snippet(&self, path: &str, terms: &[String]) -> (String, Vec<[usize; 2]>) {
+        let Ok(content) = std::fs::read_to_string(self.root.join(path)) else {
+            return (String::new(), Vec::new());
+        };
+        let (_, body) = crate::vault::parse_frontmatter(&content);
+
+        for line in body.lines() {
+            let chars: Vec<char> = line.chars().collect();
+            let folded: Vec<char> = chars.iter().map(|c| fold(*c)).collect();
+            let Some(at) = terms.iter().filter_map(|t| find_chars(&folded, t)).min() else {
+                continue;
+            };
+
+            let from = at.saturating_sub(SNIPPET_CONTEXT);
+            let to = (at + 2 * SNIPPET_CONTEXT).min(chars.len());
+            let window = &folded[from..to];
+            let mut highlights: Vec<[usize; 2]> = Vec::new();
+            for term in terms {
+                let len = term.chars().count();
+                let mut start = 0;
+                while let Some(i) = find_chars(&window[start..], term) {
+                    highlights.push([start + i, start + i + len]);
+                    start += i + len;
+                }
+            }
+            highlights.sort_unstable();
+
+            let mut snippet: String = chars[from..to].iter().collect();
+            if from > 0 {
+                snippet.insert(0, '…');
+                highlights
+                    .iter_mut()
+                    .for_each(|h| *h = [h[0] + 1, h[1] + 1]);
+            }
+            if to < chars.len() {
+                snippet.push('…');
+            }
+            return (snippet, highlights);
+        }
+        (String::new(), Vec::new())
+    }
+}
+
+/// Lowercased alphanumeric runs of two or more characters
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().nth(1).is_some())
+        .map(|t| t.chars().map(fold).collect())
+}
+
+/// Case-fold a single character, keeping one char so offsets stay aligned
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn find_chars(haystack: &[char], needle: &str) -> Option<usize> {
+    let needle: Vec<char> = needle.chars().collect();
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|w| w == needle.as_slice())
+}
+
+fn value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => {
+            items.iter().map(value_text).collect::<Vec<_>>().join(" ")
+        }
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn title(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn modified(path: &Path) -> Option<String> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339())
+}
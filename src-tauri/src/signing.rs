@@ -0,0 +1,134 @@
+use crate::sync::MutexExt;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::OsRng;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Holds the Ed25519 key used to sign audit exports.
+/// The key lives in the app data directory and is generated on first use; it
+/// never crosses the IPC boundary, only its public half does.
+pub struct SigningKeyStore {
+    key_path: Mutex<Option<PathBuf>>,
+    key: Mutex<Option<SigningKey>>,
+}
+
+impl SigningKeyStore {
+    pub fn new() -> Self {
+        Self {
+            key_path: Mutex::new(None),
+            key: Mutex::new(None),
+        }
+    }
+
+    /// Set where the key is stored; called during setup once the app data dir is known
+    pub fn set_key_path(&self, path: PathBuf) {
+        let mut key_path = self.key_path.lock_recover();
+        *key_path = Some(path);
+        self.key.lock_recover().take();
+    }
+
+    /// Load the signing key, generating and persisting one if none exists yet
+    pub fn signing_key(&self) -> Result<SigningKey, String> {
+        let mut key = self.key.lock_recover();
+        if let Some(key) = key.as_ref() {
+            return Ok(key.clone());
+        }
+
+        let path = self
+            .key_path
+            .lock_recover()
+            .clone()
+            .ok_or("Signing key store is not initialized")?;
+
+        let loaded = if path.exists() {
+            let encoded = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            let bytes: [u8; 32] = hex::decode(encoded.trim())
+                .map_err(|e| format!("Corrupt signing key: {}", e))?
+                .try_into()
+                .map_err(|_| "Corrupt signing key: wrong length".to_string())?;
+            SigningKey::from_bytes(&bytes)
+        } else {
+            let generated = SigningKey::generate(&mut OsRng);
+            write_key_file(&path, &hex::encode(generated.to_bytes()))?;
+            generated
+        };
+
+        *key = Some(loaded.clone());
+        Ok(loaded)
+    }
+
+    pub fn public_key_hex(&self) -> Result<String, String> {
+        Ok(hex::encode(self.signing_key()?.verifying_key().to_bytes()))
+    }
+}
+
+fn write_key_file(path: &PathBuf, contents: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, contents).map_err(|e| e.to_string())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Hex-encoded detached signature over `bytes`
+pub fn sign(key: &SigningKey, bytes: &[u8]) -> String {
+    hex::encode(key.sign(bytes).to_bytes())
+}
+
+/// Check a hex-encoded detached signature against a hex-encoded public key
+pub fn verify_export(bytes: &[u8], signature: &str, public_key: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(&signature) else {
+        return false;
+    };
+    let Some(public_key) = hex::decode(public_key)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b).ok())
+        .and_then(|b| VerifyingKey::from_bytes(&b).ok())
+    else {
+        return false;
+    };
+
+    public_key.verify(bytes, &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_key_is_reloaded_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys").join("signing.key");
+
+        let first = SigningKeyStore::new();
+        first.set_key_path(path.clone());
+        let public_key = first.public_key_hex().unwrap();
+
+        let second = SigningKeyStore::new();
+        second.set_key_path(path);
+        assert_eq!(second.public_key_hex().unwrap(), public_key);
+    }
+
+    #[test]
+    fn malformed_signature_or_key_does_not_verify() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let signature = sign(&key, b"export");
+        let public_key = hex::encode(key.verifying_key().to_bytes());
+
+        assert!(verify_export(b"export", &signature, &public_key));
+        assert!(!verify_export(b"export", "not hex", &public_key));
+        assert!(!verify_export(b"export", &signature[2..], &public_key));
+        assert!(!verify_export(b"export", &signature, &public_key[2..]));
+    }
+}
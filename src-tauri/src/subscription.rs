@@ -0,0 +1,113 @@
+//! In-process subscriptions to the audit log, for headless harnesses and
+//! tooling that want to follow a project's events as they're recorded.
+
+use crate::audit::AuditEvent;
+use crate::sync::MutexExt;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Registry of live subscribers, shared with each subscription so dropping
+/// one can unregister itself
+pub struct SubscriberSet {
+    next_id: AtomicU64,
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+struct Subscriber {
+    id: u64,
+    project_id: String,
+    tx: Sender<AuditEvent>,
+}
+
+impl SubscriberSet {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn subscribe(self: &Arc<Self>, project_id: &str) -> AuditSubscription {
+        let (tx, rx) = mpsc::channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.subscribers.lock_recover().push(Subscriber {
+            id,
+            project_id: project_id.to_string(),
+            tx,
+        });
+
+        AuditSubscription {
+            id,
+            set: Arc::clone(self),
+            rx,
+        }
+    }
+
+    /// Deliver an event to every subscriber of its project
+    pub fn publish(&self, event: &AuditEvent) {
+        let mut subscribers = self.subscribers.lock_recover();
+        // A failed send means the receiver is gone; drop it here too
+        subscribers
+            .retain(|s| s.project_id != event.project_id || s.tx.send(event.clone()).is_ok());
+    }
+
+    fn remove(&self, id: u64) {
+        self.subscribers.lock_recover().retain(|s| s.id != id);
+    }
+}
+
+/// Live events for one project. Unsubscribes when dropped.
+pub struct AuditSubscription {
+    id: u64,
+    set: Arc<SubscriberSet>,
+    rx: Receiver<AuditEvent>,
+}
+
+impl AuditSubscription {
+    /// Wait up to `timeout` for the next event
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<AuditEvent> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for AuditSubscription {
+    fn drop(&mut self) {
+        self.set.remove(self.id);
+    }
+}
+
+/// Blocking `tail -f` over a project's log: yields the events that existed
+/// when the tail was opened, then live ones, and ends once no event arrives
+/// within the timeout
+pub struct AuditTail {
+    backlog: VecDeque<AuditEvent>,
+    subscription: AuditSubscription,
+    timeout: Duration,
+}
+
+impl AuditTail {
+    pub fn new(
+        backlog: Vec<AuditEvent>,
+        subscription: AuditSubscription,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            backlog: backlog.into(),
+            subscription,
+            timeout,
+        }
+    }
+}
+
+impl Iterator for AuditTail {
+    type Item = AuditEvent;
+
+    fn next(&mut self) -> Option<AuditEvent> {
+        self.backlog
+            .pop_front()
+            .or_else(|| self.subscription.recv_timeout(self.timeout))
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::{Mutex, MutexGuard};
+
+/// Lock access that survives poisoning.
+/// A panic while a lock is held shouldn't take down every later command, so
+/// the guard is recovered (and the poison cleared) with a warning instead.
+pub trait MutexExt<T> {
+    fn lock_recover(&self) -> MutexGuard<'_, T>;
+}
+
+/// Names of the given locks that are poisoned, for health reports.
+/// Checking leaves the poison in place.
+pub fn poisoned<const N: usize>(locks: [(&'static str, bool); N]) -> Vec<&'static str> {
+    locks
+        .into_iter()
+        .filter_map(|(name, poisoned)| poisoned.then_some(name))
+        .collect()
+}
+
+impl<T> MutexExt<T> for Mutex<T> {
+    fn lock_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            tracing::warn!(
+                "Recovering poisoned lock on {}; a previous holder panicked",
+                std::any::type_name::<T>()
+            );
+            let guard = poisoned.into_inner();
+            self.clear_poison();
+            guard
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn lock_recover_survives_a_poisoned_lock() {
+        let lock = Arc::new(Mutex::new(vec![1, 2, 3]));
+        let holder = Arc::clone(&lock);
+        let result = std::thread::spawn(move || {
+            let mut guard = holder.lock().unwrap();
+            guard.push(4);
+            panic!("holder panicked");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(lock.is_poisoned());
+        assert_eq!(poisoned([("items", lock.is_poisoned())]), vec!["items"]);
+
+        assert_eq!(*lock.lock_recover(), vec![1, 2, 3, 4]);
+        assert!(!lock.is_poisoned());
+        assert!(poisoned([("items", lock.is_poisoned())]).is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! Tag extraction and indexing for vault notes.
+//! Tags come from the frontmatter `tags` key (a string or a list) and from
+//! inline `#tags` in the body. Tags are lowercased, and a nested tag like
+//! `#parent/child` is indexed under both `parent/child` and `parent`.
+
+use crate::vault::CacheStats;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// `#tag` at the start of a line or after whitespace; `# Heading` has a space
+/// so it never matches
+static INLINE_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|\s)#([\p{L}\p{N}_\-/]+)").unwrap());
+
+/// A tag and how many notes carry it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Every tag a note carries, with nested tags expanded to their prefixes
+pub fn extract_tags(
+    frontmatter: &HashMap<String, serde_json::Value>,
+    body: &str,
+) -> BTreeSet<String> {
+    let mut raw: Vec<String> = Vec::new();
+    match frontmatter.get("tags") {
+        Some(serde_json::Value::String(s)) => {
+            raw.extend(s.split([',', ' ']).map(str::to_string));
+        }
+        Some(serde_json::Value::Array(items)) => {
+            raw.extend(items.iter().filter_map(|v| v.as_str()).map(str::to_string));
+        }
+        _ => {}
+    }
+
+    let mut in_fence = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if !in_fence {
+            raw.extend(INLINE_TAG_RE.captures_iter(line).map(|c| c[1].to_string()));
+        }
+    }
+
+    let mut tags = BTreeSet::new();
+    for tag in raw {
+        let tag = tag
+            .trim()
+            .trim_start_matches('#')
+            .trim_matches('/')
+            .to_lowercase();
+        // Obsidian doesn't treat all-digit words like `#123` as tags
+        if tag.is_empty() || tag.chars().all(|c| c.is_ascii_digit() || c == '/') {
+            continue;
+        }
+        for (idx, _) in tag.match_indices('/') {
+            tags.insert(tag[..idx].to_string());
+        }
+        tags.insert(tag);
+    }
+    tags
+}
+
+/// Tags per note for one vault, updated a note at a time as notes are
+/// written. Each note's content hash is kept so an unchanged note isn't
+/// reparsed, and the notes per tag are kept alongside so a change only
+/// touches the tags it added or removed.
+#[derive(Debug)]
+pub struct TagIndex {
+    notes: HashMap<String, TaggedNote>,
+    by_tag: HashMap<String, BTreeSet<String>>,
+    built_at: String,
+    updated_at: Option<String>,
+}
+
+#[derive(Debug)]
+struct TaggedNote {
+    hash: String,
+    tags: BTreeSet<String>,
+}
+
+impl TagIndex {
+    /// Index `notes` (vault-relative paths)
+    pub fn build(vault_root: &Path, notes: &[String]) -> Self {
+        let mut index = Self {
+            notes: HashMap::new(),
+            by_tag: HashMap::new(),
+            built_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: None,
+        };
+        for note in notes {
+            index.update(vault_root, note);
+        }
+        index
+    }
+
+    /// Re-read one note; a note that can't be read is dropped from the index
+    pub fn update(&mut self, vault_root: &Path, note: &str) {
+        let Ok(content) = std::fs::read_to_string(vault_root.join(note)) else {
+            if let Some(old) = self.notes.remove(note) {
+                self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+                self.retag(note, &old.tags, &BTreeSet::new());
+            }
+            return;
+        };
+        let hash = hex::encode(Sha256::digest(content.as_bytes()));
+        if self.notes.get(note).is_some_and(|n| n.hash == hash) {
+            return;
+        }
+
+        self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+        let (frontmatter, body) = crate::vault::parse_frontmatter(&content);
+        let tags = extract_tags(&frontmatter, &body);
+        let old = self.notes.insert(
+            note.to_string(),
+            TaggedNote {
+                hash,
+                tags: tags.clone(),
+            },
+        );
+        self.retag(note, &old.map(|n| n.tags).unwrap_or_default(), &tags);
+    }
+
+    /// Move `note` between tags in `by_tag`, touching only the tags that differ
+    fn retag(&mut self, note: &str, old: &BTreeSet<String>, new: &BTreeSet<String>) {
+        for tag in old.difference(new) {
+            if let Some(notes) = self.by_tag.get_mut(tag) {
+                notes.remove(note);
+                if notes.is_empty() {
+                    self.by_tag.remove(tag);
+                }
+            }
+        }
+        for tag in new.difference(old) {
+            self.by_tag
+                .entry(tag.clone())
+                .or_default()
+                .insert(note.to_string());
+        }
+    }
+
+    /// Notes carrying `tag` (case-insensitive, leading `#` optional), sorted by path
+    pub fn notes_with(&self, tag: &str) -> Vec<&str> {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        self.by_tag
+            .get(&tag)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Every tag with the number of notes carrying it, sorted by tag
+    pub fn counts(&self) -> Vec<TagCount> {
+        let mut counts: Vec<TagCount> = self
+            .by_tag
+            .iter()
+            .map(|(tag, notes)| TagCount {
+                tag: tag.clone(),
+                count: notes.len(),
+            })
+            .collect();
+        counts.sort_unstable_by(|a, b| a.tag.cmp(&b.tag));
+        counts
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            notes: self.notes.len(),
+            entries: self.by_tag.values().map(BTreeSet::len).sum(),
+            built_at: self.built_at.clone(),
+            updated_at: self.updated_at.clone(),
+        }
+    }
+}
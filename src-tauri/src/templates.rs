@@ -0,0 +1,226 @@
+//! Required structure for Level 0/1/2 notes.
+//! Each level lists the frontmatter keys and section headings a note must
+//! carry. Teams can override the built-in set with a config note in the vault.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Vault-relative config note whose frontmatter replaces the default templates
+pub const TEMPLATE_CONFIG_NOTE: &str = "templates/compliance.md";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelTemplate {
+    pub required_keys: Vec<String>,
+    /// Heading text without the leading `#`s, compared case-insensitively
+    pub required_headings: Vec<String>,
+}
+
+/// Level number to the template notes of that level must satisfy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateRegistry {
+    pub levels: BTreeMap<u8, LevelTemplate>,
+}
+
+impl Default for TemplateRegistry {
+    /// Mirrors what the built-in note writers produce
+    fn default() -> Self {
+        let template = |keys: &[&str], headings: &[&str]| LevelTemplate {
+            required_keys: keys.iter().map(|s| s.to_string()).collect(),
+            required_headings: headings.iter().map(|s| s.to_string()).collect(),
+        };
+        Self {
+            levels: BTreeMap::from([
+                (
+                    0,
+                    template(
+                        &["type", "service", "change_id", "created", "author_agent"],
+                        &["What Changed", "Dependencies", "Tests Added/Updated"],
+                    ),
+                ),
+                (
+                    1,
+                    template(
+                        &["type", "service", "domain", "owner", "created"],
+                        &["Architecture", "Data Stores"],
+                    ),
+                ),
+                (
+                    2,
+                    template(&["type", "system", "created"], &["Service Catalog"]),
+                ),
+            ]),
+        }
+    }
+}
+
+impl TemplateRegistry {
+    /// The vault's configured templates, or the defaults when the config note
+    /// is missing or unreadable. The note's frontmatter is a `levels` map:
+    ///
+    /// ```yaml
+    /// levels:
+    ///   0:
+    ///     required_keys: [type, service]
+    ///     required_headings: [What Changed]
+    /// ```
+    pub fn load(vault_root: &Path) -> Self {
+        let path = vault_root.join(TEMPLATE_CONFIG_NOTE);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        let Some((frontmatter, _)) = crate::vault::split_frontmatter(&content) else {
+            tracing::warn!(
+                "{} has no frontmatter; using default templates",
+                path.display()
+            );
+            return Self::default();
+        };
+        match serde_yaml::from_str(frontmatter) {
+            Ok(registry) => registry,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Describe what a note of `level` is missing, or `None` if it complies.
+    /// Levels without a template accept anything.
+    pub fn violations(
+        &self,
+        level: u8,
+        frontmatter: &std::collections::HashMap<String, serde_json::Value>,
+        content: &str,
+    ) -> Option<String> {
+        let template = self.levels.get(&level)?;
+
+        let missing_keys: Vec<&str> = template
+            .required_keys
+            .iter()
+            .filter(|key| frontmatter.get(*key).is_none_or(|v| v.is_null()))
+            .map(String::as_str)
+            .collect();
+
+        let headings = headings(content);
+        let missing_headings: Vec<&str> = template
+            .required_headings
+            .iter()
+            .filter(|h| !headings.contains(&h.trim().to_lowercase()))
+            .map(String::as_str)
+            .collect();
+
+        let mut problems = Vec::new();
+        if !missing_keys.is_empty() {
+            problems.push(format!(
+                "missing frontmatter keys: {}",
+                missing_keys.join(", ")
+            ));
+        }
+        if !missing_headings.is_empty() {
+            problems.push(format!("missing headings: {}", missing_headings.join(", ")));
+        }
+        if problems.is_empty() {
+            None
+        } else {
+            Some(format!("level {} note {}", level, problems.join("; ")))
+        }
+    }
+}
+
+/// Lowercased ATX heading text, skipping fenced code blocks
+fn headings(content: &str) -> Vec<String> {
+    let mut in_fence = false;
+    let mut found = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence && line.starts_with('#') {
+            let text = line.trim_start_matches('#');
+            if text.starts_with(' ') {
+                found.push(text.trim().trim_end_matches('#').trim().to_lowercase());
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn frontmatter(keys: &[&str]) -> HashMap<String, serde_json::Value> {
+        keys.iter()
+            .map(|k| (k.to_string(), serde_json::json!("x")))
+            .collect()
+    }
+
+    const SYSTEM_BODY: &str = "# Overview\n\n## Service Catalog\n\n- api\n";
+
+    #[test]
+    fn compliant_note_has_no_violations() {
+        let registry = TemplateRegistry::default();
+        let fm = frontmatter(&["type", "system", "created"]);
+        assert_eq!(registry.violations(2, &fm, SYSTEM_BODY), None);
+    }
+
+    #[test]
+    fn missing_key_is_reported() {
+        let registry = TemplateRegistry::default();
+        let fm = frontmatter(&["type", "created"]);
+        let details = registry.violations(2, &fm, SYSTEM_BODY).unwrap();
+        assert_eq!(details, "level 2 note missing frontmatter keys: system");
+    }
+
+    #[test]
+    fn null_key_counts_as_missing() {
+        let registry = TemplateRegistry::default();
+        let mut fm = frontmatter(&["type", "created"]);
+        fm.insert("system".to_string(), serde_json::Value::Null);
+        let details = registry.violations(2, &fm, SYSTEM_BODY).unwrap();
+        assert!(details.contains("missing frontmatter keys: system"));
+    }
+
+    #[test]
+    fn missing_heading_is_reported() {
+        let registry = TemplateRegistry::default();
+        let fm = frontmatter(&["type", "system", "created"]);
+        let details = registry.violations(2, &fm, "# Overview\n").unwrap();
+        assert_eq!(details, "level 2 note missing headings: Service Catalog");
+    }
+
+    #[test]
+    fn missing_keys_and_headings_are_both_reported() {
+        let registry = TemplateRegistry::default();
+        let details = registry.violations(2, &HashMap::new(), "").unwrap();
+        assert_eq!(
+            details,
+            "level 2 note missing frontmatter keys: type, system, created; \
+             missing headings: Service Catalog"
+        );
+    }
+
+    #[test]
+    fn headings_in_code_fences_do_not_count() {
+        let registry = TemplateRegistry::default();
+        let fm = frontmatter(&["type", "system", "created"]);
+        let body = "```\n## Service Catalog\n```\n";
+        assert!(registry.violations(2, &fm, body).is_some());
+    }
+
+    #[test]
+    fn headings_match_case_insensitively() {
+        let registry = TemplateRegistry::default();
+        let fm = frontmatter(&["type", "system", "created"]);
+        assert_eq!(registry.violations(2, &fm, "## service catalog ##\n"), None);
+    }
+
+    #[test]
+    fn levels_without_a_template_accept_anything() {
+        let registry = TemplateRegistry::default();
+        assert_eq!(registry.violations(7, &HashMap::new(), ""), None);
+    }
+}
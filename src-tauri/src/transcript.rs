@@ -0,0 +1,108 @@
+//! Workstreams as vault notes.
+//! Each workstream is mirrored to `workstreams/{id}.md`: the frontmatter holds
+//! its metadata and the body its message transcript. Every message starts
+//! with a marker comment carrying its metadata, so a transcript can be read
+//! back and new messages can be appended without rewriting the note.
+
+use crate::commands::{MessageData, WorkstreamData};
+use std::collections::HashMap;
+
+/// Vault folder holding one note per workstream
+pub const WORKSTREAMS_DIR: &str = "workstreams";
+
+const MESSAGE_MARKER: &str = "<!-- nebula:message ";
+
+pub fn note_path(workstream_id: &str) -> String {
+    format!("{}/{}.md", WORKSTREAMS_DIR, workstream_id)
+}
+
+/// The workstream's metadata, everything but its messages
+pub fn frontmatter(ws: &WorkstreamData) -> HashMap<String, serde_json::Value> {
+    let mut frontmatter: HashMap<String, serde_json::Value> = match serde_json::to_value(ws) {
+        Ok(serde_json::Value::Object(fields)) => fields.into_iter().collect(),
+        _ => HashMap::new(),
+    };
+    frontmatter.remove("messages");
+    frontmatter.insert("type".to_string(), serde_json::json!("workstream"));
+    frontmatter
+}
+
+/// Note body: the original request followed by the full transcript
+pub fn render_body(ws: &WorkstreamData) -> String {
+    let mut body = format!("# {}\n\n{}\n\n## Transcript\n\n", ws.title, ws.user_request);
+    for message in &ws.messages {
+        body.push_str(&render_message(message));
+    }
+    body
+}
+
+/// One transcript entry, ready to append to the note
+pub fn render_message(message: &MessageData) -> String {
+    let meta = serde_json::json!({
+        "id": message.id,
+        "role": message.role,
+        "timestamp": message.timestamp,
+        "agent_id": message.agent_id,
+        "agent_name": message.agent_name,
+        "redacted": message.redacted,
+    });
+    format!(
+        "{}{} -->\n### {} · {}\n\n{}\n\n",
+        MESSAGE_MARKER,
+        meta,
+        message.agent_name.as_deref().unwrap_or(&message.role),
+        message.timestamp,
+        message.content.trim_end()
+    )
+}
+
+/// Read a workstream back from its note, or `None` if it isn't one
+pub fn parse(content: &str) -> Option<WorkstreamData> {
+    let (frontmatter, body) = crate::vault::parse_frontmatter(content);
+    if frontmatter.get("type").and_then(|t| t.as_str()) != Some("workstream") {
+        return None;
+    }
+    let mut fields: serde_json::Map<String, serde_json::Value> = frontmatter.into_iter().collect();
+    fields.insert(
+        "messages".to_string(),
+        serde_json::json!(parse_messages(&body)),
+    );
+    serde_json::from_value(serde_json::Value::Object(fields)).ok()
+}
+
+fn parse_messages(body: &str) -> Vec<MessageData> {
+    let mut messages = Vec::new();
+    let mut current: Option<(serde_json::Value, Vec<&str>)> = None;
+
+    let mut finish = |entry: Option<(serde_json::Value, Vec<&str>)>| {
+        let Some((mut meta, mut lines)) = entry else {
+            return;
+        };
+        // Drop the `### author · time` heading written under the marker
+        if lines.first().is_some_and(|l| l.starts_with("### ")) {
+            lines.remove(0);
+        }
+        meta["content"] = serde_json::json!(lines.join("\n").trim());
+        match serde_json::from_value(meta) {
+            Ok(message) => messages.push(message),
+            Err(e) => tracing::warn!("Skipping unreadable transcript message: {}", e),
+        }
+    };
+
+    for line in body.lines() {
+        let meta = line
+            .strip_prefix(MESSAGE_MARKER)
+            .and_then(|rest| rest.trim_end().strip_suffix("-->"))
+            .and_then(|json| serde_json::from_str(json.trim()).ok());
+        match meta {
+            Some(meta) => finish(current.replace((meta, Vec::new()))),
+            None => {
+                if let Some((_, lines)) = current.as_mut() {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    finish(current);
+    messages
+}
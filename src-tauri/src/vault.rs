@@ -1,41 +1,116 @@
+use crate::links::{self, BrokenLink, LinkIndex, LinkResolver, WikiLink};
+use crate::render::{self, RenderedNote};
+use crate::search_index::{RankedSearchResult, SearchIndex};
+use crate::sync::{self, MutexExt};
+use crate::tags::{TagCount, TagIndex};
+use crate::templates::{TemplateRegistry, TEMPLATE_CONFIG_NOTE};
+use crate::watcher::VaultWatchers;
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Mutex;
+use tauri::AppHandle;
+use uuid::Uuid;
 
 /// Vault manager — handles reading/writing to the Obsidian vault.
 /// Enforces template compliance for Level 0/1/2 notes.
 pub struct VaultManager {
     vault_paths: Mutex<HashMap<String, PathBuf>>,
+    level_maps: Mutex<HashMap<String, LevelMap>>,
+    /// Paths the project's config tells walks and listings to skip
+    ignore_sets: Mutex<HashMap<String, GlobSet>>,
+    /// Built on the first link query and dropped whenever a note is written
+    link_indexes: Mutex<HashMap<String, LinkIndex>>,
+    /// Built on the first tag query; a written note updates only its own entry
+    tag_indexes: Mutex<HashMap<String, TagIndex>>,
+    /// Loaded from `.nebula/index/` on the first ranked search and kept
+    /// current note by note, like the tag index
+    search_indexes: Mutex<HashMap<String, SearchIndex>>,
+    watchers: VaultWatchers,
+}
+
+/// Path globs mapped to the template level notes under them must satisfy
+struct LevelMap {
+    rules: Vec<LevelRule>,
+    matchers: Vec<GlobMatcher>,
 }
 
 impl VaultManager {
     pub fn new() -> Self {
         Self {
             vault_paths: Mutex::new(HashMap::new()),
+            level_maps: Mutex::new(HashMap::new()),
+            ignore_sets: Mutex::new(HashMap::new()),
+            link_indexes: Mutex::new(HashMap::new()),
+            tag_indexes: Mutex::new(HashMap::new()),
+            search_indexes: Mutex::new(HashMap::new()),
+            watchers: VaultWatchers::new(),
+        }
+    }
+
+    /// Watch registered vaults for outside edits, now and as they're registered
+    pub fn attach_app_handle(&self, app: AppHandle) {
+        self.watchers.attach(app);
+        let paths = self.vault_paths.lock_recover().clone();
+        for (project_id, path) in paths {
+            self.watchers.watch(&project_id, &path);
         }
     }
 
-    /// Register a project's vault path
-    pub fn register_vault(&self, project_id: &str, vault_path: PathBuf) {
-        let mut paths = self.vault_paths.lock().unwrap();
+    /// Map a project to its vault directory, which must already exist
+    pub fn register_vault(&self, project_id: &str, vault_path: PathBuf) -> Result<(), VaultError> {
+        if !vault_path.is_dir() {
+            return Err(VaultError::InvalidVaultPath(format!(
+                "{} is not an existing directory",
+                vault_path.display()
+            )));
+        }
+        let vault_path = vault_path
+            .canonicalize()
+            .map_err(|e| VaultError::IoError(e.to_string()))?;
+
+        self.watchers.watch(project_id, &vault_path);
+        let mut paths = self.vault_paths.lock_recover();
         paths.insert(project_id.to_string(), vault_path);
+        self.invalidate_indexes(project_id);
+        Ok(())
+    }
+
+    /// Forget a project's vault, e.g. to undo a failed import. The directory
+    /// is left as it is.
+    pub fn unregister_vault(&self, project_id: &str) {
+        self.vault_paths.lock_recover().remove(project_id);
+        self.watchers.unwatch(project_id);
+        self.invalidate_indexes(project_id);
     }
 
     /// Read a note from the vault
     pub fn read_note(&self, project_id: &str, note_path: &str) -> Result<VaultNote, VaultError> {
-        let paths = self.vault_paths.lock().unwrap();
-        let vault_path = paths
-            .get(project_id)
-            .ok_or(VaultError::VaultNotFound(project_id.to_string()))?;
+        self.read_note_with(project_id, note_path, false)
+    }
 
-        let full_path = vault_path.join(note_path);
+    /// Read a note, rejecting binary content. With `lossy`, invalid UTF-8 is
+    /// replaced with U+FFFD instead of failing with `NotTextNote`.
+    pub fn read_note_with(
+        &self,
+        project_id: &str,
+        note_path: &str,
+        lossy: bool,
+    ) -> Result<VaultNote, VaultError> {
+        // Cloned rather than held, so reads can run in parallel
+        let vault_path = self.vault_path(project_id)?;
+        let full_path = resolve_in_vault(&vault_path, note_path)?;
         if !full_path.exists() {
             return Err(VaultError::NoteNotFound(note_path.to_string()));
         }
 
-        let content =
-            std::fs::read_to_string(&full_path).map_err(|e| VaultError::IoError(e.to_string()))?;
+        let bytes = std::fs::read(&full_path).map_err(|e| VaultError::IoError(e.to_string()))?;
+        let content_hash = content_hash(&bytes);
+        let (content, replaced) = decode_note(note_path, bytes, lossy)?;
 
         let (frontmatter, body) = parse_frontmatter(&content);
 
@@ -56,64 +131,331 @@ impl VaultManager {
                         .to_rfc3339()
                 })
                 .unwrap_or_default(),
+            lossy: replaced,
+            content_hash,
         })
     }
 
-    /// Write a note to the vault
+    /// Write a note to the vault. A note with a template level must satisfy
+    /// that level's template; templates themselves are exempt.
+    /// With `expected_last_modified` or `expected_hash` (the `last_modified`
+    /// or `content_hash` from the last read), the write is refused with
+    /// `Conflict` if the note has changed on disk since. The hash is the more
+    /// reliable check; mtimes can shift across filesystems and sync tools.
     pub fn write_note(
         &self,
         project_id: &str,
         note_path: &str,
         frontmatter: &HashMap<String, serde_json::Value>,
         content: &str,
+        expected_last_modified: Option<&str>,
+        expected_hash: Option<&str>,
     ) -> Result<(), VaultError> {
-        let paths = self.vault_paths.lock().unwrap();
+        let paths = self.vault_paths.lock_recover();
         let vault_path = paths
             .get(project_id)
             .ok_or(VaultError::VaultNotFound(project_id.to_string()))?;
 
-        let full_path = vault_path.join(note_path);
+        let full_path = resolve_in_vault(vault_path, note_path)?;
+
+        if expected_last_modified.is_some() || expected_hash.is_some() {
+            let on_disk = modified_time(&full_path);
+            let on_disk_hash = std::fs::read(&full_path)
+                .map(|bytes| content_hash(&bytes))
+                .unwrap_or_default();
+            let modified_matches = expected_last_modified.is_none_or(|expected| {
+                let expected = chrono::DateTime::parse_from_rfc3339(expected).ok();
+                on_disk == expected.map(|t| t.with_timezone(&chrono::Utc))
+            });
+            let hash_matches = expected_hash.is_none_or(|expected| expected == on_disk_hash);
+            if !modified_matches || !hash_matches {
+                return Err(VaultError::Conflict {
+                    on_disk_modified: on_disk.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    on_disk_hash,
+                });
+            }
+        }
+
+        if !is_template_path(note_path) {
+            if let Some(level) = self.required_level(project_id, note_path, Some(frontmatter)) {
+                let registry = TemplateRegistry::load(vault_path);
+                if let Some(details) = registry.violations(level, frontmatter, content) {
+                    return Err(VaultError::TemplateViolation(format!(
+                        "{}: {}",
+                        note_path, details
+                    )));
+                }
+            }
+        }
 
         // Ensure parent directory exists
         if let Some(parent) = full_path.parent() {
             std::fs::create_dir_all(parent).map_err(|e| VaultError::IoError(e.to_string()))?;
         }
 
-        // Build frontmatter YAML
-        let fm_yaml =
-            serde_json::to_string_pretty(frontmatter).unwrap_or_else(|_| "{}".to_string());
-        let full_content = format!("---\n{}\n---\n\n{}", fm_yaml, content);
+        // Build frontmatter YAML, keys sorted so rewrites produce stable diffs
+        let full_content = if frontmatter.is_empty() {
+            content.to_string()
+        } else {
+            let sorted: BTreeMap<&String, &serde_json::Value> = frontmatter.iter().collect();
+            let fm_yaml =
+                serde_yaml::to_string(&sorted).map_err(|e| VaultError::IoError(e.to_string()))?;
+            format!("---\n{}---\n\n{}", fm_yaml, content)
+        };
 
         std::fs::write(&full_path, full_content).map_err(|e| VaultError::IoError(e.to_string()))?;
+        self.refresh_note(project_id, vault_path, note_path);
+
+        Ok(())
+    }
+
+    /// Set and remove frontmatter keys, leaving the body byte-for-byte as it
+    /// was. Existing keys keep their order; a note without frontmatter gains
+    /// a block. Removing a key the note doesn't have is a no-op. Returns the
+    /// note's frontmatter after the patch.
+    pub fn patch_frontmatter(
+        &self,
+        project_id: &str,
+        note_path: &str,
+        updates: &HashMap<String, serde_json::Value>,
+        removals: &[String],
+    ) -> Result<HashMap<String, serde_json::Value>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let full_path = resolve_in_vault(&vault_path, note_path)?;
+        if !full_path.is_file() {
+            return Err(VaultError::NoteNotFound(note_path.to_string()));
+        }
+        let bytes = std::fs::read(&full_path).map_err(|e| VaultError::IoError(e.to_string()))?;
+        let (content, _) = decode_note(note_path, bytes, false)?;
+
+        let (bom, content) = match content.strip_prefix('\u{feff}') {
+            Some(rest) => ("\u{feff}", rest),
+            None => ("", content.as_str()),
+        };
+        let had_block = split_frontmatter(content).is_some();
+        let (mut mapping, body) = match split_frontmatter(content) {
+            Some((fm_str, body)) => {
+                let mapping = match serde_yaml::from_str::<serde_yaml::Value>(fm_str) {
+                    Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+                    Ok(serde_yaml::Value::Null) => serde_yaml::Mapping::new(),
+                    Ok(_) => {
+                        return Err(VaultError::InvalidFrontmatter(format!(
+                            "{}: frontmatter is not a key/value mapping",
+                            note_path
+                        )))
+                    }
+                    Err(e) => {
+                        return Err(VaultError::InvalidFrontmatter(format!(
+                            "{}: {}",
+                            note_path, e
+                        )))
+                    }
+                };
+                (mapping, body)
+            }
+            None => (serde_yaml::Mapping::new(), content),
+        };
+
+        let mut changed = false;
+        for key in removals {
+            changed |= mapping.remove(key.as_str()).is_some();
+        }
+        let mut keys: Vec<&String> = updates.keys().collect();
+        keys.sort();
+        for key in keys {
+            let value = serde_yaml::to_value(&updates[key])
+                .map_err(|e| VaultError::InvalidFrontmatter(e.to_string()))?;
+            let key = serde_yaml::Value::String(key.clone());
+            if mapping.get(&key) != Some(&value) {
+                mapping.insert(key, value);
+                changed = true;
+            }
+        }
+
+        let yaml = serde_yaml::to_string(&mapping)
+            .map_err(|e| VaultError::InvalidFrontmatter(e.to_string()))?;
+        let (frontmatter, _) = parse_frontmatter(&format!("---\n{}---\n", yaml));
+        if !changed {
+            return Ok(frontmatter);
+        }
+
+        if !is_template_path(note_path) {
+            if let Some(level) = self.required_level(project_id, note_path, Some(&frontmatter)) {
+                let registry = TemplateRegistry::load(&vault_path);
+                if let Some(details) = registry.violations(level, &frontmatter, body.trim()) {
+                    return Err(VaultError::TemplateViolation(format!(
+                        "{}: {}",
+                        note_path, details
+                    )));
+                }
+            }
+        }
+
+        let patched = if mapping.is_empty() {
+            format!("{}{}", bom, body)
+        } else if had_block {
+            format!("{}---\n{}---\n{}", bom, yaml, body)
+        } else {
+            format!("{}---\n{}---\n\n{}", bom, yaml, body)
+        };
+        std::fs::write(&full_path, patched).map_err(|e| VaultError::IoError(e.to_string()))?;
+        self.refresh_note(project_id, &vault_path, note_path);
+        Ok(frontmatter)
+    }
+
+    /// Append text to the end of an existing note without rewriting it
+    pub fn append_note(
+        &self,
+        project_id: &str,
+        note_path: &str,
+        text: &str,
+    ) -> Result<(), VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let full_path = resolve_in_vault(&vault_path, note_path)?;
+        if !full_path.is_file() {
+            return Err(VaultError::NoteNotFound(note_path.to_string()));
+        }
 
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&full_path)
+            .map_err(|e| VaultError::IoError(e.to_string()))?;
+        file.write_all(text.as_bytes())
+            .map_err(|e| VaultError::IoError(e.to_string()))?;
+        self.refresh_note(project_id, &vault_path, note_path);
         Ok(())
     }
 
-    /// List all notes in a vault directory
+    /// List a vault directory as a tree walk: at each level directories come
+    /// first, each followed by its contents when recursing, then files, both
+    /// alphabetical. Only `.md` notes are listed unless `include_non_md`.
     pub fn list_notes(
         &self,
         project_id: &str,
         directory: &str,
+        options: &ListOptions,
     ) -> Result<Vec<VaultNoteEntry>, VaultError> {
-        let paths = self.vault_paths.lock().unwrap();
+        let paths = self.vault_paths.lock_recover();
         let vault_path = paths
             .get(project_id)
             .ok_or(VaultError::VaultNotFound(project_id.to_string()))?;
 
-        let dir_path = vault_path.join(directory);
+        let dir_path = resolve_in_vault(vault_path, directory)?;
         if !dir_path.exists() {
             return Ok(Vec::new());
         }
 
+        let ignored = self.ignored(project_id);
         let mut entries = Vec::new();
-        Self::collect_notes(&dir_path, &vault_path, &mut entries)?;
+        Self::collect_entries(&dir_path, vault_path, &ignored, options, &mut entries)?;
 
         Ok(entries)
     }
 
+    fn collect_entries(
+        dir: &Path,
+        vault_root: &Path,
+        ignored: &GlobSet,
+        options: &ListOptions,
+        entries: &mut Vec<VaultNoteEntry>,
+    ) -> Result<(), VaultError> {
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(|e| VaultError::IoError(e.to_string()))? {
+            let path = entry
+                .map_err(|e| VaultError::IoError(e.to_string()))?
+                .path();
+            if is_hidden(&path)
+                || links_outside(&path, vault_root)
+                || is_ignored(&path, vault_root, ignored)
+            {
+                continue;
+            } else if path.is_dir() {
+                dirs.push(path);
+            } else if options.include_non_md || is_note_file(&path) {
+                files.push(path);
+            }
+        }
+        dirs.sort();
+        files.sort();
+
+        for path in dirs {
+            entries.push(VaultNoteEntry {
+                is_dir: true,
+                name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                ..note_entry(&path, vault_root)
+            });
+            if options.recursive {
+                Self::collect_entries(&path, vault_root, ignored, options, entries)?;
+            }
+        }
+        for path in files {
+            let mut entry = note_entry(&path, vault_root);
+            // Attachments keep their extension so `a.png` and `a.pdf` stay distinct
+            if !is_note_file(&path) {
+                entry.name = path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+            }
+            entries.push(entry);
+        }
+
+        Ok(())
+    }
+
+    /// Count the notes `list_notes` would return for a directory, without
+    /// building entries or reading file metadata
+    pub fn count_notes(&self, project_id: &str, directory: &str) -> Result<usize, VaultError> {
+        let paths = self.vault_paths.lock_recover();
+        let vault_path = paths
+            .get(project_id)
+            .ok_or(VaultError::VaultNotFound(project_id.to_string()))?;
+
+        let dir_path = resolve_in_vault(vault_path, directory)?;
+        if !dir_path.exists() {
+            return Ok(0);
+        }
+
+        Self::count_notes_in(&dir_path, vault_path, &self.ignored(project_id))
+    }
+
+    fn count_notes_in(
+        dir: &Path,
+        vault_root: &Path,
+        ignored: &GlobSet,
+    ) -> Result<usize, VaultError> {
+        let read_dir = std::fs::read_dir(dir).map_err(|e| VaultError::IoError(e.to_string()))?;
+
+        let mut count = 0;
+        for entry in read_dir {
+            let entry = entry.map_err(|e| VaultError::IoError(e.to_string()))?;
+            let path = entry.path();
+
+            if is_hidden(&path)
+                || links_outside(&path, vault_root)
+                || is_ignored(&path, vault_root, ignored)
+            {
+                continue;
+            } else if path.is_dir() {
+                count += Self::count_notes_in(&path, vault_root, ignored)?;
+            } else if is_note_file(&path) {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
     fn collect_notes(
         dir: &Path,
         vault_root: &Path,
+        ignored: &GlobSet,
         entries: &mut Vec<VaultNoteEntry>,
     ) -> Result<(), VaultError> {
         let read_dir =
@@ -123,95 +465,2378 @@ impl VaultManager {
             let entry = entry.map_err(|e| VaultError::IoError(e.to_string()))?;
             let path = entry.path();
 
-            if path.is_dir() {
-                Self::collect_notes(&path, vault_root, entries)?;
-            } else if path.extension().map_or(false, |ext| ext == "md") {
-                let relative = path
-                    .strip_prefix(vault_root)
-                    .unwrap_or(&path)
-                    .to_string_lossy()
-                    .to_string();
+            // Skips `.obsidian`, `.git`, and our own `.nebula` snapshots
+            if is_hidden(&path)
+                || links_outside(&path, vault_root)
+                || is_ignored(&path, vault_root, ignored)
+            {
+                continue;
+            } else if path.is_dir() {
+                Self::collect_notes(&path, vault_root, ignored, entries)?;
+            } else if is_note_file(&path) {
+                entries.push(note_entry(&path, vault_root));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// List the templates under the vault's `templates/` folder with the
+    /// `{{variables}}` they declare and the note level they target.
+    /// Templates sharing a name list each other's paths in `duplicates`.
+    pub fn list_templates(&self, project_id: &str) -> Result<Vec<TemplateInfo>, VaultError> {
+        let entries = self.list_notes(project_id, TEMPLATES_DIR, &ListOptions::default())?;
+        let vault_path = self.vault_path(project_id)?;
+
+        let variable_re = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap();
+        let mut templates = Vec::new();
+
+        for entry in entries.into_iter().filter(|e| !e.is_dir) {
+            let content = std::fs::read_to_string(vault_path.join(&entry.path))
+                .map_err(|e| VaultError::IoError(e.to_string()))?;
+
+            let mut variables: Vec<String> = Vec::new();
+            for cap in variable_re.captures_iter(&content) {
+                let name = cap[1].to_string();
+                if !variables.contains(&name) {
+                    variables.push(name);
+                }
+            }
+
+            templates.push(TemplateInfo {
+                name: entry.name,
+                path: entry.path,
+                variables,
+                level: frontmatter_level(&parse_frontmatter(&content).0),
+                duplicates: Vec::new(),
+            });
+        }
+
+        let mut paths_by_name: HashMap<String, Vec<String>> = HashMap::new();
+        for template in &templates {
+            paths_by_name
+                .entry(template.name.clone())
+                .or_default()
+                .push(template.path.clone());
+        }
+        for template in &mut templates {
+            template.duplicates = paths_by_name[&template.name]
+                .iter()
+                .filter(|p| **p != template.path)
+                .cloned()
+                .collect();
+        }
+
+        templates.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.path.cmp(&b.path)));
+        Ok(templates)
+    }
+
+    /// Outbound references from a single note: resolved wikilinks, embeds,
+    /// external URLs, and link targets that don't exist in the vault
+    pub fn note_links(&self, project_id: &str, note_path: &str) -> Result<NoteLinks, VaultError> {
+        let note = self.read_note(project_id, note_path)?;
+        let resolver = LinkResolver::build(&self.vault_path(project_id)?);
+
+        let mut result = NoteLinks {
+            path: note_path.to_string(),
+            links: Vec::new(),
+            embeds: Vec::new(),
+            external: links::extract_urls(&note.content),
+            unresolved: Vec::new(),
+        };
+
+        for link in links::extract_wikilinks(&note.content) {
+            // `[[#heading]]` points back into the same note
+            let resolved = if link.target.is_empty() {
+                Some(note_path)
+            } else {
+                resolver.resolve(&link.target)
+            };
+
+            match resolved {
+                Some(path) => {
+                    let resolved = ResolvedLink {
+                        path: path.to_string(),
+                        link: link.clone(),
+                    };
+                    if link.embed {
+                        result.embeds.push(resolved);
+                    } else {
+                        result.links.push(resolved);
+                    }
+                }
+                None => result.unresolved.push(link),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Find every note that references `target_path` through a wikilink,
+    /// embed, markdown link, or relative path. With `ReferenceMatch::Basename`
+    /// any reference whose file name matches counts, as Obsidian would
+    /// resolve it; with `ReferenceMatch::Path` the reference must resolve to
+    /// exactly this file.
+    pub fn references_to(
+        &self,
+        project_id: &str,
+        target_path: &str,
+        match_by: ReferenceMatch,
+    ) -> Result<Vec<Reference>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let target = target_path.trim_start_matches('/').replace('\\', "/");
+        let target_name = target.rsplit('/').next().unwrap_or(&target).to_lowercase();
+        let target_stem = target_name.trim_end_matches(".md").to_string();
+        let resolver = LinkResolver::build(&vault_path);
+
+        let mut sources = Vec::new();
+        Self::collect_notes(
+            &vault_path,
+            &vault_path,
+            &self.ignored(project_id),
+            &mut sources,
+        )?;
+        sources.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut references = Vec::new();
+        for source in sources {
+            let source_path = source.path.replace('\\', "/");
+            let Ok(content) = std::fs::read_to_string(vault_path.join(&source.path)) else {
+                continue;
+            };
+
+            for link in links::extract_wikilinks(&content) {
+                // `[[#heading]]` points back into the note itself
+                if link.target.is_empty() {
+                    continue;
+                }
+                let matched = match match_by {
+                    ReferenceMatch::Basename => {
+                        let name = link.target.rsplit('/').next().unwrap_or_default();
+                        let name = name.to_lowercase();
+                        name == target_name || name == target_stem
+                    }
+                    ReferenceMatch::Path => resolver.resolve(&link.target) == Some(target.as_str()),
+                };
+                if matched {
+                    references.push(Reference {
+                        path: source_path.clone(),
+                        line: link.line,
+                        kind: if link.embed {
+                            ReferenceKind::Embed
+                        } else {
+                            ReferenceKind::Wikilink
+                        },
+                        target: link.target,
+                    });
+                }
+            }
+
+            for link in links::extract_path_links(&content) {
+                let matched = match match_by {
+                    ReferenceMatch::Basename => {
+                        let name = link.target.rsplit('/').next().unwrap_or_default();
+                        name.to_lowercase() == target_name
+                    }
+                    ReferenceMatch::Path => links::resolve_relative(&source_path, &link.target)
+                        .is_some_and(|p| p == target || format!("{}.md", p) == target),
+                };
+                if matched {
+                    references.push(Reference {
+                        path: source_path.clone(),
+                        line: link.line,
+                        kind: match (link.html, link.embed) {
+                            (true, _) => ReferenceKind::Path,
+                            (false, true) => ReferenceKind::Embed,
+                            (false, false) => ReferenceKind::Markdown,
+                        },
+                        target: link.target,
+                    });
+                }
+            }
+        }
+
+        references.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+        Ok(references)
+    }
+
+    /// Search note titles, bodies, or both for `query`. Files are read line
+    /// by line, so a huge note is never held in memory whole; only its
+    /// frontmatter block is buffered, and only when filtering on it.
+    /// Title matches are reported with line 0.
+    pub fn search(
+        &self,
+        project_id: &str,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<VaultSearchResult>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let fold = |s: &str| {
+            if options.case_sensitive {
+                s.to_string()
+            } else {
+                s.to_lowercase()
+            }
+        };
+        let needle = fold(query);
+        let filter = options
+            .frontmatter_key
+            .as_deref()
+            .map(|key| (key, options.frontmatter_value.as_deref().map(&fold)));
+
+        let mut notes = Vec::new();
+        Self::collect_notes(
+            &vault_path,
+            &vault_path,
+            &self.ignored(project_id),
+            &mut notes,
+        )?;
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut results = Vec::new();
+        for note in notes {
+            if results.len() >= options.limit {
+                break;
+            }
+            let Ok(file) = std::fs::File::open(vault_path.join(&note.path)) else {
+                continue;
+            };
+            let mut lines = NoteLines::new(std::io::BufReader::new(file));
+
+            // The frontmatter block is consumed here so body matches skip it
+            let frontmatter = lines.frontmatter();
+            if let Some((key, value)) = &filter {
+                let matched = frontmatter.get(*key).is_some_and(|v| match value {
+                    None => true,
+                    Some(value) => frontmatter_contains(v, value, &fold),
+                });
+                if !matched {
+                    continue;
+                }
+            }
+
+            let path = note.path.replace('\\', "/");
+            if needle.is_empty() {
+                results.push(VaultSearchResult {
+                    path,
+                    line: 0,
+                    snippet: note.name,
+                });
+                continue;
+            }
 
-                entries.push(VaultNoteEntry {
-                    path: relative,
-                    name: path
-                        .file_stem()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string(),
-                    last_modified: std::fs::metadata(&path)
-                        .ok()
-                        .and_then(|m| m.modified().ok())
-                        .map(|t| {
-                            chrono::DateTime::<chrono::Utc>::from(t)
-                                .to_rfc3339()
-                        })
-                        .unwrap_or_default(),
+            if options.scope != SearchScope::Body && fold(&note.name).contains(&needle) {
+                results.push(VaultSearchResult {
+                    path: path.clone(),
+                    line: 0,
+                    snippet: note.name.clone(),
                 });
             }
+            if options.scope == SearchScope::Title {
+                continue;
+            }
+            while let Some((number, line)) = lines.next_line() {
+                if results.len() >= options.limit {
+                    break;
+                }
+                let folded = fold(&line);
+                if let Some(at) = folded.find(&needle) {
+                    results.push(VaultSearchResult {
+                        path: path.clone(),
+                        line: number,
+                        snippet: snippet_around(&line, &folded, at, needle.len()),
+                    });
+                }
+            }
         }
 
-        Ok(())
+        Ok(results)
     }
 
-    /// Build an Obsidian deep link URL
-    pub fn build_deep_link(&self, vault_name: &str, note_path: &str) -> String {
-        let encoded_vault = urlencoding::encode(vault_name);
-        let encoded_file = urlencoding::encode(note_path);
-        format!(
-            "obsidian://open?vault={}&file={}",
-            encoded_vault, encoded_file
-        )
+    /// Notes whose wikilinks or embeds resolve to `note_path`
+    pub fn backlinks(
+        &self,
+        project_id: &str,
+        note_path: &str,
+    ) -> Result<Vec<VaultNoteEntry>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let target = note_path.trim_start_matches('/').replace('\\', "/");
+
+        self.with_link_index(project_id, &vault_path, |index| {
+            index
+                .backlinks(&target)
+                .map(|source| note_entry(&vault_path.join(source), &vault_path))
+                .collect()
+        })
+    }
+
+    /// Wikilinks anywhere in the vault whose target doesn't exist
+    pub fn broken_links(&self, project_id: &str) -> Result<Vec<BrokenLink>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        self.with_link_index(project_id, &vault_path, LinkIndex::broken)
+    }
+
+    /// Render a note read from this project's vault for preview, resolving
+    /// its wikilinks against the vault's link index
+    pub fn render_note(
+        &self,
+        project_id: &str,
+        note: VaultNote,
+    ) -> Result<RenderedNote, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let (html, toc) = self.with_link_index(project_id, &vault_path, |index| {
+            render::render_markdown(&note.content, |target| {
+                index.resolve(target).map(str::to_string)
+            })
+        })?;
+        Ok(RenderedNote {
+            path: note.path,
+            name: note.name,
+            html,
+            frontmatter: note.frontmatter,
+            toc,
+        })
     }
-}
 
-fn parse_frontmatter(content: &str) -> (HashMap<String, serde_json::Value>, String) {
-    if content.starts_with("---") {
-        if let Some(end) = content[3..].find("---") {
-            let fm_str = &content[3..end + 3];
-            let body = &content[end + 6..];
+    /// Walk the vault and report problems without changing anything:
+    /// unparseable frontmatter, broken wikilinks, notes missing what their
+    /// level's template requires, notes with no links in or out, and note
+    /// names shared by several notes, which makes `[[name]]` ambiguous.
+    /// Templates and workstream transcripts aren't expected to be linked.
+    pub fn check_health(&self, project_id: &str) -> Result<VaultHealthReport, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let notes = Self::note_paths(&vault_path, &self.ignored(project_id))?;
+        let registry = TemplateRegistry::load(&vault_path);
+        let mut issues = Vec::new();
+        let mut issue = |path: &str, kind, severity, message: String| {
+            issues.push(HealthIssue {
+                path: path.to_string(),
+                kind,
+                severity,
+                message,
+            })
+        };
 
-            // Parse as JSON (simplified — production would use YAML parser)
-            let frontmatter: HashMap<String, serde_json::Value> =
-                serde_json::from_str(fm_str).unwrap_or_default();
+        let mut by_name: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for path in &notes {
+            let name = Path::new(path).file_stem().unwrap_or_default();
+            by_name
+                .entry(name.to_string_lossy().to_lowercase())
+                .or_default()
+                .push(path);
+
+            let Ok(content) = std::fs::read_to_string(vault_path.join(path)) else {
+                continue;
+            };
+            if let Some(problem) = frontmatter_problem(&content) {
+                issue(
+                    path,
+                    HealthIssueKind::MalformedFrontmatter,
+                    HealthSeverity::Error,
+                    problem,
+                );
+                continue;
+            }
+            if is_template_path(path) {
+                continue;
+            }
+            let (frontmatter, body) = parse_frontmatter(&content);
+            let violations = self
+                .required_level(project_id, path, Some(&frontmatter))
+                .and_then(|level| registry.violations(level, &frontmatter, &body));
+            if let Some(details) = violations {
+                issue(
+                    path,
+                    HealthIssueKind::TemplateViolation,
+                    HealthSeverity::Error,
+                    details,
+                );
+            }
+        }
 
-            return (frontmatter, body.trim().to_string());
+        for paths in by_name.values().filter(|paths| paths.len() > 1) {
+            for path in paths {
+                let others: Vec<&str> = paths.iter().copied().filter(|p| p != path).collect();
+                issue(
+                    path,
+                    HealthIssueKind::DuplicateName,
+                    HealthSeverity::Warning,
+                    format!("Shares its name with {}", others.join(", ")),
+                );
+            }
         }
+
+        let transcripts = format!("{}/", crate::transcript::WORKSTREAMS_DIR);
+        self.with_link_index(project_id, &vault_path, |index| {
+            for link in index.broken() {
+                issue(
+                    &link.source,
+                    HealthIssueKind::BrokenLink,
+                    HealthSeverity::Warning,
+                    format!("Line {}: [[{}]] matches no file", link.line, link.target),
+                );
+            }
+            let orphans = notes.iter().filter(|path| {
+                !is_template_path(path)
+                    && !path.starts_with(&transcripts)
+                    && index.backlinks(path).next().is_none()
+                    && !index.links_out(path)
+            });
+            for path in orphans {
+                issue(
+                    path,
+                    HealthIssueKind::Orphan,
+                    HealthSeverity::Info,
+                    "No notes link here and it links to none".to_string(),
+                );
+            }
+        })?;
+
+        issues.sort_by(|a, b| a.path.cmp(&b.path).then(a.kind.cmp(&b.kind)));
+        Ok(VaultHealthReport {
+            project_id: project_id.to_string(),
+            checked_at: chrono::Utc::now().to_rfc3339(),
+            notes_checked: notes.len(),
+            issues,
+        })
     }
-    (HashMap::new(), content.to_string())
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VaultNote {
-    pub path: String,
-    pub name: String,
-    pub content: String,
-    pub frontmatter: HashMap<String, serde_json::Value>,
-    pub last_modified: String,
-}
+    fn with_link_index<T>(
+        &self,
+        project_id: &str,
+        vault_path: &Path,
+        f: impl FnOnce(&LinkIndex) -> T,
+    ) -> Result<T, VaultError> {
+        let mut indexes = self.link_indexes.lock_recover();
+        if !indexes.contains_key(project_id) {
+            let paths = Self::note_paths(vault_path, &self.ignored(project_id))?;
+            indexes.insert(project_id.to_string(), LinkIndex::build(vault_path, &paths));
+        }
+        Ok(f(&indexes[project_id]))
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VaultNoteEntry {
-    pub path: String,
-    pub name: String,
-    pub last_modified: String,
-}
+    /// Bring caches up to date after a note changed outside Nebula
+    pub(crate) fn apply_external_change(&self, project_id: &str, note_path: &str) {
+        if let Ok(vault_path) = self.vault_path(project_id) {
+            self.refresh_note(project_id, &vault_path, note_path);
+        }
+    }
 
-#[derive(Debug, thiserror::Error)]
-pub enum VaultError {
-    #[error("Vault not found for project: {0}")]
-    VaultNotFound(String),
-    #[error("Note not found: {0}")]
-    NoteNotFound(String),
-    #[error("IO error: {0}")]
-    IoError(String),
-}
+    /// Re-index one note's links, tags, and text after it changed, was
+    /// created, or was removed
+    fn refresh_note(&self, project_id: &str, vault_path: &Path, note_path: &str) {
+        let note_path = note_path.trim_start_matches('/').replace('\\', "/");
+        if is_ignored(
+            &vault_path.join(&note_path),
+            vault_path,
+            &self.ignored(project_id),
+        ) {
+            return;
+        }
+        if let Some(index) = self.link_indexes.lock_recover().get_mut(project_id) {
+            index.update(&note_path);
+        }
+        // Attachments are only link targets
+        if !is_note_file(Path::new(&note_path)) {
+            return;
+        }
+        if let Some(index) = self.tag_indexes.lock_recover().get_mut(project_id) {
+            index.update(vault_path, &note_path);
+        }
+        if let Some(index) = self.search_indexes.lock_recover().get_mut(project_id) {
+            index.update(&note_path);
+        }
+    }
 
-impl Serialize for VaultError {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&self.to_string())
+    /// Drop every cached index, for changes that add or remove notes wholesale.
+    /// The stored search index is kept and reconciled when next loaded.
+    fn invalidate_indexes(&self, project_id: &str) {
+        self.link_indexes.lock_recover().remove(project_id);
+        self.tag_indexes.lock_recover().remove(project_id);
+        self.search_indexes.lock_recover().remove(project_id);
+    }
+
+    /// Sizes and build times of the vault's cached indexes
+    pub fn cache_stats(&self, project_id: &str) -> Result<VaultCacheStats, VaultError> {
+        self.vault_path(project_id)?;
+        Ok(VaultCacheStats {
+            project_id: project_id.to_string(),
+            links: self
+                .link_indexes
+                .lock_recover()
+                .get(project_id)
+                .map(LinkIndex::stats),
+            tags: self
+                .tag_indexes
+                .lock_recover()
+                .get(project_id)
+                .map(TagIndex::stats),
+            search: self
+                .search_indexes
+                .lock_recover()
+                .get(project_id)
+                .map(SearchIndex::stats),
+        })
+    }
+
+    /// Notes matching any word of `query`, ranked by relevance, with a
+    /// highlighted snippet from each
+    pub fn search_ranked(
+        &self,
+        project_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<RankedSearchResult>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let mut indexes = self.search_indexes.lock_recover();
+        if !indexes.contains_key(project_id) {
+            let notes = Self::note_paths(&vault_path, &self.ignored(project_id))?;
+            indexes.insert(
+                project_id.to_string(),
+                SearchIndex::open(&vault_path, &notes),
+            );
+        }
+        Ok(indexes[project_id].search(query, limit))
+    }
+
+    /// Rebuild the vault's search index from scratch, returning how many
+    /// notes it holds
+    pub fn reindex(&self, project_id: &str) -> Result<usize, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let notes = Self::note_paths(&vault_path, &self.ignored(project_id))?;
+        let index = SearchIndex::rebuild(&vault_path, &notes)
+            .map_err(|e| VaultError::IoError(e.to_string()))?;
+        let count = index.len();
+        self.search_indexes
+            .lock_recover()
+            .insert(project_id.to_string(), index);
+        Ok(count)
+    }
+
+    /// Stream `data` into `attachments/<path>`. It goes to a hidden temp file
+    /// first, so a failed write never leaves a partial attachment behind.
+    pub fn write_attachment(
+        &self,
+        project_id: &str,
+        path: &str,
+        mut data: impl std::io::Read,
+    ) -> Result<AttachmentInfo, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let (relative, full_path) = attachment_path(&vault_path, path)?;
+        let (Some(parent), Some(file_name)) = (full_path.parent(), full_path.file_name()) else {
+            return Err(VaultError::InvalidPath(path.to_string()));
+        };
+        std::fs::create_dir_all(parent).map_err(|e| VaultError::IoError(e.to_string()))?;
+
+        let tmp = parent.join(format!(".{}.tmp", file_name.to_string_lossy()));
+        let written = std::fs::File::create(&tmp).and_then(|mut file| {
+            std::io::copy(&mut data, &mut file)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|()| std::fs::rename(&tmp, &full_path)) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(VaultError::IoError(e.to_string()));
+        }
+
+        self.refresh_note(project_id, &vault_path, &relative);
+        attachment_info(&full_path, relative)
+    }
+
+    /// The contents of `attachments/<path>`
+    pub fn read_attachment(&self, project_id: &str, path: &str) -> Result<Vec<u8>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let (relative, full_path) = attachment_path(&vault_path, path)?;
+        if !full_path.is_file() {
+            return Err(VaultError::NoteNotFound(relative));
+        }
+        std::fs::read(&full_path).map_err(|e| VaultError::IoError(e.to_string()))
+    }
+
+    /// Size and detected MIME type of `attachments/<path>`
+    pub fn attachment_info(
+        &self,
+        project_id: &str,
+        path: &str,
+    ) -> Result<AttachmentInfo, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let (relative, full_path) = attachment_path(&vault_path, path)?;
+        if !full_path.is_file() {
+            return Err(VaultError::NoteNotFound(relative));
+        }
+        attachment_info(&full_path, relative)
+    }
+
+    /// Every note in the vault with its raw content, for a project bundle.
+    /// Notes that aren't valid UTF-8 are left out.
+    pub fn export_notes(&self, project_id: &str) -> Result<Vec<BundledNote>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let mut notes: Vec<BundledNote> = Self::note_paths(&vault_path, &GlobSet::empty())?
+            .into_iter()
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(vault_path.join(&path)).ok()?;
+                Some(BundledNote { path, content })
+            })
+            .collect();
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(notes)
+    }
+
+    /// Write bundled notes into the vault as they are. Every path is checked
+    /// before anything is written, so a note that already exists or escapes
+    /// the vault leaves the vault untouched, and a failed write removes the
+    /// notes already written. The import can be undone with `undo_import`
+    /// if a later step fails.
+    pub fn import_notes(
+        &self,
+        project_id: &str,
+        notes: &[BundledNote],
+    ) -> Result<NoteImport, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let mut targets = Vec::with_capacity(notes.len());
+        let mut created_dirs = BTreeSet::new();
+        for note in notes {
+            let full_path = resolve_in_vault(&vault_path, &note.path)?;
+            if full_path.exists() {
+                return Err(VaultError::NoteExists(note.path.clone()));
+            }
+            created_dirs.extend(missing_dirs(&full_path));
+            targets.push(full_path);
+        }
+
+        let mut import = NoteImport {
+            written: Vec::with_capacity(notes.len()),
+            created_dirs,
+        };
+        for (note, full_path) in notes.iter().zip(targets) {
+            // Recorded first, so a write that fails partway is removed too
+            import.written.push(note.path.clone());
+            let written = full_path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(&full_path, &note.content));
+            if let Err(e) = written {
+                self.undo_import(project_id, import);
+                return Err(VaultError::IoError(e.to_string()));
+            }
+            self.refresh_note(project_id, &vault_path, &note.path);
+        }
+        Ok(import)
+    }
+
+    /// Remove the notes an import wrote and the directories it created
+    pub fn undo_import(&self, project_id: &str, import: NoteImport) {
+        let Ok(vault_path) = self.vault_path(project_id) else {
+            return;
+        };
+        for path in import.written.iter().rev() {
+            match std::fs::remove_file(vault_path.join(path)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    tracing::error!("Failed to remove imported note {}: {}", path, e);
+                }
+                _ => {}
+            }
+            self.refresh_note(project_id, &vault_path, path);
+        }
+        remove_created_dirs(&import.created_dirs);
+    }
+
+    /// Write several notes as one all-or-nothing batch. Each note's prior
+    /// content (or absence) is captured first; if any write fails, the notes
+    /// already written, and the failing one, are restored or removed in
+    /// reverse order, directories the batch created are removed, and the
+    /// failing note is reported. A path may appear only once per batch.
+    pub fn write_notes(
+        &self,
+        project_id: &str,
+        notes: &[NoteWrite],
+    ) -> Result<(), BatchWriteError> {
+        if notes.is_empty() {
+            return Ok(());
+        }
+        let fail = |index: usize, error: VaultError| BatchWriteError {
+            index,
+            path: notes[index].path.clone(),
+            error,
+            rollback_failures: Vec::new(),
+        };
+        let vault_path = self.vault_path(project_id).map_err(|e| fail(0, e))?;
+
+        let mut seen = HashSet::new();
+        let mut prior = Vec::with_capacity(notes.len());
+        let mut created_dirs = BTreeSet::new();
+        for (index, note) in notes.iter().enumerate() {
+            let full_path =
+                resolve_in_vault(&vault_path, &note.path).map_err(|e| fail(index, e))?;
+            if !seen.insert(full_path.clone()) {
+                return Err(fail(
+                    index,
+                    VaultError::InvalidPath(format!("{} appears twice in the batch", note.path)),
+                ));
+            }
+            created_dirs.extend(missing_dirs(&full_path));
+            let content = match std::fs::read(&full_path) {
+                Ok(bytes) => Some(bytes),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(fail(index, VaultError::IoError(e.to_string()))),
+            };
+            prior.push((full_path, content));
+        }
+
+        for (index, note) in notes.iter().enumerate() {
+            let written = self.write_note(
+                project_id,
+                &note.path,
+                &note.frontmatter,
+                &note.content,
+                None,
+                note.expected_hash.as_deref(),
+            );
+            let Err(error) = written else {
+                continue;
+            };
+
+            // The failing note is rolled back too, since its write may have
+            // stopped partway through
+            let mut failure = fail(index, error);
+            for (note, (full_path, content)) in notes.iter().zip(&prior).take(index + 1).rev() {
+                let restored = match content {
+                    Some(bytes) => std::fs::write(full_path, bytes),
+                    None => match std::fs::remove_file(full_path) {
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                        removed => removed,
+                    },
+                };
+                if let Err(e) = restored {
+                    tracing::error!("Failed to roll back {}: {}", note.path, e);
+                    failure.rollback_failures.push(note.path.clone());
+                }
+                self.refresh_note(project_id, &vault_path, &note.path);
+            }
+            remove_created_dirs(&created_dirs);
+            return Err(failure);
+        }
+        Ok(())
+    }
+
+    /// Every note in the vault not `ignored`, as `/`-separated vault-relative
+    /// paths
+    fn note_paths(vault_path: &Path, ignored: &GlobSet) -> Result<Vec<String>, VaultError> {
+        let mut notes = Vec::new();
+        Self::collect_notes(vault_path, vault_path, ignored, &mut notes)?;
+        Ok(notes
+            .into_iter()
+            .map(|n| n.path.replace('\\', "/"))
+            .collect())
+    }
+
+    /// Notes tagged `tag` or a tag nested under it, case-insensitively
+    pub fn notes_by_tag(
+        &self,
+        project_id: &str,
+        tag: &str,
+    ) -> Result<Vec<VaultNoteEntry>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        self.with_tag_index(project_id, &vault_path, |index| {
+            index
+                .notes_with(tag)
+                .into_iter()
+                .map(|note| note_entry(&vault_path.join(note), &vault_path))
+                .collect()
+        })
+    }
+
+    /// Every tag in the vault with the number of notes carrying it
+    pub fn all_tags(&self, project_id: &str) -> Result<Vec<TagCount>, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        self.with_tag_index(project_id, &vault_path, TagIndex::counts)
+    }
+
+    fn with_tag_index<T>(
+        &self,
+        project_id: &str,
+        vault_path: &Path,
+        f: impl FnOnce(&TagIndex) -> T,
+    ) -> Result<T, VaultError> {
+        let mut indexes = self.tag_indexes.lock_recover();
+        if !indexes.contains_key(project_id) {
+            let paths = Self::note_paths(vault_path, &self.ignored(project_id))?;
+            indexes.insert(project_id.to_string(), TagIndex::build(vault_path, &paths));
+        }
+        Ok(f(&indexes[project_id]))
+    }
+
+    /// Move a note into the vault's `.trash/`, keeping its relative path so it
+    /// can be restored. A note already trashed under the same path is kept by
+    /// giving the new one a `~<timestamp>` suffix, plus `-<n>` if that's taken.
+    pub fn delete_note(&self, project_id: &str, note_path: &str) -> Result<String, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let relative = note_path.trim_start_matches('/').replace('\\', "/");
+        let source = resolve_in_vault(&vault_path, &relative)?;
+        if !source.is_file() {
+            return Err(VaultError::NoteNotFound(note_path.to_string()));
+        }
+
+        let mut trash_path = format!("{}/{}", TRASH_DIR, relative);
+        if vault_path.join(&trash_path).exists() {
+            // Deletes within the same second share a stamp, so a counter
+            // keeps later ones from overwriting the earlier copies
+            let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S").to_string();
+            let (stem, ext) = match trash_path.rsplit_once('.') {
+                Some((stem, ext)) if !ext.contains('/') => (stem.to_string(), format!(".{}", ext)),
+                _ => (trash_path.clone(), String::new()),
+            };
+            trash_path = format!("{}~{}{}", stem, stamp, ext);
+            let mut counter = 1;
+            while vault_path.join(&trash_path).exists() {
+                counter += 1;
+                trash_path = format!("{}~{}-{}{}", stem, stamp, counter, ext);
+            }
+        }
+
+        move_file(&source, &vault_path.join(&trash_path))?;
+        self.refresh_note(project_id, &vault_path, &relative);
+        Ok(trash_path)
+    }
+
+    /// Move a trashed note back to where it was deleted from, returning that
+    /// path. Fails with `NoteExists` if a note has since been created there.
+    pub fn restore_note(&self, project_id: &str, trash_path: &str) -> Result<String, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let trash_path = trash_path.trim_start_matches('/').replace('\\', "/");
+        let source = resolve_in_vault(&vault_path, &trash_path)?;
+        let original = trash_path
+            .strip_prefix(TRASH_DIR)
+            .and_then(|p| p.strip_prefix('/'))
+            .filter(|_| source.is_file())
+            .ok_or_else(|| VaultError::NoteNotFound(trash_path.clone()))?;
+        let original = TRASH_STAMP_RE.replace(original, "$ext").into_owned();
+
+        let target = resolve_in_vault(&vault_path, &original)?;
+        if target.exists() {
+            return Err(VaultError::NoteExists(original));
+        }
+
+        move_file(&source, &target)?;
+        self.refresh_note(project_id, &vault_path, &original);
+        Ok(original)
+    }
+
+    /// Move or rename a note, then repoint every wikilink that resolved to the
+    /// old path. Links keep their style: a bare `[[name]]` becomes the new
+    /// file name, a `[[folder/name]]` the new path.
+    pub fn move_note(
+        &self,
+        project_id: &str,
+        from_path: &str,
+        to_path: &str,
+        overwrite: bool,
+    ) -> Result<MoveReport, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let from = from_path.trim_start_matches('/').replace('\\', "/");
+        let to = to_path.trim_start_matches('/').replace('\\', "/");
+        let source = resolve_in_vault(&vault_path, &from)?;
+        let target = resolve_in_vault(&vault_path, &to)?;
+        if !source.is_file() {
+            return Err(VaultError::NoteNotFound(from));
+        }
+        if target.exists() && !overwrite {
+            return Err(VaultError::NoteExists(to));
+        }
+
+        // Resolve links against the vault as it was before the move
+        let resolver = LinkResolver::build(&vault_path);
+        move_file(&source, &target)?;
+        self.refresh_note(project_id, &vault_path, &from);
+        self.refresh_note(project_id, &vault_path, &to);
+
+        let new_stem = to.strip_suffix(".md").unwrap_or(&to);
+        let new_name = new_stem.rsplit('/').next().unwrap_or(new_stem);
+
+        let mut notes = Vec::new();
+        Self::collect_notes(
+            &vault_path,
+            &vault_path,
+            &self.ignored(project_id),
+            &mut notes,
+        )?;
+        let mut updated = Vec::new();
+        for note in notes {
+            let path = vault_path.join(&note.path);
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let (rewritten, changed) = links::rewrite_wikilinks(&content, |link| {
+                if resolver.resolve(link) != Some(from.as_str()) {
+                    return None;
+                }
+                let base = if link.contains('/') {
+                    new_stem
+                } else {
+                    new_name
+                };
+                Some(if link.ends_with(".md") {
+                    format!("{}.md", base)
+                } else {
+                    base.to_string()
+                })
+            });
+            if changed > 0 {
+                std::fs::write(&path, rewritten).map_err(|e| VaultError::IoError(e.to_string()))?;
+                let relative = note.path.replace('\\', "/");
+                self.refresh_note(project_id, &vault_path, &relative);
+                updated.push(relative);
+            }
+        }
+        updated.sort();
+
+        Ok(MoveReport { from, to, updated })
+    }
+
+    /// Copy every note in the vault into `.nebula/snapshots/<id>` so later
+    /// changes can be diffed against this point in time
+    pub fn create_snapshot(
+        &self,
+        project_id: &str,
+        label: Option<&str>,
+    ) -> Result<SnapshotInfo, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let io = |e: std::io::Error| VaultError::IoError(e.to_string());
+
+        let mut notes = Vec::new();
+        Self::collect_notes(&vault_path, &vault_path, &GlobSet::empty(), &mut notes)?;
+
+        let info = SnapshotInfo {
+            id: Uuid::new_v4().to_string(),
+            label: label.map(str::to_string),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            note_count: notes.len(),
+        };
+        let snapshot_dir = vault_path.join(SNAPSHOTS_DIR).join(&info.id);
+        std::fs::create_dir_all(&snapshot_dir).map_err(io)?;
+
+        for note in &notes {
+            let dest = snapshot_dir.join(&note.path);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent).map_err(io)?;
+            }
+            std::fs::copy(vault_path.join(&note.path), dest).map_err(io)?;
+        }
+
+        let manifest =
+            serde_json::to_string_pretty(&info).map_err(|e| VaultError::IoError(e.to_string()))?;
+        std::fs::write(snapshot_dir.join(SNAPSHOT_MANIFEST), manifest).map_err(io)?;
+
+        Ok(info)
+    }
+
+    /// Snapshots taken for this vault, newest first
+    pub fn list_snapshots(&self, project_id: &str) -> Result<Vec<SnapshotInfo>, VaultError> {
+        let snapshots_dir = self.vault_path(project_id)?.join(SNAPSHOTS_DIR);
+        let Ok(read_dir) = std::fs::read_dir(&snapshots_dir) else {
+            return Ok(Vec::new());
+        };
+
+        let mut snapshots: Vec<SnapshotInfo> = read_dir
+            .filter_map(|e| e.ok())
+            .filter_map(|e| std::fs::read_to_string(e.path().join(SNAPSHOT_MANIFEST)).ok())
+            .filter_map(|m| serde_json::from_str(&m).ok())
+            .collect();
+        snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(snapshots)
+    }
+
+    /// Compare the vault's notes against a snapshot. Notes count as modified
+    /// only when their content differs, so touch-only changes don't show up.
+    pub fn diff_against_snapshot(
+        &self,
+        project_id: &str,
+        snapshot_id: &str,
+    ) -> Result<VaultDiff, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        let valid_id = !snapshot_id.is_empty()
+            && snapshot_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        let snapshot_dir = vault_path.join(SNAPSHOTS_DIR).join(snapshot_id);
+        if !valid_id || !snapshot_dir.join(SNAPSHOT_MANIFEST).is_file() {
+            return Err(VaultError::SnapshotNotFound(snapshot_id.to_string()));
+        }
+
+        let mut current = Vec::new();
+        Self::collect_notes(&vault_path, &vault_path, &GlobSet::empty(), &mut current)?;
+        let mut previous = Vec::new();
+        Self::collect_notes(
+            &snapshot_dir,
+            &snapshot_dir,
+            &GlobSet::empty(),
+            &mut previous,
+        )?;
+
+        let previous: HashMap<String, String> = previous
+            .into_iter()
+            .map(|n| (n.path.clone(), read_lossy(&snapshot_dir.join(&n.path))))
+            .collect();
+
+        let mut diff = VaultDiff {
+            snapshot_id: snapshot_id.to_string(),
+            added: Vec::new(),
+            deleted: Vec::new(),
+            modified: Vec::new(),
+        };
+
+        for note in &current {
+            let content = read_lossy(&vault_path.join(&note.path));
+            match previous.get(&note.path) {
+                None => diff.added.push(NoteDiff::between(&note.path, "", &content)),
+                Some(old) if *old != content => diff
+                    .modified
+                    .push(NoteDiff::between(&note.path, old, &content)),
+                Some(_) => {}
+            }
+        }
+
+        let current_paths: HashSet<&str> = current.iter().map(|n| n.path.as_str()).collect();
+        for (path, old) in &previous {
+            if !current_paths.contains(path.as_str()) {
+                diff.deleted.push(NoteDiff::between(path, old, ""));
+            }
+        }
+
+        diff.added.sort_by(|a, b| a.path.cmp(&b.path));
+        diff.deleted.sort_by(|a, b| a.path.cmp(&b.path));
+        diff.modified.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(diff)
+    }
+
+    /// The project's path-to-level rules, in match order
+    pub fn level_rules(&self, project_id: &str) -> Vec<LevelRule> {
+        let maps = self.level_maps.lock_recover();
+        maps.get(project_id)
+            .map(|m| m.rules.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the project's path-to-level rules; the first matching glob wins
+    pub fn set_level_rules(
+        &self,
+        project_id: &str,
+        rules: Vec<LevelRule>,
+    ) -> Result<(), VaultError> {
+        let matchers = rules
+            .iter()
+            .map(|rule| {
+                GlobBuilder::new(&rule.pattern)
+                    .literal_separator(true)
+                    .build()
+                    .map(|g| g.compile_matcher())
+                    .map_err(|e| VaultError::InvalidPattern(format!("{}: {}", rule.pattern, e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut maps = self.level_maps.lock_recover();
+        maps.insert(project_id.to_string(), LevelMap { rules, matchers });
+        Ok(())
+    }
+
+    /// Replace the globs of vault paths that walks and listings skip. A
+    /// matching directory hides everything under it.
+    pub fn set_ignore_patterns(
+        &self,
+        project_id: &str,
+        patterns: &[String],
+    ) -> Result<(), VaultError> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(
+                GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| VaultError::InvalidPattern(format!("{}: {}", pattern, e)))?,
+            );
+        }
+        let set = builder
+            .build()
+            .map_err(|e| VaultError::InvalidPattern(e.to_string()))?;
+
+        self.ignore_sets
+            .lock_recover()
+            .insert(project_id.to_string(), set);
+        self.invalidate_indexes(project_id);
+        Ok(())
+    }
+
+    fn ignored(&self, project_id: &str) -> GlobSet {
+        self.ignore_sets
+            .lock_recover()
+            .get(project_id)
+            .cloned()
+            .unwrap_or_else(GlobSet::empty)
+    }
+
+    /// The template level a note must satisfy. A `level` (or `type: level-N`)
+    /// frontmatter value overrides the path-based mapping.
+    pub fn required_level(
+        &self,
+        project_id: &str,
+        note_path: &str,
+        frontmatter: Option<&HashMap<String, serde_json::Value>>,
+    ) -> Option<u8> {
+        if let Some(level) = frontmatter.and_then(frontmatter_level) {
+            return Some(level);
+        }
+
+        let maps = self.level_maps.lock_recover();
+        let map = maps.get(project_id)?;
+        map.matchers
+            .iter()
+            .position(|m| m.is_match(note_path))
+            .map(|idx| map.rules[idx].level)
+    }
+
+    /// Number of projects with a registered vault
+    pub fn vault_count(&self) -> usize {
+        self.vault_paths.lock_recover().len()
+    }
+
+    /// Locks left poisoned by a panicking holder
+    pub fn poisoned_locks(&self) -> Vec<&'static str> {
+        sync::poisoned([
+            ("vault_paths", self.vault_paths.is_poisoned()),
+            ("level_maps", self.level_maps.is_poisoned()),
+            ("ignore_sets", self.ignore_sets.is_poisoned()),
+            ("link_indexes", self.link_indexes.is_poisoned()),
+            ("tag_indexes", self.tag_indexes.is_poisoned()),
+            ("search_indexes", self.search_indexes.is_poisoned()),
+        ])
+    }
+
+    /// Root directory of the project's vault, if one is registered
+    pub fn vault_root(&self, project_id: &str) -> Option<PathBuf> {
+        self.vault_path(project_id).ok()
+    }
+
+    fn vault_path(&self, project_id: &str) -> Result<PathBuf, VaultError> {
+        let paths = self.vault_paths.lock_recover();
+        paths
+            .get(project_id)
+            .cloned()
+            .ok_or(VaultError::VaultNotFound(project_id.to_string()))
+    }
+
+    /// `obsidian://` link to a note in the project's vault. Obsidian knows a
+    /// vault by its folder name, so that is taken from the registered path.
+    pub fn note_deep_link(&self, project_id: &str, note_path: &str) -> Result<String, VaultError> {
+        let vault_path = self.vault_path(project_id)?;
+        resolve_in_vault(&vault_path, note_path)?;
+        let vault_name = vault_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| VaultError::InvalidVaultPath(vault_path.display().to_string()))?;
+        let note_path = note_path.trim_start_matches('/').replace('\\', "/");
+        Ok(self.build_deep_link(&vault_name, &note_path))
+    }
+
+    /// Build an Obsidian deep link URL
+    pub fn build_deep_link(&self, vault_name: &str, note_path: &str) -> String {
+        let encoded_vault = urlencoding::encode(vault_name);
+        let encoded_file = urlencoding::encode(note_path);
+        format!(
+            "obsidian://open?vault={}&file={}",
+            encoded_vault, encoded_file
+        )
+    }
+}
+
+const TEMPLATES_DIR: &str = "templates";
+/// Where images, PDFs, and other files notes embed are stored
+const ATTACHMENTS_DIR: &str = "attachments";
+const SNAPSHOTS_DIR: &str = ".nebula/snapshots";
+const SNAPSHOT_MANIFEST: &str = "snapshot.json";
+const TRASH_DIR: &str = ".trash";
+
+/// The `~<timestamp>` a trashed note gets when its trash path was taken
+static TRASH_STAMP_RE: std::sync::LazyLock<Regex> =
+    std::sync::LazyLock::new(|| Regex::new(r"~\d{8}T\d{6}(-\d+)?(?P<ext>\.[^./]*)?$").unwrap());
+
+/// Read a level from `level: 1`, `level: "level-1"`, or `type: level-1`
+fn frontmatter_level(frontmatter: &HashMap<String, serde_json::Value>) -> Option<u8> {
+    fn parse(value: &serde_json::Value) -> Option<u8> {
+        match value {
+            serde_json::Value::Number(n) => n.as_u64().and_then(|n| u8::try_from(n).ok()),
+            serde_json::Value::String(s) => {
+                s.strip_prefix("level-").unwrap_or(s).trim().parse().ok()
+            }
+            _ => None,
+        }
+    }
+
+    frontmatter.get("level").and_then(parse).or_else(|| {
+        frontmatter
+            .get("type")
+            .and_then(|v| v.as_str())
+            .and_then(|t| t.strip_prefix("level-"))
+            .and_then(|n| n.parse().ok())
+    })
+}
+
+/// Templates and the template config note are exempt from template checks
+fn is_template_path(note_path: &str) -> bool {
+    Path::new(note_path).starts_with(TEMPLATES_DIR)
+        || note_path.replace('\\', "/") == TEMPLATE_CONFIG_NOTE
+}
+
+/// Join a vault-relative path onto the vault root, refusing absolute paths,
+/// `..` components, and symlinks that lead outside the vault
+fn resolve_in_vault(vault_root: &Path, relative: &str) -> Result<PathBuf, VaultError> {
+    let invalid = || VaultError::InvalidPath(relative.to_string());
+    let normalized = relative.replace('\\', "/");
+    let relative_path = Path::new(&normalized);
+    if relative_path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(invalid());
+    }
+
+    // The target may not exist yet, so check the deepest part of it that does.
+    // A dangling symlink fails to canonicalize and is refused too.
+    let full_path = vault_root.join(relative_path);
+    let existing = full_path
+        .ancestors()
+        .find(|p| p.symlink_metadata().is_ok())
+        .unwrap_or(vault_root);
+    match existing.canonicalize() {
+        Ok(canonical) if canonical.starts_with(vault_root) => Ok(full_path),
+        _ => Err(invalid()),
+    }
+}
+
+/// A symlink inside the vault whose target lies outside it
+fn links_outside(path: &Path, vault_root: &Path) -> bool {
+    path.is_symlink()
+        && !path
+            .canonicalize()
+            .is_ok_and(|target| target.starts_with(vault_root))
+}
+
+/// Whether `path` or a directory it sits in matches an ignore glob
+fn is_ignored(path: &Path, vault_root: &Path, ignored: &GlobSet) -> bool {
+    if ignored.is_empty() {
+        return false;
+    }
+    path.strip_prefix(vault_root).is_ok_and(|relative| {
+        relative
+            .ancestors()
+            .any(|p| !p.as_os_str().is_empty() && ignored.is_match(p))
+    })
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+}
+
+fn read_lossy(path: &Path) -> String {
+    std::fs::read(path)
+        .map(|b| String::from_utf8_lossy(&b).to_string())
+        .unwrap_or_default()
+}
+
+/// Count lines added and removed between two texts via their longest
+/// common subsequence of lines
+fn line_diff_stats(old: &str, new: &str) -> (usize, usize) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Common prefix and suffix don't affect the counts and are cheap to drop
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+
+    let mut row = vec![0usize; new.len() + 1];
+    for a in old {
+        let mut diagonal = 0;
+        for (j, b) in new.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if a == b {
+                diagonal + 1
+            } else {
+                above.max(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    let common = row[new.len()];
+
+    (new.len() - common, old.len() - common)
+}
+
+/// How much of a file to scan for NUL bytes when sniffing for binary content
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Decode note bytes as UTF-8. Returns the text and whether any invalid
+/// sequences were replaced.
+fn decode_note(note_path: &str, bytes: Vec<u8>, lossy: bool) -> Result<(String, bool), VaultError> {
+    if bytes.iter().take(BINARY_SNIFF_LEN).any(|&b| b == 0) {
+        return Err(VaultError::NotTextNote(format!(
+            "{} appears to be a binary file",
+            note_path
+        )));
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok((text, false)),
+        Err(e) if lossy => Ok((String::from_utf8_lossy(e.as_bytes()).to_string(), true)),
+        Err(e) => Err(VaultError::NotTextNote(format!(
+            "{} is not valid UTF-8 (byte {})",
+            note_path,
+            e.utf8_error().valid_up_to()
+        ))),
+    }
+}
+
+/// Characters of context kept on each side of a search match
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Buffered line reader over a note that tracks 1-based line numbers and
+/// tolerates invalid UTF-8
+struct NoteLines<R> {
+    reader: R,
+    number: usize,
+    pending: Option<String>,
+}
+
+impl<R: std::io::BufRead> NoteLines<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            number: 0,
+            pending: None,
+        }
+    }
+
+    fn next_line(&mut self) -> Option<(usize, String)> {
+        if let Some(line) = self.pending.take() {
+            return Some((self.number, line));
+        }
+        let mut buf = Vec::new();
+        match self.reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                self.number += 1;
+                let line = String::from_utf8_lossy(&buf);
+                Some((self.number, line.trim_end_matches(['\r', '\n']).to_string()))
+            }
+        }
+    }
+
+    /// Read a leading frontmatter block, following the same fence rules as
+    /// `split_frontmatter`. Without one, the first line is left for `next_line`.
+    fn frontmatter(&mut self) -> HashMap<String, serde_json::Value> {
+        let Some((_, first)) = self.next_line() else {
+            return HashMap::new();
+        };
+        if first.strip_prefix('\u{feff}').unwrap_or(&first) != "---" {
+            self.pending = Some(first);
+            return HashMap::new();
+        }
+
+        let mut block = String::from("---\n");
+        while let Some((_, line)) = self.next_line() {
+            block.push_str(&line);
+            block.push('\n');
+            if line.trim_end() == "---" {
+                return parse_frontmatter(&block).0;
+            }
+        }
+        HashMap::new()
+    }
+}
+
+/// Whether a frontmatter value equals `wanted`, or contains it when it's a list
+fn frontmatter_contains(
+    value: &serde_json::Value,
+    wanted: &str,
+    fold: &impl Fn(&str) -> String,
+) -> bool {
+    match value {
+        serde_json::Value::String(s) => fold(s) == wanted,
+        serde_json::Value::Array(items) => items
+            .iter()
+            .any(|item| frontmatter_contains(item, wanted, fold)),
+        other => fold(&other.to_string()) == wanted,
+    }
+}
+
+/// The matched text with up to `SNIPPET_CONTEXT` characters either side.
+/// `at` and `len` are byte offsets into the case-folded line, so they are
+/// mapped back to the original through character counts.
+fn snippet_around(line: &str, folded: &str, at: usize, len: usize) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let start = folded
+        .get(..at)
+        .map_or(0, |s| s.chars().count())
+        .min(chars.len());
+    let end = folded
+        .get(..at + len)
+        .map_or(chars.len(), |s| s.chars().count())
+        .min(chars.len());
+
+    let from = start.saturating_sub(SNIPPET_CONTEXT);
+    let to = (end + SNIPPET_CONTEXT).min(chars.len());
+    let mut snippet: String = chars[from..to].iter().collect();
+    if from > 0 {
+        snippet.insert(0, '…');
+    }
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    snippet.trim().to_string()
+}
+
+/// Directories that writing `full_path` would create
+fn missing_dirs(full_path: &Path) -> Vec<PathBuf> {
+    full_path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| !dir.exists())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Remove directories a rolled-back write created. Deepest go first, so each
+/// is empty by the time it's reached; one that isn't has gained files from
+/// elsewhere and stays.
+fn remove_created_dirs(dirs: &BTreeSet<PathBuf>) {
+    for dir in dirs.iter().rev() {
+        if let Err(e) = std::fs::remove_dir(dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Left {} in place after rollback: {}", dir.display(), e);
+            }
+        }
+    }
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), VaultError> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| VaultError::IoError(e.to_string()))?;
+    }
+    std::fs::rename(from, to).map_err(|e| VaultError::IoError(e.to_string()))
+}
+
+/// SHA-256 of a note's bytes as stored, hex-encoded
+fn content_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn modified_time(path: &Path) -> Option<chrono::DateTime<chrono::Utc>> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.into())
+}
+
+/// Resolve a path under `attachments/` to its vault-relative and full paths.
+/// Notes aren't attachments, so `.md` paths are refused.
+fn attachment_path(vault_root: &Path, path: &str) -> Result<(String, PathBuf), VaultError> {
+    let relative = format!(
+        "{}/{}",
+        ATTACHMENTS_DIR,
+        path.trim_start_matches('/').replace('\\', "/")
+    );
+    let full_path = resolve_in_vault(vault_root, &relative)?;
+    if is_note_file(&full_path) || full_path == vault_root.join(ATTACHMENTS_DIR) {
+        return Err(VaultError::InvalidPath(path.to_string()));
+    }
+    Ok((relative, full_path))
+}
+
+fn attachment_info(full_path: &Path, path: String) -> Result<AttachmentInfo, VaultError> {
+    let metadata = std::fs::metadata(full_path).map_err(|e| VaultError::IoError(e.to_string()))?;
+    Ok(AttachmentInfo {
+        mime_type: detect_mime(full_path),
+        size: metadata.len(),
+        last_modified: modified_time(full_path)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default(),
+        path,
+    })
+}
+
+/// MIME type from the file's leading bytes, falling back to its extension
+/// for text formats that have no signature
+fn detect_mime(path: &Path) -> String {
+    let mut head = Vec::with_capacity(BINARY_SNIFF_LEN);
+    if let Ok(file) = std::fs::File::open(path) {
+        use std::io::Read;
+        let _ = file.take(BINARY_SNIFF_LEN as u64).read_to_end(&mut head);
+    }
+    if let Some(kind) = infer::get(&head) {
+        return kind.mime_type().to_string();
+    }
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "svg" => "image/svg+xml",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "canvas" => "application/json",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+fn note_entry(path: &Path, vault_root: &Path) -> VaultNoteEntry {
+    VaultNoteEntry {
+        path: path
+            .strip_prefix(vault_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string(),
+        name: path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        last_modified: modified_time(path)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default(),
+        is_dir: false,
+    }
+}
+
+fn is_note_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
+
+/// Split a note into its YAML frontmatter and body. The block must open on
+/// the first line and close with a line that is exactly `---`.
+pub(crate) fn parse_frontmatter(content: &str) -> (HashMap<String, serde_json::Value>, String) {
+    let Some((fm_str, body)) = split_frontmatter(content) else {
+        return (HashMap::new(), content.to_string());
+    };
+
+    let mut frontmatter = HashMap::new();
+    match serde_yaml::from_str::<serde_yaml::Value>(fm_str) {
+        Ok(serde_yaml::Value::Mapping(mapping)) => {
+            for (key, value) in mapping {
+                let key = match key {
+                    serde_yaml::Value::String(s) => s,
+                    other => match serde_yaml::to_string(&other) {
+                        Ok(s) => s.trim_end().to_string(),
+                        Err(_) => continue,
+                    },
+                };
+                // Values JSON can't hold (e.g. maps with non-string keys) are dropped
+                if let Ok(value) = serde_json::to_value(value) {
+                    frontmatter.insert(key, value);
+                }
+            }
+        }
+        Ok(serde_yaml::Value::Null) => {}
+        Ok(_) => tracing::warn!("Frontmatter is not a key/value mapping"),
+        Err(e) => tracing::warn!("Unparseable frontmatter: {}", e),
+    }
+
+    (frontmatter, body.trim().to_string())
+}
+
+/// Why a note's frontmatter can't be read, if it can't. `parse_frontmatter`
+/// treats such a block as empty.
+fn frontmatter_problem(content: &str) -> Option<String> {
+    let trimmed = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let opens = trimmed
+        .lines()
+        .next()
+        .is_some_and(|line| line.trim_end() == "---");
+    let Some((fm_str, _)) = split_frontmatter(content) else {
+        return opens.then(|| "Frontmatter block is never closed".to_string());
+    };
+    match serde_yaml::from_str::<serde_yaml::Value>(fm_str) {
+        Ok(serde_yaml::Value::Mapping(_) | serde_yaml::Value::Null) => None,
+        Ok(_) => Some("Frontmatter is not a key/value mapping".to_string()),
+        Err(e) => Some(format!("Unparseable frontmatter: {}", e)),
+    }
+}
+
+/// Find the frontmatter fences. The opening `---` must be the whole first
+/// line (so `----` doesn't count), and only a line that is exactly `---`
+/// closes the block; a horizontal rule or fenced `---` in the body is left
+/// alone because the first closing line always wins. A leading BOM is skipped.
+pub(crate) fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let rest = content.strip_prefix("---")?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+impl VaultNote {
+    /// The note's `classification` frontmatter value, if any
+    pub fn classification(&self) -> Option<&str> {
+        self.frontmatter
+            .get("classification")
+            .and_then(|v| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultNote {
+    pub path: String,
+    pub name: String,
+    pub content: String,
+    pub frontmatter: HashMap<String, serde_json::Value>,
+    pub last_modified: String,
+    /// Set when invalid UTF-8 was replaced during a lossy read
+    #[serde(default)]
+    pub lossy: bool,
+    /// SHA-256 of the file's bytes, hex-encoded; pass back as `expected_hash`
+    /// to detect edits made since this read
+    #[serde(default)]
+    pub content_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultNoteEntry {
+    pub path: String,
+    pub name: String,
+    pub last_modified: String,
+    #[serde(default)]
+    pub is_dir: bool,
+}
+
+/// What `list_notes` walks and returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListOptions {
+    pub recursive: bool,
+    /// Also list attachments and other non-`.md` files
+    pub include_non_md: bool,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            recursive: true,
+            include_non_md: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteLinks {
+    pub path: String,
+    pub links: Vec<ResolvedLink>,
+    pub embeds: Vec<ResolvedLink>,
+    pub external: Vec<String>,
+    pub unresolved: Vec<WikiLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedLink {
+    pub path: String,
+    #[serde(flatten)]
+    pub link: WikiLink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthIssueKind {
+    MalformedFrontmatter,
+    TemplateViolation,
+    BrokenLink,
+    DuplicateName,
+    Orphan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthIssue {
+    pub path: String,
+    pub kind: HealthIssueKind,
+    pub severity: HealthSeverity,
+    pub message: String,
+}
+
+/// Problems found by `check_health`, sorted by note path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultHealthReport {
+    pub project_id: String,
+    pub checked_at: String,
+    pub notes_checked: usize,
+    pub issues: Vec<HealthIssue>,
+}
+
+/// One note in a `write_notes` batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteWrite {
+    pub path: String,
+    #[serde(default)]
+    pub frontmatter: HashMap<String, serde_json::Value>,
+    pub content: String,
+    /// `content_hash` from the last read; the batch fails if the note changed since
+    #[serde(default)]
+    pub expected_hash: Option<String>,
+}
+
+/// An attachment's size and type, so the UI can render or offer a download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentInfo {
+    /// Vault-relative, under `attachments/`
+    pub path: String,
+    pub mime_type: String,
+    pub size: u64,
+    pub last_modified: String,
+}
+
+/// A note carried in a project bundle, frontmatter included
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledNote {
+    pub path: String,
+    pub content: String,
+}
+
+/// What `import_notes` wrote, so a failed project import can undo it
+#[derive(Debug)]
+pub struct NoteImport {
+    written: Vec<String>,
+    created_dirs: BTreeSet<PathBuf>,
+}
+
+/// Size and age of one cached index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub notes: usize,
+    /// Backlinks, tag memberships, or term postings held
+    pub entries: usize,
+    pub built_at: String,
+    /// Last time a note change altered the index
+    pub updated_at: Option<String>,
+}
+
+/// The vault's cached indexes; an index not built yet is `None`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultCacheStats {
+    pub project_id: String,
+    pub links: Option<CacheStats>,
+    pub tags: Option<CacheStats>,
+    pub search: Option<CacheStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveReport {
+    pub from: String,
+    pub to: String,
+    /// Notes whose links were rewritten to the new path
+    pub updated: Vec<String>,
+}
+
+/// Which part of a note `search` looks in
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchScope {
+    #[default]
+    All,
+    Title,
+    Body,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub scope: SearchScope,
+    /// Only search notes with this frontmatter key
+    pub frontmatter_key: Option<String>,
+    /// ...whose value equals this (or, for a list, contains it)
+    pub frontmatter_value: Option<String>,
+    pub case_sensitive: bool,
+    pub limit: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            scope: SearchScope::All,
+            frontmatter_key: None,
+            frontmatter_value: None,
+            case_sensitive: false,
+            limit: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultSearchResult {
+    pub path: String,
+    /// 1-based line of a body match; 0 for a title match
+    pub line: usize,
+    pub snippet: String,
+}
+
+/// How `references_to` decides a link points at the target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceMatch {
+    Path,
+    Basename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+    Wikilink,
+    Embed,
+    Markdown,
+    Path,
+}
+
+/// A single reference to a note or attachment, located by source file and line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reference {
+    pub path: String,
+    pub line: usize,
+    pub kind: ReferenceKind,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub label: Option<String>,
+    pub created_at: String,
+    pub note_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultDiff {
+    pub snapshot_id: String,
+    pub added: Vec<NoteDiff>,
+    pub deleted: Vec<NoteDiff>,
+    pub modified: Vec<NoteDiff>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteDiff {
+    pub path: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl NoteDiff {
+    fn between(path: &str, old: &str, new: &str) -> Self {
+        let (lines_added, lines_removed) = line_diff_stats(old, new);
+        Self {
+            path: path.to_string(),
+            lines_added,
+            lines_removed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelRule {
+    pub pattern: String,
+    pub level: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub path: String,
+    pub variables: Vec<String>,
+    pub level: Option<u8>,
+    pub duplicates: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VaultError {
+    #[error("Vault not found for project: {0}")]
+    VaultNotFound(String),
+    #[error("Note not found: {0}")]
+    NoteNotFound(String),
+    #[error("IO error: {0}")]
+    IoError(String),
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("Snapshot not found: {0}")]
+    SnapshotNotFound(String),
+    #[error("Not a text note: {0}")]
+    NotTextNote(String),
+    #[error("Invalid vault path: {0}")]
+    InvalidVaultPath(String),
+    /// A note path that is absolute, climbs out with `..`, or resolves
+    /// outside the vault through a symlink
+    #[error("Invalid note path: {0}")]
+    InvalidPath(String),
+    #[error("Invalid frontmatter: {0}")]
+    InvalidFrontmatter(String),
+    #[error("Template violation: {0}")]
+    TemplateViolation(String),
+    #[error("Note already exists: {0}")]
+    NoteExists(String),
+    /// `on_disk_modified` and `on_disk_hash` are empty when the note has
+    /// since been deleted
+    #[error("Note changed on disk since it was read (modified {on_disk_modified})")]
+    Conflict {
+        on_disk_modified: String,
+        on_disk_hash: String,
+    },
+}
+
+/// A `write_notes` batch that failed and was rolled back
+#[derive(Debug, thiserror::Error)]
+#[error("Batch write failed at note {index} ({path}): {error}")]
+pub struct BatchWriteError {
+    /// Position of the failing note in the batch
+    pub index: usize,
+    pub path: String,
+    pub error: VaultError,
+    /// Notes whose prior state couldn't be restored; empty after a clean rollback
+    pub rollback_failures: Vec<String>,
+}
+
+impl Serialize for VaultError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault() -> (tempfile::TempDir, VaultManager) {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = VaultManager::new();
+        manager
+            .register_vault("p", dir.path().to_path_buf())
+            .unwrap();
+        (dir, manager)
+    }
+
+    fn note(path: &str, content: &str) -> NoteWrite {
+        NoteWrite {
+            path: path.to_string(),
+            frontmatter: HashMap::new(),
+            content: content.to_string(),
+            expected_hash: None,
+        }
+    }
+
+    #[test]
+    fn write_notes_rolls_back_a_mid_batch_failure() {
+        let (dir, manager) = vault();
+        std::fs::write(dir.path().join("design.md"), "original design").unwrap();
+        std::fs::write(dir.path().join("adr.md"), "original adr").unwrap();
+
+        let mut stale = note("adr.md", "new adr");
+        stale.expected_hash = Some("stale".to_string());
+        let notes = [
+            note("design.md", "new design"),
+            note("tasks/sprint/todo.md", "new tasks"),
+            stale,
+        ];
+        let err = manager.write_notes("p", &notes).unwrap_err();
+
+        assert_eq!(err.index, 2);
+        assert_eq!(err.path, "adr.md");
+        assert!(matches!(err.error, VaultError::Conflict { .. }));
+        assert!(err.rollback_failures.is_empty());
+        assert_eq!(
+            std::fs::read(dir.path().join("design.md")).unwrap(),
+            b"original design"
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("adr.md")).unwrap(),
+            b"original adr"
+        );
+        assert!(!dir.path().join("tasks").exists());
+    }
+
+    #[test]
+    fn delete_note_twice_keeps_both_trashed_copies() {
+        let (dir, manager) = vault();
+        let mut trashed = Vec::new();
+        for content in ["first", "second", "third"] {
+            std::fs::write(dir.path().join("note.md"), content).unwrap();
+            trashed.push(manager.delete_note("p", "note.md").unwrap());
+        }
+
+        assert_eq!(trashed[0], ".trash/note.md");
+        let contents: Vec<String> = trashed
+            .iter()
+            .map(|path| std::fs::read_to_string(dir.path().join(path)).unwrap())
+            .collect();
+        assert_eq!(contents, ["first", "second", "third"]);
+
+        for path in trashed.iter().rev() {
+            assert_eq!(manager.restore_note("p", path).unwrap(), "note.md");
+            std::fs::remove_file(dir.path().join("note.md")).unwrap();
+        }
+    }
+
+    #[test]
+    fn write_notes_writes_every_note_on_success() {
+        let (dir, manager) = vault();
+        let notes = [note("a.md", "alpha"), note("nested/b.md", "beta")];
+        manager.write_notes("p", &notes).unwrap();
+
+        assert_eq!(std::fs::read(dir.path().join("a.md")).unwrap(), b"alpha");
+        assert_eq!(
+            std::fs::read(dir.path().join("nested/b.md")).unwrap(),
+            b"beta"
+        );
+    }
+
+    fn bundled(path: &str, content: &str) -> BundledNote {
+        BundledNote {
+            path: path.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn import_notes_refuses_existing_notes_before_writing() {
+        let (dir, manager) = vault();
+        std::fs::write(dir.path().join("taken.md"), "mine").unwrap();
+
+        let notes = [bundled("docs/new.md", "new"), bundled("taken.md", "theirs")];
+        let err = manager.import_notes("p", &notes).unwrap_err();
+        assert!(matches!(err, VaultError::NoteExists(path) if path == "taken.md"));
+        assert!(!dir.path().join("docs").exists());
+        assert_eq!(std::fs::read(dir.path().join("taken.md")).unwrap(), b"mine");
+    }
+
+    #[test]
+    fn undo_import_removes_imported_notes_and_directories() {
+        let (dir, manager) = vault();
+        std::fs::write(dir.path().join("kept.md"), "kept").unwrap();
+
+        let notes = [bundled("a.md", "a"), bundled("docs/deep/b.md", "b")];
+        let import = manager.import_notes("p", &notes).unwrap();
+        assert!(dir.path().join("docs/deep/b.md").is_file());
+
+        manager.undo_import("p", import);
+        let mut left: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["kept.md"]);
+    }
+
+    #[test]
+    fn frontmatter_closing_fence_may_end_the_file() {
+        let (frontmatter, body) = parse_frontmatter("---\ntitle: Plan\n---");
+        assert_eq!(frontmatter["title"], "Plan");
+        assert_eq!(body, "");
+    }
+
+    #[test]
+    fn frontmatter_ignores_dashes_in_the_body() {
+        let content = "---\ntitle: Plan\n---\n\nIntro\n\n---\n\n```\n---\n```\nend: true\n";
+        let (frontmatter, body) = parse_frontmatter(content);
+        assert_eq!(frontmatter.len(), 1);
+        assert_eq!(frontmatter["title"], "Plan");
+        assert_eq!(body, "Intro\n\n---\n\n```\n---\n```\nend: true");
+    }
+
+    #[test]
+    fn frontmatter_accepts_crlf_fences() {
+        let (frontmatter, body) = parse_frontmatter("---\r\ntitle: Plan\r\n---\r\nBody\r\n");
+        assert_eq!(frontmatter["title"], "Plan");
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn frontmatter_must_open_with_exactly_three_dashes() {
+        let content = "----\ntitle: Plan\n---\nBody";
+        let (frontmatter, body) = parse_frontmatter(content);
+        assert!(frontmatter.is_empty());
+        assert_eq!(body, content);
+    }
+
+    #[test]
+    fn invalid_utf8_note_is_not_a_text_note() {
+        let (dir, manager) = vault();
+        std::fs::write(dir.path().join("broken.md"), b"caf\xe9 notes").unwrap();
+
+        let err = manager.read_note("p", "broken.md").unwrap_err();
+        assert!(matches!(err, VaultError::NotTextNote(_)));
+
+        let note = manager.read_note_with("p", "broken.md", true).unwrap();
+        assert!(note.lossy);
+        assert_eq!(note.content, "caf\u{fffd} notes");
+    }
+
+    #[test]
+    fn binary_note_is_not_a_text_note() {
+        let (dir, manager) = vault();
+        std::fs::write(dir.path().join("image.md"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+
+        let err = manager.read_note_with("p", "image.md", true).unwrap_err();
+        assert!(matches!(err, VaultError::NotTextNote(_)));
+    }
+
+    #[test]
+    fn write_note_rejects_a_non_compliant_level_note() {
+        let (dir, manager) = vault();
+        let mut frontmatter = HashMap::new();
+        frontmatter.insert("level".to_string(), serde_json::json!(2));
+
+        let result = manager.write_note("p", "system.md", &frontmatter, "# Overview\n", None, None);
+        assert!(matches!(result, Err(VaultError::TemplateViolation(_))));
+        assert!(!dir.path().join("system.md").exists());
+    }
+
+    #[test]
+    fn stale_expected_last_modified_conflicts_and_leaves_the_note() {
+        let (dir, manager) = vault();
+        let path = dir.path().join("n.md");
+        std::fs::write(&path, "on disk\n").unwrap();
+        let stale = "2000-01-01T00:00:00+00:00";
+
+        let result = manager.write_note("p", "n.md", &HashMap::new(), "mine\n", Some(stale), None);
+        match result {
+            Err(VaultError::Conflict {
+                on_disk_modified,
+                on_disk_hash,
+            }) => {
+                assert!(!on_disk_modified.is_empty());
+                assert_eq!(on_disk_hash, content_hash(b"on disk\n"));
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "on disk\n");
+    }
+
+    #[test]
+    fn current_expected_last_modified_writes() {
+        let (dir, manager) = vault();
+        std::fs::write(dir.path().join("n.md"), "on disk\n").unwrap();
+        let current = manager.read_note("p", "n.md").unwrap().last_modified;
+
+        manager
+            .write_note("p", "n.md", &HashMap::new(), "mine\n", Some(&current), None)
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("n.md")).unwrap(),
+            "mine\n"
+        );
+    }
+
+    #[test]
+    fn parent_traversal_is_refused() {
+        let (dir, manager) = vault();
+        let root = dir.path().canonicalize().unwrap();
+        for path in [
+            "../outside.md",
+            "notes/../../outside.md",
+            "notes\\..\\..\\outside.md",
+        ] {
+            assert!(
+                matches!(
+                    resolve_in_vault(&root, path),
+                    Err(VaultError::InvalidPath(_))
+                ),
+                "{} should be refused",
+                path
+            );
+        }
+        let result = manager.write_note("p", "../outside.md", &HashMap::new(), "x", None, None);
+        assert!(matches!(result, Err(VaultError::InvalidPath(_))));
+        assert!(!root.parent().unwrap().join("outside.md").exists());
+    }
+
+    #[test]
+    fn absolute_paths_are_refused() {
+        let (dir, manager) = vault();
+        let root = dir.path().canonicalize().unwrap();
+        let absolute = root.join("n.md");
+        assert!(matches!(
+            resolve_in_vault(&root, absolute.to_str().unwrap()),
+            Err(VaultError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            manager.read_note("p", "/etc/passwd"),
+            Err(VaultError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn nested_relative_paths_resolve_inside_the_vault() {
+        let (dir, _manager) = vault();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            resolve_in_vault(&root, "./a/b.md").unwrap(),
+            root.join("a/b.md")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_escaping_the_vault_are_refused() {
+        let (dir, manager) = vault();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.md"), "secret").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).unwrap();
+
+        assert!(matches!(
+            manager.read_note("p", "escape/secret.md"),
+            Err(VaultError::InvalidPath(_))
+        ));
+        let result = manager.write_note("p", "escape/new.md", &HashMap::new(), "x", None, None);
+        assert!(matches!(result, Err(VaultError::InvalidPath(_))));
+        assert!(!outside.path().join("new.md").exists());
+    }
+
+    #[test]
+    fn content_hash_is_stable_across_reads() {
+        let (dir, manager) = vault();
+        std::fs::write(dir.path().join("n.md"), "---\ntitle: N\n---\nbody\n").unwrap();
+
+        let first = manager.read_note("p", "n.md").unwrap().content_hash;
+        let second = manager.read_note("p", "n.md").unwrap().content_hash;
+        assert_eq!(first, second);
+        assert_eq!(first, content_hash(b"---\ntitle: N\n---\nbody\n"));
+    }
+
+    #[test]
+    fn content_hash_changes_after_an_edit() {
+        let (_dir, manager) = vault();
+        manager
+            .write_note("p", "n.md", &HashMap::new(), "before\n", None, None)
+            .unwrap();
+        let before = manager.read_note("p", "n.md").unwrap().content_hash;
+
+        manager
+            .write_note("p", "n.md", &HashMap::new(), "after\n", None, Some(&before))
+            .unwrap();
+        let after = manager.read_note("p", "n.md").unwrap().content_hash;
+        assert_ne!(before, after);
+
+        let stale =
+            manager.write_note("p", "n.md", &HashMap::new(), "again\n", None, Some(&before));
+        assert!(matches!(stale, Err(VaultError::Conflict { .. })));
+    }
+
+    #[test]
+    fn move_note_repoints_every_link_form() {
+        let (dir, manager) = vault();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::write(root.join("notes/old.md"), "# Setup\n").unwrap();
+        std::fs::write(
+            root.join("index.md"),
+            "[[old]] [[notes/old]] [[old|alias]] [[old#Setup]] ![[notes/old#Setup]] [[other]]\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("unrelated.md"), "[[other]]\n").unwrap();
+
+        let report = manager
+            .move_note("p", "notes/old.md", "archive/new.md", false)
+            .unwrap();
+
+        assert_eq!(report.to, "archive/new.md");
+        assert_eq!(report.updated, vec!["index.md".to_string()]);
+        assert!(!root.join("notes/old.md").exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join("archive/new.md")).unwrap(),
+            "# Setup\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("index.md")).unwrap(),
+            "[[new]] [[archive/new]] [[new|alias]] [[new#Setup]] ![[archive/new#Setup]] [[other]]\n"
+        );
+    }
+
+    #[test]
+    fn move_note_refuses_to_overwrite_without_the_flag() {
+        let (dir, manager) = vault();
+        let root = dir.path();
+        std::fs::write(root.join("a.md"), "a").unwrap();
+        std::fs::write(root.join("b.md"), "b").unwrap();
+
+        let result = manager.move_note("p", "a.md", "b.md", false);
+        assert!(matches!(result, Err(VaultError::NoteExists(_))));
+        assert_eq!(std::fs::read_to_string(root.join("a.md")).unwrap(), "a");
+        assert_eq!(std::fs::read_to_string(root.join("b.md")).unwrap(), "b");
+
+        manager.move_note("p", "a.md", "b.md", true).unwrap();
+        assert!(!root.join("a.md").exists());
+        assert_eq!(std::fs::read_to_string(root.join("b.md")).unwrap(), "a");
+    }
+
+    #[test]
+    fn ignored_paths_are_left_out_of_walks() {
+        let (dir, manager) = vault();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("drafts/deep")).unwrap();
+        std::fs::write(root.join("kept.md"), "rocket\n").unwrap();
+        std::fs::write(root.join("drafts/deep/wip.md"), "rocket\n").unwrap();
+        std::fs::write(root.join("scratch.tmp.md"), "rocket\n").unwrap();
+        manager
+            .set_ignore_patterns("p", &["drafts".to_string(), "*.tmp.md".to_string()])
+            .unwrap();
+
+        let listed: Vec<String> = manager
+            .list_notes("p", "", &ListOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(listed, ["kept.md"]);
+        assert_eq!(manager.count_notes("p", "").unwrap(), 1);
+
+        let found: Vec<String> = manager
+            .search("p", "rocket", &SearchOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|r| r.path)
+            .collect();
+        assert_eq!(found, ["kept.md"]);
+
+        manager.set_ignore_patterns("p", &[]).unwrap();
+        assert_eq!(manager.count_notes("p", "").unwrap(), 3);
+    }
+
+    #[test]
+    fn bad_ignore_pattern_is_refused() {
+        let (_dir, manager) = vault();
+        assert!(matches!(
+            manager.set_ignore_patterns("p", &["a[".to_string()]),
+            Err(VaultError::InvalidPattern(_))
+        ));
     }
 }
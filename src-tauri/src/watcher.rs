@@ -0,0 +1,192 @@
+//! Filesystem watching for registered vaults.
+//! Edits made outside Nebula (e.g. in Obsidian) are coalesced over a short
+//! window, applied to the vault manager's caches, and sent to the frontend as
+//! `vault://changed` events.
+
+use crate::sync::MutexExt;
+use crate::vault::VaultManager;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Editors often save a file as several writes; changes within this window
+/// of the first one go out together
+const COALESCE_WINDOW: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VaultChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl VaultChangeKind {
+    /// What a path's net change is after `self` followed by `next`
+    fn then(self, next: Self) -> Self {
+        match (self, next) {
+            (_, Self::Deleted) => Self::Deleted,
+            (Self::Created, _) => Self::Created,
+            (Self::Deleted, _) => Self::Modified,
+            (_, next) => next,
+        }
+    }
+}
+
+/// Payload of a `vault://changed` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultChange {
+    pub project_id: String,
+    /// Vault-relative, `/`-separated
+    pub path: String,
+    pub kind: VaultChangeKind,
+}
+
+/// One watcher per project's vault. Dropping a watcher stops it, and its
+/// coalescing thread exits once the watcher's channel closes.
+pub struct VaultWatchers {
+    app: Mutex<Option<AppHandle>>,
+    active: Mutex<HashMap<String, (PathBuf, RecommendedWatcher)>>,
+}
+
+impl VaultWatchers {
+    pub fn new() -> Self {
+        Self {
+            app: Mutex::new(None),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn attach(&self, app: AppHandle) {
+        *self.app.lock_recover() = Some(app);
+    }
+
+    /// Watch `vault_root` for a project, replacing a watcher on a different
+    /// directory. Does nothing until an app handle is attached.
+    pub fn watch(&self, project_id: &str, vault_root: &Path) {
+        let Some(app) = self.app.lock_recover().clone() else {
+            return;
+        };
+        let mut active = self.active.lock_recover();
+        if active
+            .get(project_id)
+            .is_some_and(|(root, _)| root == vault_root)
+        {
+            return;
+        }
+        active.remove(project_id);
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                tracing::warn!("Cannot watch vault for {}: {}", project_id, e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(vault_root, RecursiveMode::Recursive) {
+            tracing::warn!("Cannot watch {}: {}", vault_root.display(), e);
+            return;
+        }
+
+        let project = project_id.to_string();
+        let root = vault_root.to_path_buf();
+        std::thread::spawn(move || coalesce(rx, project, root, app));
+        active.insert(project_id.to_string(), (vault_root.to_path_buf(), watcher));
+    }
+
+    /// Stop watching a project's vault
+    pub fn unwatch(&self, project_id: &str) {
+        self.active.lock_recover().remove(project_id);
+    }
+}
+
+/// Collect changes from the first event until the window closes, then apply
+/// them. Changes still pending when the watcher is dropped are discarded,
+/// since the project now points at another directory.
+fn coalesce(
+    rx: Receiver<notify::Result<Event>>,
+    project_id: String,
+    root: PathBuf,
+    app: AppHandle,
+) {
+    let mut pending: BTreeMap<String, VaultChangeKind> = BTreeMap::new();
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let received = match deadline {
+            Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Ok(event)) => {
+                for (path, kind) in changes(&event, &root) {
+                    pending
+                        .entry(path)
+                        .and_modify(|k| *k = k.then(kind))
+                        .or_insert(kind);
+                }
+                if !pending.is_empty() && deadline.is_none() {
+                    deadline = Some(Instant::now() + COALESCE_WINDOW);
+                }
+            }
+            Ok(Err(e)) => tracing::warn!("Vault watch error for {}: {}", project_id, e),
+            Err(RecvTimeoutError::Timeout) => {
+                deadline = None;
+                for (path, kind) in std::mem::take(&mut pending) {
+                    if let Some(vault_manager) = app.try_state::<VaultManager>() {
+                        vault_manager.apply_external_change(&project_id, &path);
+                    }
+                    let change = VaultChange {
+                        project_id: project_id.clone(),
+                        path,
+                        kind,
+                    };
+                    if let Err(e) = app.emit("vault://changed", &change) {
+                        tracing::warn!("Failed to emit vault://changed: {}", e);
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/// Vault-relative paths an event touches and how. Paths under a hidden
+/// directory (`.trash/`, `.nebula/`, `.obsidian/`, ...) are ignored.
+fn changes(event: &Event, root: &Path) -> Vec<(String, VaultChangeKind)> {
+    let kinds: Vec<VaultChangeKind> = match event.kind {
+        EventKind::Create(_) => vec![VaultChangeKind::Created],
+        EventKind::Remove(_) => vec![VaultChangeKind::Deleted],
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => vec![VaultChangeKind::Deleted],
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => vec![VaultChangeKind::Created],
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            vec![VaultChangeKind::Deleted, VaultChangeKind::Created]
+        }
+        EventKind::Modify(_) => vec![VaultChangeKind::Modified],
+        _ => return Vec::new(),
+    };
+
+    event
+        .paths
+        .iter()
+        .enumerate()
+        .filter_map(|(i, path)| {
+            let relative = path.strip_prefix(root).ok()?;
+            let hidden = relative
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+            if hidden || relative.as_os_str().is_empty() {
+                return None;
+            }
+            let kind = kinds.get(i).or(kinds.last()).copied()?;
+            Some((relative.to_string_lossy().replace('\\', "/"), kind))
+        })
+        .collect()
+}
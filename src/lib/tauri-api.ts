@@ -4,6 +4,55 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+
+// ── Errors ──
+
+export type ErrorCode =
+  | "vault_not_found"
+  | "invalid_vault_path"
+  | "invalid_path"
+  | "note_not_found"
+  | "note_exists"
+  | "not_text_note"
+  | "template_violation"
+  | "conflict"
+  | "snapshot_not_found"
+  | "policy_not_found"
+  | "policy_version_not_found"
+  | "invalid_policy"
+  | "run_not_found"
+  | "span_not_found"
+  | "project_not_found"
+  | "project_not_empty"
+  | "project_exists"
+  | "workstream_not_found"
+  | "access_denied"
+  | "permission_denied"
+  | "invalid_pattern"
+  | "invalid_input"
+  | "invalid_config"
+  | "storage_unavailable"
+  | "audit_write_failed"
+  | "verification_failed"
+  | "io_error"
+  | "internal";
+
+/** Rejection value of any command that returns `Result<_, CommandError>` */
+export interface CommandError {
+  code: ErrorCode;
+  message: string;
+  details: unknown | null;
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "code" in error &&
+    "message" in error
+  );
+}
 
 // ── Projects ──
 
@@ -15,28 +64,156 @@ export interface ProjectData {
   status: string;
   vault_path: string;
   workstreams: string[];
+  /** For an imported project, its history's old project and workstream ids mapped to the new ones */
+  imported_ids?: Record<string, string>;
+}
+
+/** Archived projects are left out unless `includeArchived` is set. */
+export interface StoreHealth {
+  name: string;
+  healthy: boolean;
+  poisoned_locks: string[];
+}
+
+export interface StorageHealth {
+  name: string;
+  path: string | null;
+  writable: boolean;
+  error: string | null;
+}
+
+export interface SystemHealth {
+  /** No poisoned locks and every storage directory writable */
+  healthy: boolean;
+  checked_at: string;
+  projects: number;
+  workstreams: number;
+  vaults: number;
+  audit_events: number;
+  stores: StoreHealth[];
+  storage: StorageHealth[];
+}
+
+/** Readiness of the backend's state stores and storage, for diagnostics and the status bar */
+export async function getSystemHealth(): Promise<SystemHealth> {
+  return invoke<SystemHealth>("system_health");
 }
 
-export async function getProjects(): Promise<ProjectData[]> {
-  return invoke<ProjectData[]>("get_projects");
+export async function getProjects(includeArchived?: boolean): Promise<ProjectData[]> {
+  return invoke<ProjectData[]>("get_projects", { includeArchived });
 }
 
+export interface ProjectSummary extends ProjectData {
+  /** Workstreams that aren't archived */
+  workstream_count: number;
+  /** Workstreams that aren't archived or finished */
+  active_workstream_count: number;
+  /** Timestamp of the project's newest audit event */
+  last_activity: string | null;
+}
+
+/** Projects with workstream counts and last activity, in one call. */
+export async function getProjectsWithStats(includeArchived?: boolean): Promise<ProjectSummary[]> {
+  return invoke<ProjectSummary[]>("get_projects_with_stats", { includeArchived });
+}
+
+export interface ProjectPage {
+  projects: ProjectData[];
+  total: number;
+}
+
+export async function getProjectsPaged(
+  offset?: number,
+  limit?: number,
+  query?: string
+): Promise<ProjectPage> {
+  return invoke<ProjectPage>("get_projects_paged", { offset, limit, query });
+}
+
+/**
+ * Rejects with `project_exists` if a non-archived project already has this
+ * name (ignoring case), unless `allowDuplicate` is set.
+ */
 export async function createProject(
   name: string,
   description: string,
-  vaultPath: string
+  vaultPath: string,
+  allowDuplicate?: boolean
 ): Promise<ProjectData> {
   return invoke<ProjectData>("create_project", {
     name,
     description,
     vaultPath,
+    allowDuplicate,
   });
 }
 
+export async function registerVault(projectId: string, vaultPath: string): Promise<void> {
+  return invoke("register_vault", { projectId, vaultPath });
+}
+
 export async function getProject(projectId: string): Promise<ProjectData | null> {
   return invoke<ProjectData | null>("get_project", { projectId });
 }
 
+export interface ProjectUpdate {
+  name?: string;
+  description?: string;
+  /** Must be an existing directory; `null` detaches the vault */
+  vault_path?: string | null;
+}
+
+/** Only the fields supplied change */
+export async function updateProject(
+  projectId: string,
+  changes: ProjectUpdate,
+  allowDuplicate?: boolean
+): Promise<ProjectData> {
+  return invoke<ProjectData>("update_project", { projectId, changes, allowDuplicate });
+}
+
+export interface BundledNote {
+  path: string;
+  content: string;
+}
+
+/** A project with its workstreams, policy, vault notes, and audit history */
+export interface ProjectBundle {
+  version: number;
+  exported_at: string;
+  project: ProjectData;
+  workstreams: WorkstreamData[];
+  policy: unknown | null;
+  notes: BundledNote[];
+  audit_events: AuditEvent[];
+}
+
+export interface ProjectImport {
+  project: ProjectData;
+  workstreams: number;
+  notes: number;
+  audit: ImportReport;
+}
+
+export async function exportProject(
+  projectId: string,
+  readerRole?: string
+): Promise<ProjectBundle> {
+  return invoke<ProjectBundle>("export_project", { projectId, readerRole });
+}
+
+/**
+ * Recreates a bundled project under new ids, writing its notes into `vaultPath`.
+ * Audit events are imported unchanged.
+ */
+export async function importProject(
+  bundle: ProjectBundle,
+  vaultPath: string,
+  allowDuplicate?: boolean
+): Promise<ProjectImport> {
+  return invoke<ProjectImport>("import_project", { bundle, vaultPath, allowDuplicate });
+}
+
 // ── Workstreams ──
 
 export interface WorkstreamData {
@@ -49,6 +226,7 @@ export interface WorkstreamData {
   user_request: string;
   current_phase: string;
   messages: MessageData[];
+  run_id: string | null;
 }
 
 export interface MessageData {
@@ -58,10 +236,67 @@ export interface MessageData {
   timestamp: string;
   agent_id: string | null;
   agent_name: string | null;
+  redacted: boolean;
+}
+
+/** Archived workstreams are left out unless `includeArchived` is set. */
+export async function getWorkstreams(
+  projectId: string,
+  includeArchived?: boolean
+): Promise<WorkstreamData[]> {
+  return invoke<WorkstreamData[]>("get_workstreams", { projectId, includeArchived });
+}
+
+export interface WorkstreamPage {
+  workstreams: WorkstreamData[];
+  /** Matches before paging */
+  total: number;
+}
+
+export interface WorkstreamPageOptions {
+  status?: string;
+  phase?: string;
+  /** Implied when `status` is `archived` */
+  includeArchived?: boolean;
+  offset?: number;
+  /** Defaults to 50 */
+  limit?: number;
+}
+
+/** Newest-first page of a project's workstreams, filtered by status and phase. */
+export async function getWorkstreamsPaged(
+  projectId: string,
+  options: WorkstreamPageOptions = {}
+): Promise<WorkstreamPage> {
+  return invoke<WorkstreamPage>("get_workstreams_paged", { projectId, ...options });
+}
+
+/** With `since` (RFC 3339), only messages sent after it are included. */
+export async function getWorkstream(
+  workstreamId: string,
+  since?: string
+): Promise<WorkstreamData | null> {
+  return invoke<WorkstreamData | null>("get_workstream", { workstreamId, since });
+}
+
+export async function archiveProject(projectId: string): Promise<ProjectData> {
+  return invoke<ProjectData>("archive_project", { projectId });
 }
 
-export async function getWorkstreams(projectId: string): Promise<WorkstreamData[]> {
-  return invoke<WorkstreamData[]>("get_workstreams", { projectId });
+export async function archiveWorkstream(workstreamId: string): Promise<WorkstreamData> {
+  return invoke<WorkstreamData>("archive_workstream", { workstreamId });
+}
+
+/**
+ * Remove a project and its workstreams. Rejects with `project_not_empty`
+ * while any workstream is not archived.
+ */
+export async function deleteProject(projectId: string): Promise<void> {
+  return invoke<void>("delete_project", { projectId });
+}
+
+export async function deleteWorkstream(workstreamId: string): Promise<void> {
+  return invoke<void>("delete_workstream", { workstreamId });
 }
 
 export async function createWorkstream(
@@ -76,6 +311,53 @@ export async function createWorkstream(
   });
 }
 
+/**
+ * Starts a new workstream from an existing one, at the first phase with a new run.
+ * Messages are copied only when `copyMessages` is set.
+ */
+export async function duplicateWorkstream(
+  workstreamId: string,
+  newTitle: string,
+  copyMessages?: boolean
+): Promise<WorkstreamData> {
+  return invoke<WorkstreamData>("duplicate_workstream", {
+    workstreamId,
+    newTitle,
+    copyMessages,
+  });
+}
+
+export async function reopenWorkstream(
+  workstreamId: string,
+  toPhase: string,
+  reason?: string
+): Promise<WorkstreamData> {
+  return invoke<WorkstreamData>("reopen_workstream", { workstreamId, toPhase, reason });
+}
+
+/**
+ * Move a workstream to the next phase. Illegal jumps reject with
+ * `invalid_input`; `details.allowed` lists the legal next phases.
+ */
+export async function transitionPhase(
+  workstreamId: string,
+  toPhase: string
+): Promise<WorkstreamData> {
+  return invoke<WorkstreamData>("transition_phase", { workstreamId, toPhase });
+}
+
+/** Terminal statuses also complete the workstream's current run. */
+export async function updateWorkstreamStatus(
+  workstreamId: string,
+  status: string
+): Promise<WorkstreamData> {
+  return invoke<WorkstreamData>("update_workstream_status", { workstreamId, status });
+}
+
+/**
+ * Posts a message as the user. Rejects with `invalid_input` if the content is empty or longer
+ * than the project's `max_message_length`, with `{ length, max_length }` in `details`.
+ */
 export async function sendMessage(
   workstreamId: string,
   content: string
@@ -83,6 +365,23 @@ export async function sendMessage(
   return invoke<MessageData>("send_message", { workstreamId, content });
 }
 
+/** Rejects with `permission_denied` unless the role may `chat.post`. */
+export async function sendAgentMessage(
+  workstreamId: string,
+  agentId: string,
+  agentName: string,
+  agentRole: string,
+  content: string
+): Promise<MessageData> {
+  return invoke<MessageData>("send_agent_message", {
+    workstreamId,
+    agentId,
+    agentName,
+    agentRole,
+    content,
+  });
+}
+
 // ── Audit ──
 
 export interface AuditEvent {
@@ -102,12 +401,15 @@ export interface AuditEvent {
   parent_event_id: string | null;
   span_id: string | null;
   trace_id: string | null;
+  prev_hash: string | null;
+  hash: string | null;
 }
 
 export interface RunRecord {
   id: string;
   project_id: string;
   workstream_id: string;
+  phase: string;
   started_at: string;
   completed_at: string | null;
   status: string;
@@ -129,18 +431,385 @@ export interface RunSummary {
   deployments_completed: number;
   documentation_updates: number;
   linear_updates: number;
+  /** Spans started but never ended */
+  open_spans: number;
   duration: number;
 }
 
 export async function getAuditLog(
   projectId: string,
-  limit?: number
+  limit?: number,
+  timezone?: string,
+  format?: string
+): Promise<AuditEvent[]> {
+  return invoke<AuditEvent[]>("get_audit_log", { projectId, limit, timezone, format });
+}
+
+/** Unset fields match everything; `event_types` entries are exact or `prefix.*`. */
+export interface EventFilter {
+  event_types?: string[];
+  actor_type?: string;
+  run_id?: string;
+  from?: string;
+  to?: string;
+  limit?: number;
+}
+
+export interface EventQueryResult {
+  events: AuditEvent[];
+  /** All matches, even when `limit` truncated `events` */
+  total: number;
+}
+
+export async function queryEvents(
+  projectId: string,
+  filter: EventFilter
+): Promise<EventQueryResult> {
+  return invoke<EventQueryResult>("query_events", { projectId, filter });
+}
+
+export type MatchMode = "exact" | "contains";
+
+export interface AuditSearchOptions {
+  /** `exact` (default) or `contains`; only string values match `contains` */
+  matchMode?: MatchMode;
+  /** Exact type or `prefix.*`, checked before the payload */
+  eventType?: string;
+  limit?: number;
+}
+
+/**
+ * Events whose payload has a value at `jsonPath` matching `value`, newest first.
+ * `jsonPath` is dotted, e.g. `commit.sha` or `files[*].path`; events without it are skipped.
+ */
+export async function searchAudit(
+  projectId: string,
+  jsonPath: string,
+  value: string,
+  options: AuditSearchOptions = {}
 ): Promise<AuditEvent[]> {
-  return invoke<AuditEvent[]>("get_audit_log", { projectId, limit });
+  return invoke<AuditEvent[]>("search_audit", { projectId, jsonPath, value, ...options });
+}
+
+export interface EventPage {
+  events: AuditEvent[];
+  /** Pass as `before` for the next older page; null on the last page */
+  next_cursor: string | null;
+}
+
+export async function getAuditLogPage(
+  projectId: string,
+  before?: string,
+  limit?: number
+): Promise<EventPage> {
+  return invoke<EventPage>("get_audit_log_page", { projectId, before, limit });
+}
+
+export async function getRunRecord(
+  runId: string,
+  includeEvents?: boolean,
+  eventTypePrefix?: string
+): Promise<RunRecord | null> {
+  return invoke<RunRecord | null>("get_run_record", {
+    runId,
+    includeEvents,
+    eventTypePrefix,
+  });
 }
 
-export async function getRunRecord(runId: string): Promise<RunRecord | null> {
-  return invoke<RunRecord | null>("get_run_record", { runId });
+/** Returns the completed run without its events. */
+export async function completeRun(
+  runId: string,
+  status: "completed" | "failed" | "cancelled"
+): Promise<RunRecord> {
+  return invoke<RunRecord>("complete_run", { runId, status });
+}
+
+export type AuditActor = AuditEvent["actor"];
+
+export interface RunCancellation {
+  run_id: string;
+  project_id: string;
+  workstream_id: string;
+  reason: string;
+  actor: AuditActor;
+}
+
+/** Cancel a run still in progress; `actor` defaults to the user */
+export async function cancelRun(
+  runId: string,
+  reason: string,
+  actor?: AuditActor
+): Promise<RunRecord> {
+  return invoke<RunRecord>("cancel_run", { runId, reason, actor });
+}
+
+export type SpanEndStatus = "ok" | "error" | "cancelled";
+
+export interface RunSpan {
+  span_id: string;
+  name: string;
+  started_at: string;
+  /** `null` while the span is still open */
+  ended_at: string | null;
+  status: SpanEndStatus | null;
+  duration_ms: number | null;
+}
+
+/** Opens a span on a running run; resolves to the span id to pass to `endSpan` */
+export async function startSpan(runId: string, name: string, actor: AuditActor): Promise<string> {
+  return invoke<string>("start_span", { runId, name, actor });
+}
+
+/** Closes a span; rejects with `conflict` if it already ended */
+export async function endSpan(
+  spanId: string,
+  status: SpanEndStatus,
+  actor: AuditActor
+): Promise<RunSpan> {
+  return invoke<RunSpan>("end_span", { spanId, status, actor });
+}
+
+/** A run's spans in start order, for a timeline view */
+export async function getRunSpans(runId: string): Promise<RunSpan[]> {
+  return invoke<RunSpan[]>("get_run_spans", { runId });
+}
+
+/** Follows run cancellations so agent work on a run can stop */
+export async function subscribeRunCancellations(
+  onCancel: (cancellation: RunCancellation) => void
+): Promise<UnlistenFn> {
+  return listen<RunCancellation>("run://cancelled", ({ payload }) => onCancel(payload));
+}
+
+/** Runs for a workstream, oldest first; `events` is always empty. */
+export async function getWorkstreamRuns(workstreamId: string): Promise<RunRecord[]> {
+  return invoke<RunRecord[]>("get_workstream_runs", { workstreamId });
+}
+
+export interface ProjectStats {
+  project_id: string;
+  total_runs: number;
+  runs_by_status: Record<string, number>;
+  /** Summed over all runs; `duration` covers finished runs only */
+  totals: RunSummary;
+  /** Seconds, over finished runs */
+  mean_duration: number | null;
+  median_duration: number | null;
+}
+
+export async function getProjectStats(projectId: string): Promise<ProjectStats> {
+  return invoke<ProjectStats>("get_project_stats", { projectId });
+}
+
+export interface RunQuery {
+  project_id?: string;
+  workstream_id?: string;
+  phase?: string;
+  status?: string;
+}
+
+export async function searchRuns(query: RunQuery): Promise<RunRecord[]> {
+  return invoke<RunRecord[]>("search_runs", { query });
+}
+
+export interface ImportReport {
+  imported: number;
+  skipped_duplicates: number;
+  skipped_invalid: number;
+  runs_created: number;
+}
+
+export type AuditBackendKind = "memory" | "jsonl";
+
+export interface MigrationReport {
+  from: AuditBackendKind;
+  to: AuditBackendKind;
+  events_migrated: number;
+  runs_migrated: number;
+  verified: boolean;
+}
+
+/** Every project's policy must grant `requestedByRole` `audit.migrate`. */
+export async function migrateAuditBackend(
+  requestedByRole: string,
+  backend: AuditBackendKind
+): Promise<MigrationReport> {
+  return invoke<MigrationReport>("migrate_audit_backend", {
+    requestedByRole,
+    backend,
+  });
+}
+
+export async function getAuditBackend(): Promise<AuditBackendKind> {
+  return invoke<AuditBackendKind>("get_audit_backend");
+}
+
+export interface ChainVerification {
+  events_checked: number;
+  /** Index of the first event whose hash or back-link doesn't match, or where an unreadable line was skipped */
+  first_broken: number | null;
+}
+
+export async function verifyAuditChain(): Promise<ChainVerification> {
+  return invoke<ChainVerification>("verify_audit_chain");
+}
+
+export async function getEventHistogram(
+  projectId: string,
+  options: { from?: string; to?: string; eventTypePrefix?: string } = {}
+): Promise<Record<string, number>> {
+  return invoke<Record<string, number>>("get_event_histogram", { projectId, ...options });
+}
+
+export async function importAuditLog(
+  jsonl: string,
+  verify?: boolean,
+  merge?: boolean
+): Promise<ImportReport> {
+  return invoke<ImportReport>("import_audit_log", { jsonl, verify, merge });
+}
+
+export interface DedupReport {
+  examined: number;
+  removed: number;
+  removed_event_ids: string[];
+  tombstone_event_id: string;
+}
+
+export async function dedupAuditLog(
+  projectId: string,
+  requestedByRole: string
+): Promise<DedupReport> {
+  return invoke<DedupReport>("dedup_audit_log", { projectId, requestedByRole });
+}
+
+/**
+ * Removes events past each project's `audit_retention_days` now instead of at
+ * the next hourly prune; resolves to the number removed.
+ */
+export async function pruneAuditLog(): Promise<number> {
+  return invoke<number>("prune_audit_log");
+}
+
+export type ExportFormat = "jsonl" | "markdown";
+
+export interface SignedExport {
+  run_id: string;
+  format: ExportFormat;
+  content: string;
+  signature: string;
+  public_key: string;
+  signed_at: string;
+}
+
+export async function exportSignedRun(
+  runId: string,
+  format?: ExportFormat
+): Promise<SignedExport> {
+  return invoke<SignedExport>("export_signed_run", { runId, format });
+}
+
+/** An event and the events that name it as their parent. */
+export interface EventNode {
+  event: AuditEvent;
+  children: EventNode[];
+}
+
+export async function getEventTree(runId: string): Promise<EventNode[]> {
+  return invoke<EventNode[]>("get_event_tree", { runId });
+}
+
+export type ReportFormat = "json" | "markdown";
+
+/**
+ * Renders a run's full audit trail (record, ordered events, summary, chain status).
+ * With `outputPath`, e.g. from the dialog plugin's `save()`, the report is also written there.
+ */
+export async function exportRunReport(
+  runId: string,
+  format?: ReportFormat,
+  outputPath?: string
+): Promise<string> {
+  return invoke<string>("export_run_report", { runId, format, outputPath });
+}
+
+export async function verifyExportedRun(
+  content: string,
+  signature: string,
+  publicKey: string
+): Promise<boolean> {
+  return invoke<boolean>("verify_exported_run", { content, signature, publicKey });
+}
+
+export async function getAuditSigningKey(): Promise<string> {
+  return invoke<string>("get_audit_signing_key");
+}
+
+export interface EmissionConfig {
+  batch_window_ms: number;
+  priority_event_types: string[];
+}
+
+/** Payload of the `audit://batch` event; single priority events arrive as `audit://event`. */
+export interface AuditBatch {
+  project_id: string;
+  events: AuditEvent[];
+}
+
+/**
+ * Follows a project's audit events as they're recorded, whether they arrive
+ * batched or as a priority event. Resolves to a function that stops listening.
+ */
+export async function subscribeAuditEvents(
+  projectId: string,
+  onEvents: (events: AuditEvent[]) => void
+): Promise<UnlistenFn> {
+  const unlistenBatch = await listen<AuditBatch>("audit://batch", ({ payload }) => {
+    if (payload.project_id === projectId) onEvents(payload.events);
+  });
+  const unlistenEvent = await listen<AuditEvent>("audit://event", ({ payload }) => {
+    if (payload.project_id === projectId) onEvents([payload]);
+  });
+  return () => {
+    unlistenBatch();
+    unlistenEvent();
+  };
+}
+
+export async function getAuditEmissionConfig(projectId: string): Promise<EmissionConfig> {
+  return invoke<EmissionConfig>("get_audit_emission_config", { projectId });
+}
+
+export async function configureAuditEmission(
+  projectId: string,
+  config: EmissionConfig
+): Promise<void> {
+  return invoke("configure_audit_emission", { projectId, config });
+}
+
+// ── Config ──
+
+export interface ProjectConfig {
+  ignore_patterns: string[];
+  template_levels: LevelRule[];
+  emission: EmissionConfig;
+  /** Days audit events are kept before pruning; null keeps them forever */
+  audit_retention_days: number | null;
+  /** Longest message `sendMessage` accepts, in characters; null uses the default of 100,000 */
+  max_message_length: number | null;
+}
+
+export async function getConfig(projectId: string): Promise<ProjectConfig> {
+  return invoke<ProjectConfig>("get_config", { projectId });
+}
+
+/** Applies a JSON merge patch; `null` values reset a key to its default. */
+export async function updateConfig(
+  projectId: string,
+  patch: Partial<Record<keyof ProjectConfig, unknown>>
+): Promise<ProjectConfig> {
+  return invoke<ProjectConfig>("update_config", { projectId, patch });
 }
 
 // ── Policy ──
@@ -149,8 +818,274 @@ export async function getPolicy(projectId: string): Promise<unknown> {
   return invoke("get_policy", { projectId });
 }
 
-export async function updatePolicy(projectId: string, policy: unknown): Promise<void> {
-  return invoke("update_policy", { projectId, policy });
+/** A policy field that failed validation, by its dotted path. */
+export interface PolicyValidationError {
+  field: string;
+  message: string;
+}
+
+/**
+ * Saves a new policy version; resolves to the stored policy with its bumped `version`.
+ * `expectedVersion`, if given, is the version the edit was based on (0 if there was none); if
+ * another write got there first this rejects with `conflict` and
+ * `{ expected_version, current_version }` in `details`, and the caller should re-read and
+ * re-apply. Without it the write always goes through.
+ * Rejects with `invalid_policy` and a `PolicyValidationError[]` in `details` if validation fails.
+ */
+export async function updatePolicy(
+  projectId: string,
+  policy: unknown,
+  expectedVersion?: number,
+): Promise<unknown> {
+  return invoke("update_policy", { projectId, policy, expectedVersion });
+}
+
+/**
+ * Loads a project's policy from a `.json`, `.yaml`, or `.yml` file and saves it as a new version.
+ * A parse error rejects with `invalid_policy` and `{ line, column }` in `details`; a validation
+ * failure with `invalid_policy` and a `PolicyValidationError[]`.
+ */
+export async function importPolicyFromFile(
+  projectId: string,
+  path: string,
+  expectedVersion?: number
+): Promise<unknown> {
+  return invoke("import_policy_from_file", { projectId, path, expectedVersion });
+}
+
+/** Writes a project's current policy to a `.json`, `.yaml`, or `.yml` file */
+export async function exportPolicyToFile(projectId: string, path: string): Promise<void> {
+  return invoke("export_policy_to_file", { projectId, path });
+}
+
+export interface PolicyChange {
+  /** Dotted field path with `[i]` for list items, e.g. `repositories.write_scopes[0].allowed_paths` */
+  path: string;
+  kind: "added" | "removed" | "modified";
+  old_value: unknown | null;
+  new_value: unknown | null;
+}
+
+export interface PolicyDiff {
+  from_version: number;
+  to_version: number;
+  changes: PolicyChange[];
+}
+
+export async function diffPolicy(
+  projectId: string,
+  fromVersion: number,
+  toVersion: number
+): Promise<PolicyDiff> {
+  return invoke<PolicyDiff>("diff_policy", { projectId, fromVersion, toVersion });
+}
+
+export interface RedactionResult {
+  redacted: string;
+  hits: { pattern: string; count: number }[];
+  matches: { pattern: string; start: number; end: number; matched: string }[];
+}
+
+export async function previewRedaction(
+  projectId: string,
+  sample: string
+): Promise<RedactionResult> {
+  return invoke<RedactionResult>("preview_redaction", { projectId, sample });
+}
+
+export async function redactText(projectId: string, text: string): Promise<string> {
+  return invoke<string>("redact_text", { projectId, text });
+}
+
+export interface PolicyDecision {
+  allowed: boolean;
+  reason: string;
+}
+
+/** Whether data of `classification` (or the policy default) may go to `provider`. */
+export async function checkProviderAccess(
+  projectId: string,
+  provider: string,
+  classification?: string
+): Promise<PolicyDecision> {
+  return invoke<PolicyDecision>("check_provider_access", { projectId, provider, classification });
+}
+
+export interface GateEvaluation {
+  passed: boolean;
+  /** Required gates that failed or had no result */
+  failed_gates: string[];
+  failed_optional_gates: string[];
+}
+
+export interface MergeEvaluation {
+  allowed: boolean;
+  reason: string;
+  requires_approval: boolean;
+  approvers: string[];
+  gates: GateEvaluation;
+}
+
+/** Decides a merge to main from merge gate results; recorded as `gate.evaluated`. */
+export async function evaluateMerge(
+  projectId: string,
+  agentRole: string,
+  gateResults: Record<string, boolean>,
+  workstreamId?: string
+): Promise<MergeEvaluation> {
+  return invoke<MergeEvaluation>("evaluate_merge", {
+    projectId,
+    workstreamId,
+    agentRole,
+    gateResults,
+  });
+}
+
+export type GateState = "passed" | "failed" | "pending";
+
+export interface GateStatus {
+  id: string;
+  name: string;
+  gate_type: string;
+  required: boolean;
+  state: GateState;
+  /** `null` while pending */
+  evaluated_at: string | null;
+}
+
+/**
+ * Gates guarding a phase (merge gates for build/test, deploy gates for deploy) with their
+ * latest result from the audit log; pass `workstreamId` to count only that workstream's results.
+ */
+export async function gatesForPhase(
+  projectId: string,
+  phase: string,
+  workstreamId?: string
+): Promise<GateStatus[]> {
+  return invoke<GateStatus[]>("gates_for_phase", { projectId, phase, workstreamId });
+}
+
+export interface CanaryStep {
+  index: number;
+  /** Fraction of traffic on the new release during this step */
+  traffic: number;
+  /** Seconds after the rollout starts */
+  offset_seconds: number;
+  starts_at: string;
+  metrics: string[];
+}
+
+export interface CanaryPlan {
+  environment: string;
+  starts_at: string;
+  steps: CanaryStep[];
+}
+
+/** Canary rollout plan starting now; rejects with `invalid_policy` if the steps are unusable. */
+export async function planCanary(projectId: string, environment: string): Promise<CanaryPlan> {
+  return invoke<CanaryPlan>("plan_canary", { projectId, environment });
+}
+
+export interface RollbackDecision {
+  metric: string;
+  value: number;
+  threshold: number;
+  window: number;
+  /** False when policy wants a person to approve the rollback */
+  automatic: boolean;
+  reason: string;
+}
+
+/** The first rollback trigger the metrics fire, or null; recorded as `deploy.rollback_triggered`. */
+export async function evaluateRollback(
+  projectId: string,
+  environment: string,
+  metrics: Record<string, number>,
+  workstreamId?: string
+): Promise<RollbackDecision | null> {
+  return invoke<RollbackDecision | null>("evaluate_rollback", {
+    projectId,
+    workstreamId,
+    environment,
+    metrics,
+  });
+}
+
+/** Whether the role may deploy to `environment`; recorded as `deploy.permission_checked`. */
+export async function canDeploy(
+  projectId: string,
+  agentRole: string,
+  environment: string,
+  workstreamId?: string
+): Promise<PolicyDecision> {
+  return invoke<PolicyDecision>("can_deploy", {
+    projectId,
+    workstreamId,
+    agentRole,
+    environment,
+  });
+}
+
+/**
+ * Evaluates a tool permission; the decision is recorded as a `policy.decision` event.
+ * Roles are evaluated together with every role they inherit from, and a rule only
+ * applies if its `operations` include `operation` (or `"*"`).
+ */
+export async function evaluatePermission(
+  projectId: string,
+  agentRoles: string | string[],
+  action: string,
+  operation: string,
+  resource: string,
+  workstreamId?: string
+): Promise<PolicyDecision> {
+  return invoke<PolicyDecision>("evaluate_permission", {
+    projectId,
+    workstreamId,
+    agentRoles: Array.isArray(agentRoles) ? agentRoles : [agentRoles],
+    action,
+    operation,
+    resource,
+  });
+}
+
+export interface EvaluatedRule {
+  tool_id: string;
+  operations: string[];
+  resource_scope: string[];
+  deny: boolean;
+  /** Role the rule came from; null for a default permission */
+  role: string | null;
+  tool_matched: boolean;
+  operation_matched: boolean;
+  scope_matched: boolean;
+  /** Whether this rule decided the outcome */
+  decisive: boolean;
+}
+
+export interface PermissionExplanation {
+  allowed: boolean;
+  reason: string;
+  /** The agent's roles and every role they inherit from */
+  roles: string[];
+  rules: EvaluatedRule[];
+}
+
+/** Lists every rule considered for an action, in evaluation order. Nothing is recorded. */
+export async function explainPermission(
+  projectId: string,
+  agentRoles: string | string[],
+  action: string,
+  operation: string,
+  resource: string
+): Promise<PermissionExplanation> {
+  return invoke<PermissionExplanation>("explain_permission", {
+    projectId,
+    agentRoles: Array.isArray(agentRoles) ? agentRoles : [agentRoles],
+    action,
+    operation,
+    resource,
+  });
 }
 
 // ── Vault ──
@@ -161,38 +1096,515 @@ export interface VaultNote {
   content: string;
   frontmatter: Record<string, unknown>;
   last_modified: string;
+  lossy: boolean;
+  /** SHA-256 of the file's bytes; pass back as `expectedHash` when writing */
+  content_hash: string;
 }
 
 export interface VaultNoteEntry {
   path: string;
   name: string;
   last_modified: string;
+  is_dir: boolean;
 }
 
 export async function readVaultNote(
   projectId: string,
-  notePath: string
+  notePath: string,
+  readerRole?: string,
+  lossy?: boolean
 ): Promise<VaultNote> {
-  return invoke<VaultNote>("read_vault_note", { projectId, notePath });
+  return invoke<VaultNote>("read_vault_note", { projectId, notePath, readerRole, lossy });
+}
+
+export interface TocEntry {
+  level: number;
+  text: string;
+  /** `id` of the rendered heading element */
+  anchor: string;
+}
+
+export interface RenderedNote {
+  path: string;
+  name: string;
+  /**
+   * Sanitized HTML. Wikilinks are `<a class="wikilink" data-note="path">` anchors, with
+   * `data-heading` when they point at a heading and a `broken` class when unresolved.
+   */
+  html: string;
+  frontmatter: Record<string, unknown>;
+  toc: TocEntry[];
+}
+
+/** Renders a note to sanitized HTML for preview, with its headings as a table of contents */
+export async function renderNote(
+  projectId: string,
+  notePath: string,
+  readerRole?: string
+): Promise<RenderedNote> {
+  return invoke<RenderedNote>("render_note", { projectId, notePath, readerRole });
+}
+
+/** Open a note in Obsidian; resolves to the obsidian:// link that was opened */
+export async function openNoteInObsidian(projectId: string, notePath: string): Promise<string> {
+  return invoke<string>("open_note_in_obsidian", { projectId, notePath });
+}
+
+/** Exactly one of `note` and `error` is set */
+export interface NoteReadResult {
+  path: string;
+  note: VaultNote | null;
+  error: CommandError | null;
 }
 
+/** Read many notes in one call; results keep the order of notePaths */
+export async function readNotes(
+  projectId: string,
+  notePaths: string[],
+  readerRole?: string,
+  lossy?: boolean
+): Promise<NoteReadResult[]> {
+  return invoke<NoteReadResult[]>("read_notes", { projectId, notePaths, readerRole, lossy });
+}
+
+/**
+ * Pass the `content_hash` (or `last_modified`) from the last read as `expectedHash`
+ * (or `expectedLastModified`) to reject the write with a `conflict` error if the note
+ * was changed on disk since. The hash is the more reliable of the two across syncs.
+ */
 export async function writeVaultNote(
   projectId: string,
   notePath: string,
   frontmatter: Record<string, unknown>,
-  content: string
+  content: string,
+  writerRole?: string,
+  expectedLastModified?: string,
+  expectedHash?: string
 ): Promise<void> {
   return invoke("write_vault_note", {
     projectId,
     notePath,
     frontmatter,
     content,
+    writerRole,
+    expectedLastModified,
+    expectedHash,
+  });
+}
+
+export interface NoteWrite {
+  path: string;
+  frontmatter?: Record<string, unknown>;
+  content: string;
+  expected_hash?: string;
+}
+
+/**
+ * Write several notes all-or-nothing. On failure the notes already written
+ * are rolled back and the error's details name the failing note's index and
+ * path.
+ */
+export async function writeNotes(
+  projectId: string,
+  notes: NoteWrite[],
+  writerRole?: string
+): Promise<void> {
+  return invoke("write_notes", { projectId, notes, writerRole });
+}
+
+/**
+ * Set and remove frontmatter keys without touching the note body; resolves to
+ * the note's frontmatter after the patch.
+ */
+export async function patchVaultFrontmatter(
+  projectId: string,
+  notePath: string,
+  updates: Record<string, unknown>,
+  removals?: string[],
+  writerRole?: string
+): Promise<Record<string, unknown>> {
+  return invoke<Record<string, unknown>>("patch_vault_frontmatter", {
+    projectId,
+    notePath,
+    updates,
+    removals,
+    writerRole,
+  });
+}
+
+/** Moves a note into the vault's `.trash/`; resolves to its trash path. */
+export async function deleteVaultNote(
+  projectId: string,
+  notePath: string,
+  writerRole?: string
+): Promise<string> {
+  return invoke<string>("delete_vault_note", { projectId, notePath, writerRole });
+}
+
+/** Moves a trashed note back; resolves to the path it was restored to. */
+export async function restoreVaultNote(
+  projectId: string,
+  trashPath: string,
+  writerRole?: string
+): Promise<string> {
+  return invoke<string>("restore_vault_note", { projectId, trashPath, writerRole });
+}
+
+export interface MoveReport {
+  from: string;
+  to: string;
+  /** Notes whose links were rewritten to the new path */
+  updated: string[];
+}
+
+/** Moves or renames a note and repoints wikilinks to it; fails with `note_exists` unless `overwrite`. */
+export async function moveVaultNote(
+  projectId: string,
+  fromPath: string,
+  toPath: string,
+  overwrite?: boolean,
+  writerRole?: string
+): Promise<MoveReport> {
+  return invoke<MoveReport>("move_vault_note", {
+    projectId,
+    fromPath,
+    toPath,
+    overwrite,
+    writerRole,
   });
 }
 
+/**
+ * Directories come first at each level (followed by their contents when
+ * recursive), then files. Defaults: recursive, `.md` notes only.
+ */
 export async function listVaultNotes(
+  projectId: string,
+  directory: string,
+  recursive?: boolean,
+  includeNonMd?: boolean
+): Promise<VaultNoteEntry[]> {
+  return invoke<VaultNoteEntry[]>("list_vault_notes", {
+    projectId,
+    directory,
+    recursive,
+    includeNonMd,
+  });
+}
+
+export async function countVaultNotes(
   projectId: string,
   directory: string
+): Promise<number> {
+  return invoke<number>("count_vault_notes", { projectId, directory });
+}
+
+export interface SnapshotInfo {
+  id: string;
+  label: string | null;
+  created_at: string;
+  note_count: number;
+}
+
+export interface NoteDiff {
+  path: string;
+  lines_added: number;
+  lines_removed: number;
+}
+
+export interface VaultDiff {
+  snapshot_id: string;
+  added: NoteDiff[];
+  deleted: NoteDiff[];
+  modified: NoteDiff[];
+}
+
+export async function createVaultSnapshot(
+  projectId: string,
+  label?: string
+): Promise<SnapshotInfo> {
+  return invoke<SnapshotInfo>("create_vault_snapshot", { projectId, label });
+}
+
+export async function listVaultSnapshots(projectId: string): Promise<SnapshotInfo[]> {
+  return invoke<SnapshotInfo[]>("list_vault_snapshots", { projectId });
+}
+
+export async function diffVaultSnapshot(
+  projectId: string,
+  snapshotId: string
+): Promise<VaultDiff> {
+  return invoke<VaultDiff>("diff_vault_snapshot", { projectId, snapshotId });
+}
+
+export interface TemplateInfo {
+  name: string;
+  path: string;
+  variables: string[];
+  level: number | null;
+  duplicates: string[];
+}
+
+export async function listVaultTemplates(projectId: string): Promise<TemplateInfo[]> {
+  return invoke<TemplateInfo[]>("list_vault_templates", { projectId });
+}
+
+export interface WikiLink {
+  target: string;
+  heading: string | null;
+  alias: string | null;
+  embed: boolean;
+  line: number;
+}
+
+export interface NoteLinks {
+  path: string;
+  links: (WikiLink & { path: string })[];
+  embeds: (WikiLink & { path: string })[];
+  external: string[];
+  unresolved: WikiLink[];
+}
+
+export async function getNoteLinks(
+  projectId: string,
+  notePath: string,
+  readerRole?: string
+): Promise<NoteLinks> {
+  return invoke<NoteLinks>("get_note_links", { projectId, notePath, readerRole });
+}
+
+export type ReferenceMatch = "path" | "basename";
+
+export interface Reference {
+  path: string;
+  line: number;
+  kind: "wikilink" | "embed" | "markdown" | "path";
+  target: string;
+}
+
+export async function findReferences(
+  projectId: string,
+  targetPath: string,
+  matchBy?: ReferenceMatch,
+  readerRole?: string
+): Promise<Reference[]> {
+  return invoke<Reference[]>("find_references", { projectId, targetPath, matchBy, readerRole });
+}
+
+/** Notes whose wikilinks or embeds resolve to `notePath`. */
+export async function getBacklinks(
+  projectId: string,
+  notePath: string,
+  readerRole?: string
+): Promise<VaultNoteEntry[]> {
+  return invoke<VaultNoteEntry[]>("get_backlinks", { projectId, notePath, readerRole });
+}
+
+export interface BrokenLink {
+  source: string;
+  target: string;
+  line: number;
+}
+
+export async function getBrokenLinks(projectId: string, readerRole?: string): Promise<BrokenLink[]> {
+  return invoke<BrokenLink[]>("get_broken_links", { projectId, readerRole });
+}
+
+/** Notes tagged `tag` (case-insensitive); a parent tag also matches nested tags. */
+export async function listNotesByTag(
+  projectId: string,
+  tag: string,
+  readerRole?: string
 ): Promise<VaultNoteEntry[]> {
-  return invoke<VaultNoteEntry[]>("list_vault_notes", { projectId, directory });
+  return invoke<VaultNoteEntry[]>("list_notes_by_tag", { projectId, tag, readerRole });
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
+}
+
+export async function getAllTags(projectId: string): Promise<TagCount[]> {
+  return invoke<TagCount[]>("all_tags", { projectId });
+}
+
+export interface VaultChange {
+  project_id: string;
+  /** Vault-relative, `/`-separated */
+  path: string;
+  kind: "created" | "modified" | "deleted";
+}
+
+/**
+ * Follows changes to a project's vault made outside Nebula, coalesced over a
+ * short window. Resolves to a function that stops listening.
+ */
+export async function subscribeVaultChanges(
+  projectId: string,
+  onChange: (change: VaultChange) => void
+): Promise<UnlistenFn> {
+  return listen<VaultChange>("vault://changed", ({ payload }) => {
+    if (payload.project_id === projectId) onChange(payload);
+  });
+}
+
+export interface SearchOptions {
+  scope?: "all" | "title" | "body";
+  /** Only search notes with this frontmatter key... */
+  frontmatter_key?: string;
+  /** ...whose value equals this (or, for a list, contains it) */
+  frontmatter_value?: string;
+  case_sensitive?: boolean;
+  limit?: number;
+}
+
+export interface VaultSearchResult {
+  path: string;
+  /** 1-based line of a body match; 0 for a title match */
+  line: number;
+  snippet: string;
+}
+
+export async function searchVault(
+  projectId: string,
+  query: string,
+  options?: SearchOptions,
+  readerRole?: string
+): Promise<VaultSearchResult[]> {
+  return invoke<VaultSearchResult[]>("search_vault", { projectId, query, options, readerRole });
+}
+
+export interface RankedSearchResult {
+  path: string;
+  title: string;
+  score: number;
+  snippet: string;
+  /** [start, end) character ranges of query terms within snippet */
+  highlights: [number, number][];
+}
+
+export async function searchVaultRanked(
+  projectId: string,
+  query: string,
+  limit?: number,
+  readerRole?: string
+): Promise<RankedSearchResult[]> {
+  return invoke<RankedSearchResult[]>("search_vault_ranked", {
+    projectId,
+    query,
+    limit,
+    readerRole,
+  });
+}
+
+/** Rebuild the vault's search index; resolves to the number of notes indexed */
+export async function reindexVault(projectId: string): Promise<number> {
+  return invoke<number>("reindex_vault", { projectId });
+}
+
+export interface CacheStats {
+  notes: number;
+  /** Backlinks, tag memberships, or term postings held */
+  entries: number;
+  built_at: string;
+  updated_at: string | null;
+}
+
+/** Cached vault indexes; an index not built yet is null */
+export interface VaultCacheStats {
+  project_id: string;
+  links: CacheStats | null;
+  tags: CacheStats | null;
+  search: CacheStats | null;
+}
+
+export async function getVaultCacheStats(projectId: string): Promise<VaultCacheStats> {
+  return invoke<VaultCacheStats>("vault_cache_stats", { projectId });
+}
+
+export type HealthIssueKind =
+  | "malformed_frontmatter"
+  | "template_violation"
+  | "broken_link"
+  | "duplicate_name"
+  | "orphan";
+
+export type HealthSeverity = "error" | "warning" | "info";
+
+export interface HealthIssue {
+  path: string;
+  kind: HealthIssueKind;
+  severity: HealthSeverity;
+  message: string;
+}
+
+export interface VaultHealthReport {
+  project_id: string;
+  checked_at: string;
+  notes_checked: number;
+  /** Sorted by note path */
+  issues: HealthIssue[];
+}
+
+/** Reports problems in the vault without changing it. */
+export async function checkVaultHealth(projectId: string): Promise<VaultHealthReport> {
+  return invoke<VaultHealthReport>("check_vault_health", { projectId });
+}
+
+export interface AttachmentInfo {
+  /** Vault-relative, under `attachments/` */
+  path: string;
+  mime_type: string;
+  size: number;
+  last_modified: string;
+}
+
+/** Stores a file under the vault's `attachments/` folder, sent as raw bytes. */
+export async function writeAttachment(
+  projectId: string,
+  path: string,
+  data: Uint8Array | ArrayBuffer
+): Promise<AttachmentInfo> {
+  return invoke<AttachmentInfo>("write_attachment", data, {
+    headers: {
+      "project-id": encodeURIComponent(projectId),
+      "attachment-path": encodeURIComponent(path),
+    },
+  });
+}
+
+/** Reads a file under `attachments/` as a Blob typed with its detected MIME type. */
+export async function readAttachment(
+  projectId: string,
+  path: string
+): Promise<{ info: AttachmentInfo; blob: Blob }> {
+  const [info, data] = await Promise.all([
+    getAttachmentInfo(projectId, path),
+    invoke<ArrayBuffer>("read_attachment", { projectId, path }),
+  ]);
+  return { info, blob: new Blob([data], { type: info.mime_type }) };
+}
+
+export async function getAttachmentInfo(projectId: string, path: string): Promise<AttachmentInfo> {
+  return invoke<AttachmentInfo>("get_attachment_info", { projectId, path });
+}
+
+export interface LevelRule {
+  pattern: string;
+  level: number;
+}
+
+export async function getTemplateLevels(projectId: string): Promise<LevelRule[]> {
+  return invoke<LevelRule[]>("get_template_levels", { projectId });
+}
+
+export async function setTemplateLevels(projectId: string, rules: LevelRule[]): Promise<void> {
+  return invoke("set_template_levels", { projectId, rules });
+}
+
+export async function resolveTemplateLevel(
+  projectId: string,
+  notePath: string
+): Promise<number | null> {
+  return invoke<number | null>("resolve_template_level", { projectId, notePath });
 }
@@ -105,7 +105,7 @@ export type AuditPayload =
 
 export interface RunPayload {
   kind: "run";
-  status: "started" | "completed" | "failed";
+  status: "started" | "completed" | "failed" | "cancelled";
   input?: string;
   output?: string;
   error?: string;
@@ -253,7 +253,7 @@ export interface RunRecord {
   workstreamId: string;
   startedAt: string;
   completedAt?: string;
-  status: "running" | "completed" | "failed";
+  status: "running" | "completed" | "failed" | "cancelled";
   userRequest: string;
   events: AuditEvent[];
   summary?: RunSummary;
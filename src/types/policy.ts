@@ -207,6 +207,9 @@ export interface ToolPermissionPolicy {
 
   /** Per-agent-role tool permissions */
   rolePermissions: Record<AgentRole, ToolPermission[]>;
+
+  /** Parent roles per role; a role gets every permission of its ancestors */
+  roleInheritance?: Record<string, string[]>;
 }
 
 export interface ToolPermission {
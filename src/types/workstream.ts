@@ -50,7 +50,9 @@ export type WorkstreamStatus =
   | "deploying"
   | "completed"
   | "failed"
-  | "paused";
+  | "cancelled"
+  | "paused"
+  | "archived";
 
 export type WorkPhase =
   | "design"